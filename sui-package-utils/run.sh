@@ -1,4 +1,5 @@
 #!/bin/bash
+set -euo pipefail
 set -x
 
 mkdir -p out
@@ -14,6 +15,7 @@ cargo run --bin sui-packages-graphql-poller -- \
       --move-decompiler-path ~/ML/sui-packages/third-party/move-decompiler/move-decompiler-linux-x86_64 \
       --initial-checkpoint "$MAX_CHECKPOINT_BEFORE" \
       --packages-dir "out/packages" \
-      --max-checkpoint-seen-file "out/action_helper.json"
+      --max-checkpoint-seen-file "out/action_helper.json" \
+      --run-report-file "out/run_report.json" \
+      --events
 
-      
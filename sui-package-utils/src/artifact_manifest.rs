@@ -0,0 +1,114 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Error as IOError;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+// sidecar file in every package directory recording how each artifact was generated
+pub const ARTIFACT_MANIFEST_FILE: &str = "manifest.json";
+// bump when the shape of manifest.json changes; see store_layout.rs
+pub const ARTIFACT_MANIFEST_SCHEMA_VERSION: u32 = 1;
+
+#[derive(Debug, Default, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ArtifactManifest {
+    artifacts: BTreeMap<String, ArtifactStamp>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ArtifactStamp {
+    pub generator_version: u32,
+    pub input_hash: String,
+}
+
+impl ArtifactStamp {
+    pub fn new(generator_version: u32, input: &[u8]) -> Self {
+        Self {
+            generator_version,
+            input_hash: hash_bytes(input),
+        }
+    }
+}
+
+impl ArtifactManifest {
+    // a missing or unreadable manifest is treated as empty, so every artifact gets re-checked
+    pub fn load(package_dir: &str) -> Self {
+        fs::read_to_string(format!("{}/{}", package_dir, ARTIFACT_MANIFEST_FILE))
+            .ok()
+            .and_then(|manifest_json| serde_json::from_str(&manifest_json).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, package_dir: &str) -> Result<(), IOError> {
+        fs::write(
+            format!("{}/{}", package_dir, ARTIFACT_MANIFEST_FILE),
+            serde_json::to_string_pretty(self)?,
+        )
+    }
+
+    pub fn get_artifacts(&self) -> &BTreeMap<String, ArtifactStamp> {
+        &self.artifacts
+    }
+
+    pub fn is_up_to_date(&self, artifact: &str, stamp: &ArtifactStamp) -> bool {
+        self.artifacts.get(artifact) == Some(stamp)
+    }
+
+    // returns true if the stamp changed
+    pub fn stamp(&mut self, artifact: &str, stamp: ArtifactStamp) -> bool {
+        self.artifacts.insert(artifact.to_string(), stamp.clone()) != Some(stamp)
+    }
+}
+
+pub fn hash_bytes(bytes: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::test_dir;
+
+    #[test]
+    fn up_to_date_only_with_same_generator_and_input() {
+        let mut manifest = ArtifactManifest::default();
+        let stamp = ArtifactStamp::new(1, b"bcs");
+        assert!(!manifest.is_up_to_date("bcs.json", &stamp));
+
+        assert!(manifest.stamp("bcs.json", stamp.clone()));
+        assert!(manifest.is_up_to_date("bcs.json", &stamp));
+        assert!(!manifest.is_up_to_date("bcs.json", &ArtifactStamp::new(2, b"bcs")));
+        assert!(!manifest.is_up_to_date("bcs.json", &ArtifactStamp::new(1, b"other")));
+        assert!(!manifest.is_up_to_date("call_graph.json", &stamp));
+
+        // stamping the same stamp again is not a change
+        assert!(!manifest.stamp("bcs.json", stamp));
+        assert!(manifest.stamp("bcs.json", ArtifactStamp::new(2, b"bcs")));
+    }
+
+    #[test]
+    fn save_and_load() {
+        let dir = test_dir("artifact-manifest");
+        let package_dir = dir.to_str().unwrap();
+        // missing manifests load as empty
+        assert!(ArtifactManifest::load(package_dir)
+            .get_artifacts()
+            .is_empty());
+
+        let mut manifest = ArtifactManifest::default();
+        manifest.stamp("bcs.json", ArtifactStamp::new(1, b"bcs"));
+        manifest.save(package_dir).unwrap();
+        let loaded = ArtifactManifest::load(package_dir);
+        assert_eq!(loaded.get_artifacts(), manifest.get_artifacts());
+
+        // so do unreadable ones
+        fs::write(dir.join(ARTIFACT_MANIFEST_FILE), "{").unwrap();
+        assert!(ArtifactManifest::load(package_dir)
+            .get_artifacts()
+            .is_empty());
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,558 @@
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::bcs_json::{BcsJsonMismatch, BcsJsonSchema};
+use crate::common_types::MovePackageWithMetadata;
+use crate::metadata::PackageMetadata;
+use crate::package_artifacts::PACKAGE_ARTIFACTS;
+use crate::package_id_io::PackagesDir;
+use crate::package_saver::{save_package, SaveArgs};
+
+// misplaced package directories whose content was rewritten to the expected directory are moved
+// here instead of being deleted. Package directories start with 0x, so this one is never listed
+pub const QUARANTINE_DIR: &str = "quarantine";
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase", tag = "kind")]
+pub enum AuditIssue {
+    UnreadableBcsJson {
+        message: String,
+    },
+    UnreadableMetadata {
+        message: String,
+    },
+    DirectoryMismatch {
+        expected_dir: String,
+    },
+    MissingBytecodeModule {
+        module: String,
+    },
+    UnexpectedBytecodeModule {
+        module: String,
+    },
+    BytecodeMismatch {
+        module: String,
+    },
+    MissingDecompiledModule {
+        module: String,
+    },
+    EmptyDecompiledModule {
+        module: String,
+    },
+    MetadataMismatch {
+        field: String,
+        metadata_value: String,
+        bcs_value: String,
+    },
+    InvalidBcsJson {
+        mismatches: Vec<BcsJsonMismatch>,
+    },
+    RoundTripFailure {
+        message: String,
+    },
+}
+
+impl AuditIssue {
+    // whether the issue can be fixed from the content of bcs.json and metadata.json alone
+    pub fn is_repairable(&self) -> bool {
+        match self {
+            AuditIssue::DirectoryMismatch { .. }
+            | AuditIssue::MissingBytecodeModule { .. }
+            | AuditIssue::BytecodeMismatch { .. }
+            | AuditIssue::MissingDecompiledModule { .. }
+            | AuditIssue::EmptyDecompiledModule { .. }
+            | AuditIssue::MetadataMismatch { .. } => true,
+            AuditIssue::UnreadableBcsJson { .. }
+            | AuditIssue::UnreadableMetadata { .. }
+            | AuditIssue::UnexpectedBytecodeModule { .. }
+            | AuditIssue::RoundTripFailure { .. } => false,
+            AuditIssue::InvalidBcsJson { mismatches } => {
+                mismatches.iter().all(|mismatch| mismatch.is_derived())
+            }
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PackageAuditReport {
+    pub path: String,
+    pub package_id: Option<String>,
+    pub issues: Vec<AuditIssue>,
+    pub repaired: bool,
+    pub repair_error: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditReport {
+    pub packages_checked: u64,
+    pub packages_with_issues: u64,
+    pub packages_repaired: u64,
+    pub packages: Vec<PackageAuditReport>,
+}
+
+impl AuditReport {
+    pub fn add(&mut self, package_report: PackageAuditReport) {
+        self.packages_checked += 1;
+        if package_report.issues.is_empty() {
+            return;
+        }
+        self.packages_with_issues += 1;
+        if package_report.repaired {
+            self.packages_repaired += 1;
+        }
+        self.packages.push(package_report);
+    }
+}
+
+// bcs_json_schema_version is the bcs.json schema version recorded for the store
+pub fn audit_package(
+    packages_dir: &PackagesDir,
+    package_path: &Path,
+    bcs_json_schema_version: u32,
+) -> PackageAuditReport {
+    let mut report = PackageAuditReport {
+        path: package_path.to_str().unwrap().to_string(),
+        package_id: packages_dir.get_package_id_from_dir(package_path),
+        issues: Vec::new(),
+        repaired: false,
+        repair_error: None,
+    };
+
+    let bcs_json = match read_bcs_json(package_path) {
+        Ok(bcs_json) => bcs_json,
+        Err(message) => {
+            report
+                .issues
+                .push(AuditIssue::UnreadableBcsJson { message });
+            return report;
+        }
+    };
+    let mismatches = bcs_json.validate(bcs_json_schema_version);
+    let bcs_json_valid = mismatches.is_empty();
+    if !bcs_json_valid {
+        let can_build_package = mismatches.iter().all(|mismatch| {
+            !matches!(
+                mismatch,
+                BcsJsonMismatch::InvalidPackageId { .. }
+                    | BcsJsonMismatch::ModuleDecodeError { .. }
+                    | BcsJsonMismatch::ModuleDeserializeError { .. }
+            )
+        });
+        report
+            .issues
+            .push(AuditIssue::InvalidBcsJson { mismatches });
+        if !can_build_package {
+            return report;
+        }
+    }
+    let package = match bcs_json.to_move_package() {
+        Ok(package) => package,
+        Err(e) => {
+            report.issues.push(AuditIssue::UnreadableBcsJson {
+                message: e.to_string(),
+            });
+            return report;
+        }
+    };
+    let package_id = package.id().to_canonical_string(true);
+
+    // directory path must match the package id
+    let expected_dir = packages_dir.get_package_dir(&package_id);
+    if Path::new(&expected_dir) != package_path {
+        report
+            .issues
+            .push(AuditIssue::DirectoryMismatch { expected_dir });
+    }
+
+    // bytecode_modules/*.mv must match the moduleMap
+    let bytecode_dir = package_path.join("bytecode_modules");
+    let mut bytecode_files: BTreeSet<String> = list_module_files(&bytecode_dir, "mv");
+    for (module_name, module_bytes) in package.serialized_module_map() {
+        if !bytecode_files.remove(module_name) {
+            report.issues.push(AuditIssue::MissingBytecodeModule {
+                module: module_name.clone(),
+            });
+            continue;
+        }
+        let bytecode_path = bytecode_dir.join(format!("{}.mv", module_name));
+        if fs::read(&bytecode_path).ok().as_ref() != Some(module_bytes) {
+            report.issues.push(AuditIssue::BytecodeMismatch {
+                module: module_name.clone(),
+            });
+        }
+    }
+    for module_name in bytecode_files {
+        report.issues.push(AuditIssue::UnexpectedBytecodeModule {
+            module: module_name,
+        });
+    }
+
+    // every module must have a non-empty decompiled file
+    let decompiled_dir = package_path.join("decompiled_modules");
+    for module_name in package.serialized_module_map().keys() {
+        let decompiled_path = decompiled_dir.join(format!("{}.move", module_name));
+        match fs::metadata(&decompiled_path) {
+            Ok(file_metadata) if file_metadata.len() == 0 => {
+                report.issues.push(AuditIssue::EmptyDecompiledModule {
+                    module: module_name.clone(),
+                });
+            }
+            Ok(_) => {}
+            Err(_) => {
+                report.issues.push(AuditIssue::MissingDecompiledModule {
+                    module: module_name.clone(),
+                });
+            }
+        }
+    }
+
+    // metadata.json must agree with bcs.json
+    match read_metadata(package_path) {
+        Ok(metadata) => {
+            if metadata.id != package_id {
+                report.issues.push(AuditIssue::MetadataMismatch {
+                    field: "id".to_string(),
+                    metadata_value: metadata.id.clone(),
+                    bcs_value: package_id.clone(),
+                });
+            }
+            if metadata.version != bcs_json.get_version() {
+                report.issues.push(AuditIssue::MetadataMismatch {
+                    field: "version".to_string(),
+                    metadata_value: metadata.version.to_string(),
+                    bcs_value: bcs_json.get_version().to_string(),
+                });
+            }
+            let original_package_id = package.original_package_id().to_canonical_string(true);
+            if metadata.original_package_id != original_package_id {
+                report.issues.push(AuditIssue::MetadataMismatch {
+                    field: "originalPackageId".to_string(),
+                    metadata_value: metadata.original_package_id.clone(),
+                    bcs_value: original_package_id,
+                });
+            }
+        }
+        Err(message) => {
+            report
+                .issues
+                .push(AuditIssue::UnreadableMetadata { message });
+        }
+    }
+
+    // an invalid bcs.json can never re-serialize to itself, it has been reported already
+    if let (true, Some(dir_package_id)) = (bcs_json_valid, &report.package_id) {
+        if let Err(e) = packages_dir.verify_package_round_trip(dir_package_id) {
+            report.issues.push(AuditIssue::RoundTripFailure {
+                message: e.to_string(),
+            });
+        }
+    }
+    report
+}
+
+// rewrites the artifacts affected by repairable issues from bcs.json and metadata.json
+pub fn repair_package(
+    packages_dir: &PackagesDir,
+    package_path: &Path,
+    report: &PackageAuditReport,
+    move_decompiler_path: Option<&PathBuf>,
+) -> Result<bool, String> {
+    if !report.issues.iter().any(|issue| issue.is_repairable()) {
+        return Ok(false);
+    }
+    let bcs_json = read_bcs_json(package_path)?;
+    let package = bcs_json.to_move_package().map_err(|e| e.to_string())?;
+    let metadata = read_metadata(package_path)?;
+    let pkg_with_metadata = MovePackageWithMetadata {
+        package,
+        checkpoint: metadata.checkpoint,
+        transaction_digest: metadata.transaction_digest,
+        sender: metadata.sender,
+    };
+
+    let mut save_args = SaveArgs {
+        bcs: false,
+        bytecode: false,
+        call_graph: false,
+        artifacts: Vec::new(),
+        metadata: false,
+        move_code: false,
+        force: true,
+        packages_dir: packages_dir.get_prefix(),
+        move_decompiler_path: move_decompiler_path.cloned().unwrap_or_default(),
+    };
+    // the misplaced directory is moved to where it belongs, or quarantined once its content has
+    // been rewritten there when that directory already exists
+    let mut misplaced_dir_to_quarantine = None;
+    let mut moved_dir = false;
+    for issue in &report.issues {
+        match issue {
+            AuditIssue::DirectoryMismatch { expected_dir } => {
+                let expected_dir = Path::new(expected_dir);
+                if !expected_dir.exists() {
+                    if let Some(parent_dir) = expected_dir.parent() {
+                        fs::create_dir_all(parent_dir).map_err(|e| e.to_string())?;
+                    }
+                    fs::rename(package_path, expected_dir).map_err(|e| {
+                        format!(
+                            "could not move {} to {}: {}",
+                            package_path.display(),
+                            expected_dir.display(),
+                            e
+                        )
+                    })?;
+                    moved_dir = true;
+                    continue;
+                }
+                save_args.bcs = true;
+                save_args.bytecode = true;
+                save_args.call_graph = true;
+                // optional artifacts are only rewritten when the misplaced directory had them
+                save_args.artifacts = PACKAGE_ARTIFACTS
+                    .iter()
+                    .filter(|artifact| package_path.join(artifact.file_name).exists())
+                    .collect();
+                save_args.metadata = true;
+                save_args.move_code = move_decompiler_path.is_some();
+                misplaced_dir_to_quarantine = Some(package_path);
+            }
+            AuditIssue::MissingBytecodeModule { .. } | AuditIssue::BytecodeMismatch { .. } => {
+                save_args.bytecode = true;
+            }
+            AuditIssue::MissingDecompiledModule { .. }
+            | AuditIssue::EmptyDecompiledModule { .. } => {
+                if move_decompiler_path.is_some() {
+                    save_args.bytecode = true;
+                    save_args.move_code = true;
+                }
+            }
+            AuditIssue::MetadataMismatch { .. } => {
+                save_args.metadata = true;
+            }
+            AuditIssue::InvalidBcsJson { .. } => {
+                if issue.is_repairable() {
+                    save_args.bcs = true;
+                }
+            }
+            _ => {}
+        }
+    }
+    if !(save_args.bcs || save_args.bytecode || save_args.metadata || save_args.move_code) {
+        return Ok(moved_dir);
+    }
+    save_package(&save_args, &pkg_with_metadata).map_err(|e| e.to_string())?;
+    if let Some(misplaced_dir) = misplaced_dir_to_quarantine {
+        quarantine_dir(packages_dir, misplaced_dir)
+            .map_err(|e| format!("rewrote the package but {}", e))?;
+    }
+    Ok(true)
+}
+
+// keeps the path of the directory relative to the store so quarantined directories do not clash
+fn quarantine_dir(packages_dir: &PackagesDir, dir: &Path) -> Result<PathBuf, String> {
+    let prefix = packages_dir.get_prefix();
+    let relative_dir = dir
+        .strip_prefix(&prefix)
+        .ok()
+        .or_else(|| dir.file_name().map(Path::new))
+        .ok_or_else(|| format!("could not quarantine {}", dir.display()))?;
+    let quarantined_dir = prefix.join(QUARANTINE_DIR).join(relative_dir);
+    if quarantined_dir.exists() {
+        return Err(format!(
+            "could not quarantine {}: {} already exists",
+            dir.display(),
+            quarantined_dir.display()
+        ));
+    }
+    if let Some(parent_dir) = quarantined_dir.parent() {
+        fs::create_dir_all(parent_dir).map_err(|e| e.to_string())?;
+    }
+    fs::rename(dir, &quarantined_dir).map_err(|e| {
+        format!(
+            "could not move {} to {}: {}",
+            dir.display(),
+            quarantined_dir.display(),
+            e
+        )
+    })?;
+    Ok(quarantined_dir)
+}
+
+fn read_bcs_json(package_path: &Path) -> Result<BcsJsonSchema, String> {
+    let bcs_json = fs::read_to_string(package_path.join("bcs.json")).map_err(|e| e.to_string())?;
+    serde_json::from_str(&bcs_json).map_err(|e| e.to_string())
+}
+
+fn read_metadata(package_path: &Path) -> Result<PackageMetadata, String> {
+    let metadata_json =
+        fs::read_to_string(package_path.join("metadata.json")).map_err(|e| e.to_string())?;
+    serde_json::from_str(&metadata_json).map_err(|e| e.to_string())
+}
+
+fn list_module_files(dir: &Path, extension: &str) -> BTreeSet<String> {
+    let mut module_names = BTreeSet::new();
+    if let Ok(entries) = fs::read_dir(dir) {
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension().and_then(|e| e.to_str()) == Some(extension) {
+                if let Some(stem) = path.file_stem().and_then(|s| s.to_str()) {
+                    module_names.insert(stem.to_string());
+                }
+            }
+        }
+    }
+    module_names
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bcs_json::BCS_JSON_SCHEMA_VERSION;
+    use crate::test_utils::{test_dir, test_package_with_metadata, write_test_package};
+
+    // a store with one package and all of its files in place
+    fn write_audited_package(prefix: &Path) -> (PackagesDir, PathBuf) {
+        let packages_dir = PackagesDir::new(prefix.to_path_buf());
+        let pkg_with_metadata = test_package_with_metadata();
+        write_test_package(&packages_dir, &pkg_with_metadata);
+        let package_path = PathBuf::from(
+            packages_dir.get_package_dir(&pkg_with_metadata.package.id().to_canonical_string(true)),
+        );
+        fs::create_dir_all(package_path.join("bytecode_modules")).unwrap();
+        fs::create_dir_all(package_path.join("decompiled_modules")).unwrap();
+        for (module_name, module_bytes) in pkg_with_metadata.package.serialized_module_map() {
+            fs::write(
+                package_path.join(format!("bytecode_modules/{}.mv", module_name)),
+                module_bytes,
+            )
+            .unwrap();
+            fs::write(
+                package_path.join(format!("decompiled_modules/{}.move", module_name)),
+                "module 0x0::test {}",
+            )
+            .unwrap();
+        }
+        (packages_dir, package_path)
+    }
+
+    #[test]
+    fn audit_package_clean() {
+        let prefix = test_dir("audit-clean");
+        let (packages_dir, package_path) = write_audited_package(&prefix);
+        let report = audit_package(&packages_dir, &package_path, BCS_JSON_SCHEMA_VERSION);
+        assert!(report.issues.is_empty(), "{:?}", report.issues);
+        assert!(report.package_id.is_some());
+        fs::remove_dir_all(prefix).unwrap();
+    }
+
+    #[test]
+    fn audit_and_repair_package() {
+        let prefix = test_dir("audit-repair");
+        let (packages_dir, package_path) = write_audited_package(&prefix);
+        fs::write(package_path.join("bytecode_modules/test.mv"), [0u8; 4]).unwrap();
+        fs::write(package_path.join("bytecode_modules/extra.mv"), [0u8; 4]).unwrap();
+        fs::write(package_path.join("decompiled_modules/test.move"), "").unwrap();
+        let mut metadata = read_metadata(&package_path).unwrap();
+        metadata.version = 2;
+        fs::write(
+            package_path.join("metadata.json"),
+            serde_json::to_string_pretty(&metadata).unwrap(),
+        )
+        .unwrap();
+
+        let report = audit_package(&packages_dir, &package_path, BCS_JSON_SCHEMA_VERSION);
+        assert!(matches!(
+            report.issues.as_slice(),
+            [
+                AuditIssue::BytecodeMismatch { module },
+                AuditIssue::UnexpectedBytecodeModule { module: unexpected },
+                AuditIssue::EmptyDecompiledModule { .. },
+                AuditIssue::MetadataMismatch { field, .. },
+            ] if module == "test" && unexpected == "extra" && field == "version"
+        ));
+
+        // without a decompiler the empty decompiled file and the extra module stay
+        assert!(repair_package(&packages_dir, &package_path, &report, None).unwrap());
+        let report = audit_package(&packages_dir, &package_path, BCS_JSON_SCHEMA_VERSION);
+        assert!(matches!(
+            report.issues.as_slice(),
+            [
+                AuditIssue::UnexpectedBytecodeModule { .. },
+                AuditIssue::EmptyDecompiledModule { .. },
+            ]
+        ));
+        fs::remove_dir_all(prefix).unwrap();
+    }
+
+    #[test]
+    fn audit_package_directory_mismatch() {
+        let prefix = test_dir("audit-directory-mismatch");
+        let (packages_dir, package_path) = write_audited_package(&prefix);
+        let misplaced_path = prefix.join("misplaced");
+        fs::rename(&package_path, &misplaced_path).unwrap();
+
+        let report = audit_package(&packages_dir, &misplaced_path, BCS_JSON_SCHEMA_VERSION);
+        assert!(report.issues.iter().any(|issue| matches!(
+            issue,
+            AuditIssue::DirectoryMismatch { expected_dir }
+                if Path::new(expected_dir) == package_path
+        )));
+        fs::remove_dir_all(prefix).unwrap();
+    }
+
+    #[test]
+    fn repair_quarantines_misplaced_copy() {
+        let prefix = test_dir("audit-quarantine");
+        let (packages_dir, package_path) = write_audited_package(&prefix);
+        let misplaced_path = prefix.join("0x00").join("misplaced");
+        fs::create_dir_all(&misplaced_path).unwrap();
+        for file_name in ["bcs.json", "metadata.json"] {
+            fs::copy(package_path.join(file_name), misplaced_path.join(file_name)).unwrap();
+        }
+
+        let report = audit_package(&packages_dir, &misplaced_path, BCS_JSON_SCHEMA_VERSION);
+        assert!(repair_package(&packages_dir, &misplaced_path, &report, None).unwrap());
+        assert!(!misplaced_path.exists());
+        assert!(prefix
+            .join(QUARANTINE_DIR)
+            .join("0x00/misplaced/bcs.json")
+            .exists());
+        assert!(package_path.join("bcs.json").exists());
+        fs::remove_dir_all(prefix).unwrap();
+    }
+
+    #[test]
+    fn audit_report_add() {
+        let package_report = |issues: Vec<AuditIssue>, repaired: bool| PackageAuditReport {
+            path: "path".to_string(),
+            package_id: None,
+            issues,
+            repaired,
+            repair_error: None,
+        };
+        let mut report = AuditReport::default();
+        report.add(package_report(vec![], false));
+        report.add(package_report(
+            vec![AuditIssue::BytecodeMismatch {
+                module: "test".to_string(),
+            }],
+            true,
+        ));
+        report.add(package_report(
+            vec![AuditIssue::RoundTripFailure {
+                message: "message".to_string(),
+            }],
+            false,
+        ));
+        assert_eq!(report.packages_checked, 3);
+        assert_eq!(report.packages_with_issues, 2);
+        assert_eq!(report.packages_repaired, 1);
+        // clean packages are only counted
+        assert_eq!(report.packages.len(), 2);
+    }
+}
@@ -1,16 +1,27 @@
 use base64::{engine::general_purpose::STANDARD as BASE64_STANDARD, Engine as _};
 use move_binary_format::file_format::{
-    CompiledModule, DatatypeHandleIndex, SignatureToken, Visibility,
+    Ability, AbilitySet, Bytecode, CompiledModule, DatatypeHandleIndex, DatatypeTyParameter,
+    FieldDefinition, SignatureToken, Visibility,
 };
 use move_core_types::account_address::AccountAddress;
 use move_core_types::identifier::IdentStr;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use std::collections::BTreeMap;
-use sui_types::base_types::ObjectID;
+use std::collections::{BTreeMap, BTreeSet};
+use sui_types::base_types::{ObjectID, SequenceNumber};
+use sui_types::is_system_package;
 use sui_types::move_package::MovePackage;
 use sui_types::move_package::{TypeOrigin, UpgradeInfo};
+use thiserror::Error;
 
-#[derive(Debug, Deserialize, PartialEq, Serialize)]
+// bump when the shape of bcs.json changes; see store_layout.rs
+pub const BCS_JSON_SCHEMA_VERSION: u32 = 3;
+// files written before this version lack derived fields the current code fills in: their struct
+// and enum maps and the function fields added in version 3 are left to sui-packages-migrate
+// instead of being reported as mismatches
+const DERIVED_MAPS_SCHEMA_VERSION: u32 = 3;
+
+#[derive(Debug, Deserialize, PartialEq, Serialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct BcsJsonSchema {
     data_type: String,
@@ -20,20 +31,368 @@ pub struct BcsJsonSchema {
     type_origin_table: Vec<TypeOrigin>,
     linkage_table: BTreeMap<ObjectID, UpgradeInfo>,
     function_map: BTreeMap<String, BTreeMap<String, BcsFunctionEntry>>,
+    // added in schema version 2
+    #[serde(default)]
+    struct_map: BTreeMap<String, BTreeMap<String, BcsStructEntry>>,
+    #[serde(default)]
+    enum_map: BTreeMap<String, BTreeMap<String, BcsEnumEntry>>,
 }
 
-#[derive(Debug, Deserialize, PartialEq, Serialize)]
+#[derive(Debug, Deserialize, PartialEq, Serialize, JsonSchema)]
 pub struct BcsFunctionEntry {
     visibility: String,
     is_entry: bool,
     params: Vec<String>,
     #[serde(rename = "return")]
     return_: Vec<String>,
+    // added in schema version 3
+    #[serde(default)]
+    type_parameters: Vec<BcsFunctionTypeParameter>,
+    #[serde(default)]
+    is_native: bool,
+    #[serde(default)]
+    has_empty_body: bool,
+}
+
+#[derive(Debug, Deserialize, PartialEq, Serialize, JsonSchema)]
+pub struct BcsFunctionTypeParameter {
+    constraints: Vec<String>,
+}
+
+#[derive(Debug, Deserialize, PartialEq, Serialize, JsonSchema)]
+pub struct BcsStructEntry {
+    abilities: Vec<String>,
+    type_parameters: Vec<BcsDatatypeTypeParameter>,
+    fields: Vec<BcsFieldEntry>,
+}
+
+#[derive(Debug, Deserialize, PartialEq, Serialize, JsonSchema)]
+pub struct BcsEnumEntry {
+    abilities: Vec<String>,
+    type_parameters: Vec<BcsDatatypeTypeParameter>,
+    variants: Vec<BcsVariantEntry>,
+}
+
+#[derive(Debug, Deserialize, PartialEq, Serialize, JsonSchema)]
+pub struct BcsDatatypeTypeParameter {
+    constraints: Vec<String>,
+    is_phantom: bool,
+}
+
+#[derive(Debug, Deserialize, PartialEq, Serialize, JsonSchema)]
+pub struct BcsFieldEntry {
+    name: String,
+    #[serde(rename = "type")]
+    type_: String,
+}
+
+#[derive(Debug, Deserialize, PartialEq, Serialize, JsonSchema)]
+pub struct BcsVariantEntry {
+    name: String,
+    fields: Vec<BcsFieldEntry>,
 }
+#[derive(Error, Debug)]
+pub enum BcsJsonError {
+    #[error("Invalid package id in bcs.json: {0}")]
+    InvalidPackageId(String),
+    #[error("Failed to decode base64 for module {0}: {1}")]
+    ModuleDecodeError(String, base64::DecodeError),
+    #[error("Failed to build package {0}: {1}")]
+    PackageBuildError(String, String),
+}
+
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase", tag = "kind")]
+pub enum BcsJsonMismatch {
+    InvalidPackageId {
+        id: String,
+    },
+    ModuleDecodeError {
+        module: String,
+        message: String,
+    },
+    ModuleDeserializeError {
+        module: String,
+        message: String,
+    },
+    ModuleNameMismatch {
+        module: String,
+        bytecode_name: String,
+    },
+    // derived maps that do not match what the bytecode in moduleMap produces
+    FunctionMapMismatch {
+        module: String,
+    },
+    StructMapMismatch {
+        module: String,
+    },
+    EnumMapMismatch {
+        module: String,
+    },
+    MissingTypeOrigin {
+        module: String,
+        datatype: String,
+    },
+    UnexpectedTypeOrigin {
+        module: String,
+        datatype: String,
+    },
+    MissingLinkage {
+        address: String,
+    },
+}
+
+impl BcsJsonMismatch {
+    // whether the mismatch disappears by regenerating bcs.json from its moduleMap
+    pub fn is_derived(&self) -> bool {
+        matches!(
+            self,
+            BcsJsonMismatch::FunctionMapMismatch { .. }
+                | BcsJsonMismatch::StructMapMismatch { .. }
+                | BcsJsonMismatch::EnumMapMismatch { .. }
+        )
+    }
+}
+
+impl BcsFunctionEntry {
+    // PUBLIC, FRIEND or PRIVATE
+    pub fn get_visibility(&self) -> &str {
+        &self.visibility
+    }
+    pub fn get_is_entry(&self) -> bool {
+        self.is_entry
+    }
+
+    // the fields every schema version has
+    fn legacy_fields(&self) -> (&str, bool, &[String], &[String]) {
+        (&self.visibility, self.is_entry, &self.params, &self.return_)
+    }
+}
+
+// compares function maps on the fields older schema versions have
+fn legacy_function_maps_match(
+    function_map: Option<&BTreeMap<String, BcsFunctionEntry>>,
+    expected_function_map: Option<&BTreeMap<String, BcsFunctionEntry>>,
+) -> bool {
+    let legacy_fields = |function_map: Option<&BTreeMap<String, BcsFunctionEntry>>| {
+        function_map.map(|function_map| {
+            function_map
+                .iter()
+                .map(|(name, entry)| (name, entry.legacy_fields()))
+                .collect::<Vec<_>>()
+        })
+    };
+    legacy_fields(function_map) == legacy_fields(expected_function_map)
+}
+
 impl BcsJsonSchema {
     pub fn get_module_map(&self) -> &BTreeMap<String, String> {
         &self.module_map
     }
+    pub fn get_id(&self) -> &str {
+        &self.id
+    }
+    pub fn get_version(&self) -> u64 {
+        self.version
+    }
+    pub fn get_type_origin_table(&self) -> &Vec<TypeOrigin> {
+        &self.type_origin_table
+    }
+    pub fn get_linkage_table(&self) -> &BTreeMap<ObjectID, UpgradeInfo> {
+        &self.linkage_table
+    }
+    pub fn get_struct_map(&self) -> &BTreeMap<String, BTreeMap<String, BcsStructEntry>> {
+        &self.struct_map
+    }
+    pub fn get_enum_map(&self) -> &BTreeMap<String, BTreeMap<String, BcsEnumEntry>> {
+        &self.enum_map
+    }
+    pub fn get_function_map(&self) -> &BTreeMap<String, BTreeMap<String, BcsFunctionEntry>> {
+        &self.function_map
+    }
+
+    // rebuilds the MovePackage from the module map, type origin table and linkage table
+    pub fn to_move_package(&self) -> Result<MovePackage, BcsJsonError> {
+        let id = ObjectID::from_hex_literal(&self.id)
+            .map_err(|_| BcsJsonError::InvalidPackageId(self.id.clone()))?;
+        let mut module_map: BTreeMap<String, Vec<u8>> = BTreeMap::new();
+        for (module_name, module_b64) in &self.module_map {
+            let module_bytes = BASE64_STANDARD
+                .decode(module_b64)
+                .map_err(|e| BcsJsonError::ModuleDecodeError(module_name.clone(), e))?;
+            module_map.insert(module_name.clone(), module_bytes);
+        }
+        MovePackage::new(
+            id,
+            SequenceNumber::from_u64(self.version),
+            module_map,
+            u64::MAX,
+            self.type_origin_table.clone(),
+            self.linkage_table.clone(),
+        )
+        .map_err(|e| BcsJsonError::PackageBuildError(self.id.clone(), e.to_string()))
+    }
+
+    // recomputes the derived fields from moduleMap and checks the type origin and linkage
+    // tables against the datatypes and addresses referenced by the modules. schema_version is
+    // the bcs.json schema version recorded for the store
+    pub fn validate(&self, schema_version: u32) -> Vec<BcsJsonMismatch> {
+        let mut mismatches = Vec::new();
+        let id = match ObjectID::from_hex_literal(&self.id) {
+            Ok(id) => Some(id),
+            Err(_) => {
+                mismatches.push(BcsJsonMismatch::InvalidPackageId {
+                    id: self.id.clone(),
+                });
+                None
+            }
+        };
+
+        let mut modules: BTreeMap<String, CompiledModule> = BTreeMap::new();
+        for (module_name, module_b64) in &self.module_map {
+            let module_bytes = match BASE64_STANDARD.decode(module_b64) {
+                Ok(module_bytes) => module_bytes,
+                Err(e) => {
+                    mismatches.push(BcsJsonMismatch::ModuleDecodeError {
+                        module: module_name.clone(),
+                        message: e.to_string(),
+                    });
+                    continue;
+                }
+            };
+            match CompiledModule::deserialize_with_defaults(&module_bytes) {
+                Ok(module) => {
+                    modules.insert(module_name.clone(), module);
+                }
+                Err(e) => mismatches.push(BcsJsonMismatch::ModuleDeserializeError {
+                    module: module_name.clone(),
+                    message: e.to_string(),
+                }),
+            }
+        }
+
+        let module_names: BTreeSet<&String> = self
+            .module_map
+            .keys()
+            .chain(self.function_map.keys())
+            .chain(self.struct_map.keys())
+            .chain(self.enum_map.keys())
+            .collect();
+        for module_name in module_names {
+            let module = modules.get(module_name);
+            if let Some(module) = module {
+                let bytecode_name = module.name().to_string();
+                if &bytecode_name != module_name {
+                    mismatches.push(BcsJsonMismatch::ModuleNameMismatch {
+                        module: module_name.clone(),
+                        bytecode_name,
+                    });
+                }
+            } else if self.module_map.contains_key(module_name) {
+                // already reported as a decode or deserialize error
+                continue;
+            }
+            let expected_function_map = module.map(move_module_to_bcs_function_map);
+            let function_map_matches = if schema_version < DERIVED_MAPS_SCHEMA_VERSION {
+                legacy_function_maps_match(
+                    self.function_map.get(module_name),
+                    expected_function_map.as_ref(),
+                )
+            } else {
+                self.function_map.get(module_name) == expected_function_map.as_ref()
+            };
+            if !function_map_matches {
+                mismatches.push(BcsJsonMismatch::FunctionMapMismatch {
+                    module: module_name.clone(),
+                });
+            }
+            if schema_version < DERIVED_MAPS_SCHEMA_VERSION {
+                continue;
+            }
+            if self.struct_map.get(module_name)
+                != module.map(move_module_to_bcs_struct_map).as_ref()
+            {
+                mismatches.push(BcsJsonMismatch::StructMapMismatch {
+                    module: module_name.clone(),
+                });
+            }
+            if self.enum_map.get(module_name) != module.map(move_module_to_bcs_enum_map).as_ref() {
+                mismatches.push(BcsJsonMismatch::EnumMapMismatch {
+                    module: module_name.clone(),
+                });
+            }
+        }
+
+        // every declared datatype needs exactly one type origin and vice versa
+        let mut declared_datatypes: BTreeSet<(String, String)> = BTreeSet::new();
+        for (module_name, module) in &modules {
+            for struct_def in module.struct_defs() {
+                let struct_handle = module.datatype_handle_at(struct_def.struct_handle);
+                declared_datatypes.insert((
+                    module_name.clone(),
+                    module.identifier_at(struct_handle.name).to_string(),
+                ));
+            }
+            for enum_def in module.enum_defs() {
+                let enum_handle = module.datatype_handle_at(enum_def.enum_handle);
+                declared_datatypes.insert((
+                    module_name.clone(),
+                    module.identifier_at(enum_handle.name).to_string(),
+                ));
+            }
+        }
+        let type_origins: BTreeSet<(String, String)> = self
+            .type_origin_table
+            .iter()
+            .map(|origin| (origin.module_name.clone(), origin.datatype_name.clone()))
+            .collect();
+        for (module, datatype) in declared_datatypes.difference(&type_origins) {
+            mismatches.push(BcsJsonMismatch::MissingTypeOrigin {
+                module: module.clone(),
+                datatype: datatype.clone(),
+            });
+        }
+        for (module, datatype) in type_origins.difference(&declared_datatypes) {
+            mismatches.push(BcsJsonMismatch::UnexpectedTypeOrigin {
+                module: module.clone(),
+                datatype: datatype.clone(),
+            });
+        }
+
+        // system packages are published without linkage tables
+        if id.is_some_and(|id| !is_system_package(id)) {
+            // only module handles used by a function or datatype handle need a linkage entry,
+            // system packages resolve without one
+            let mut referenced_addresses: BTreeSet<AccountAddress> = BTreeSet::new();
+            for module in modules.values() {
+                let used_module_handles = module
+                    .function_handles()
+                    .iter()
+                    .map(|function_handle| function_handle.module)
+                    .chain(
+                        module
+                            .datatype_handles()
+                            .iter()
+                            .map(|datatype_handle| datatype_handle.module),
+                    );
+                for module_handle_index in used_module_handles {
+                    let module_handle = module.module_handle_at(module_handle_index);
+                    let address = module.address_identifier_at(module_handle.address);
+                    if address != module.address() && !is_system_package(*address) {
+                        referenced_addresses.insert(*address);
+                    }
+                }
+            }
+            for address in referenced_addresses {
+                if !self.linkage_table.contains_key(&ObjectID::from(address)) {
+                    mismatches.push(BcsJsonMismatch::MissingLinkage {
+                        address: address.to_canonical_string(true),
+                    });
+                }
+            }
+        }
+        mismatches
+    }
 }
 
 impl From<&MovePackage> for BcsJsonSchema {
@@ -57,6 +416,14 @@ impl From<&MovePackage> for BcsJsonSchema {
             .iter()
             .map(|(k, v)| (k.clone(), move_module_to_bcs_function_map(&v)))
             .collect::<BTreeMap<String, BTreeMap<String, BcsFunctionEntry>>>();
+        let struct_map: BTreeMap<String, BTreeMap<String, BcsStructEntry>> = pkg_modules
+            .iter()
+            .map(|(k, v)| (k.clone(), move_module_to_bcs_struct_map(v)))
+            .collect();
+        let enum_map: BTreeMap<String, BTreeMap<String, BcsEnumEntry>> = pkg_modules
+            .iter()
+            .map(|(k, v)| (k.clone(), move_module_to_bcs_enum_map(v)))
+            .collect();
         BcsJsonSchema {
             data_type: String::from("package"),
             id: pkg.id().to_string(),
@@ -65,6 +432,8 @@ impl From<&MovePackage> for BcsJsonSchema {
             type_origin_table: pkg.type_origin_table().clone(),
             linkage_table: pkg.linkage_table().clone(),
             function_map: function_map,
+            struct_map,
+            enum_map,
         }
     }
 }
@@ -97,12 +466,98 @@ pub fn move_module_to_bcs_function_map(
                 .iter()
                 .map(|r| format_signature_token(module, r))
                 .collect(),
+            type_parameters: function_handle
+                .type_parameters
+                .iter()
+                .map(|constraints| BcsFunctionTypeParameter {
+                    constraints: format_abilities(*constraints),
+                })
+                .collect(),
+            is_native: function_def.code.is_none(),
+            has_empty_body: function_def
+                .code
+                .as_ref()
+                .is_some_and(|code_unit| code_unit.code == [Bytecode::Ret]),
         };
         function_map.insert(function_name, function_entry);
     }
     function_map
 }
 
+pub fn move_module_to_bcs_struct_map(module: &CompiledModule) -> BTreeMap<String, BcsStructEntry> {
+    let mut struct_map: BTreeMap<String, BcsStructEntry> = BTreeMap::new();
+    for struct_def in module.struct_defs() {
+        let struct_handle = module.datatype_handle_at(struct_def.struct_handle);
+        let struct_name = module.identifier_at(struct_handle.name).to_string();
+        let struct_entry = BcsStructEntry {
+            abilities: format_abilities(struct_handle.abilities),
+            type_parameters: format_datatype_type_parameters(&struct_handle.type_parameters),
+            fields: format_fields(module, struct_def.fields().unwrap_or(&[])),
+        };
+        struct_map.insert(struct_name, struct_entry);
+    }
+    struct_map
+}
+
+pub fn move_module_to_bcs_enum_map(module: &CompiledModule) -> BTreeMap<String, BcsEnumEntry> {
+    let mut enum_map: BTreeMap<String, BcsEnumEntry> = BTreeMap::new();
+    for enum_def in module.enum_defs() {
+        let enum_handle = module.datatype_handle_at(enum_def.enum_handle);
+        let enum_name = module.identifier_at(enum_handle.name).to_string();
+        let enum_entry = BcsEnumEntry {
+            abilities: format_abilities(enum_handle.abilities),
+            type_parameters: format_datatype_type_parameters(&enum_handle.type_parameters),
+            variants: enum_def
+                .variants
+                .iter()
+                .map(|variant| BcsVariantEntry {
+                    name: module.identifier_at(variant.variant_name).to_string(),
+                    fields: format_fields(module, &variant.fields),
+                })
+                .collect(),
+        };
+        enum_map.insert(enum_name, enum_entry);
+    }
+    enum_map
+}
+
+fn format_abilities(abilities: AbilitySet) -> Vec<String> {
+    abilities
+        .into_iter()
+        .map(|ability| {
+            (match ability {
+                Ability::Copy => "copy",
+                Ability::Drop => "drop",
+                Ability::Store => "store",
+                Ability::Key => "key",
+            })
+            .to_string()
+        })
+        .collect()
+}
+
+fn format_datatype_type_parameters(
+    type_parameters: &[DatatypeTyParameter],
+) -> Vec<BcsDatatypeTypeParameter> {
+    type_parameters
+        .iter()
+        .map(|type_parameter| BcsDatatypeTypeParameter {
+            constraints: format_abilities(type_parameter.constraints),
+            is_phantom: type_parameter.is_phantom,
+        })
+        .collect()
+}
+
+fn format_fields(module: &CompiledModule, fields: &[FieldDefinition]) -> Vec<BcsFieldEntry> {
+    fields
+        .iter()
+        .map(|field| BcsFieldEntry {
+            name: module.identifier_at(field.name).to_string(),
+            type_: format_signature_token(module, &field.signature.0),
+        })
+        .collect()
+}
+
 // TODO remove - copied from move-bytecode-utils/src/lib.rs because I want canonical addresses instead of short
 fn resolve_struct(
     module: &CompiledModule,
@@ -116,7 +571,7 @@ fn resolve_struct(
     (address, module_name, struct_name)
 }
 
-fn format_signature_token(module: &CompiledModule, t: &SignatureToken) -> String {
+pub fn format_signature_token(module: &CompiledModule, t: &SignatureToken) -> String {
     match t {
         SignatureToken::Bool => "bool".to_string(),
         SignatureToken::U8 => "u8".to_string(),
@@ -144,6 +599,12 @@ fn format_signature_token(module: &CompiledModule, t: &SignatureToken) -> String
     }
 }
 
+// whether a type mentions a type parameter of the enclosing function or datatype
+pub fn has_type_parameter(t: &SignatureToken) -> bool {
+    t.preorder_traversal()
+        .any(|t| matches!(t, SignatureToken::TypeParameter(_)))
+}
+
 fn format_signature_token_struct(
     module: &CompiledModule,
     sidx: DatatypeHandleIndex,
@@ -172,3 +633,237 @@ fn format_signature_token_struct(
         ty_args_string
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{test_package, TestModuleBuilder};
+
+    #[test]
+    fn struct_and_enum_maps() {
+        let mut builder = TestModuleBuilder::new();
+        let balance = builder.add_struct(
+            "Balance",
+            AbilitySet::EMPTY | Ability::Store,
+            vec![DatatypeTyParameter {
+                constraints: AbilitySet::EMPTY,
+                is_phantom: true,
+            }],
+            &[("value", SignatureToken::U64)],
+        );
+        builder.add_enum(
+            "Action",
+            AbilitySet::EMPTY | Ability::Copy | Ability::Drop,
+            vec![],
+            &[
+                ("Stop", vec![]),
+                (
+                    "Pay",
+                    vec![
+                        ("recipient", SignatureToken::Address),
+                        (
+                            "amounts",
+                            SignatureToken::Vector(Box::new(SignatureToken::U64)),
+                        ),
+                    ],
+                ),
+            ],
+        );
+        builder.add_struct(
+            "Pool",
+            AbilitySet::EMPTY | Ability::Key,
+            vec![DatatypeTyParameter {
+                constraints: AbilitySet::EMPTY | Ability::Store,
+                is_phantom: false,
+            }],
+            &[
+                ("balance", balance),
+                ("item", SignatureToken::TypeParameter(0)),
+            ],
+        );
+        let bcs_json = BcsJsonSchema::from(&test_package(&[builder.build()], 1));
+
+        let struct_map = &bcs_json.get_struct_map()["test"];
+        assert_eq!(
+            struct_map.keys().collect::<Vec<_>>(),
+            vec!["Balance", "Pool"]
+        );
+        assert_eq!(
+            struct_map["Pool"],
+            BcsStructEntry {
+                abilities: vec!["key".to_string()],
+                type_parameters: vec![BcsDatatypeTypeParameter {
+                    constraints: vec!["store".to_string()],
+                    is_phantom: false,
+                }],
+                fields: vec![
+                    BcsFieldEntry {
+                        name: "balance".to_string(),
+                        type_: "0x0000000000000000000000000000000000000000000000000000000000000000::test::Balance"
+                            .to_string(),
+                    },
+                    BcsFieldEntry {
+                        name: "item".to_string(),
+                        type_: "T0".to_string(),
+                    },
+                ],
+            }
+        );
+        assert!(struct_map["Balance"].type_parameters[0].is_phantom);
+
+        // variants keep their declaration order
+        assert_eq!(
+            bcs_json.get_enum_map()["test"]["Action"],
+            BcsEnumEntry {
+                abilities: vec!["copy".to_string(), "drop".to_string()],
+                type_parameters: vec![],
+                variants: vec![
+                    BcsVariantEntry {
+                        name: "Stop".to_string(),
+                        fields: vec![],
+                    },
+                    BcsVariantEntry {
+                        name: "Pay".to_string(),
+                        fields: vec![
+                            BcsFieldEntry {
+                                name: "recipient".to_string(),
+                                type_: "address".to_string(),
+                            },
+                            BcsFieldEntry {
+                                name: "amounts".to_string(),
+                                type_: "vector<u64>".to_string(),
+                            },
+                        ],
+                    },
+                ],
+            }
+        );
+    }
+
+    #[test]
+    fn function_type_parameters_and_bodies() {
+        let mut builder = TestModuleBuilder::new();
+        builder.add_function(
+            "swap",
+            2,
+            &[("coin", "zero", vec![SignatureToken::TypeParameter(0)])],
+        );
+        builder.add_function("noop", 0, &[]);
+        builder.add_function("hash", 0, &[]);
+        let mut module = builder.build();
+        module.function_handles[0].type_parameters[1] =
+            AbilitySet::EMPTY | Ability::Copy | Ability::Drop;
+        module.function_defs[2].code = None;
+        let bcs_json = BcsJsonSchema::from(&test_package(&[module], 1));
+
+        let function_map = &bcs_json.function_map["test"];
+        let swap = &function_map["swap"];
+        assert_eq!(
+            swap.type_parameters,
+            vec![
+                BcsFunctionTypeParameter {
+                    constraints: vec![],
+                },
+                BcsFunctionTypeParameter {
+                    constraints: vec!["copy".to_string(), "drop".to_string()],
+                },
+            ]
+        );
+        assert!(!swap.is_native && !swap.has_empty_body);
+        assert!(!function_map["noop"].is_native && function_map["noop"].has_empty_body);
+        assert!(function_map["hash"].is_native && !function_map["hash"].has_empty_body);
+    }
+
+    #[test]
+    fn validate_package_without_references() {
+        let mut builder = TestModuleBuilder::new();
+        builder.add_function("swap", 0, &[("test", "noop", vec![])]);
+        builder.add_function("noop", 0, &[]);
+        let bcs_json = BcsJsonSchema::from(&test_package(&[builder.build()], 1));
+        assert_eq!(bcs_json.validate(BCS_JSON_SCHEMA_VERSION), vec![]);
+    }
+
+    #[test]
+    fn validate_reports_mismatches() {
+        let mut builder = TestModuleBuilder::new();
+        builder.add_struct("Pool", AbilitySet::EMPTY | Ability::Key, vec![], &[]);
+        builder.add_function("swap", 0, &[("0xab::oracle", "price", vec![])]);
+        let mut bcs_json = BcsJsonSchema::from(&test_package(&[builder.build()], 1));
+        bcs_json
+            .function_map
+            .get_mut("test")
+            .unwrap()
+            .remove("swap");
+        bcs_json
+            .module_map
+            .insert("broken".to_string(), "not base64".to_string());
+
+        let mismatches = bcs_json.validate(BCS_JSON_SCHEMA_VERSION);
+        assert!(matches!(
+            mismatches.as_slice(),
+            [
+                BcsJsonMismatch::ModuleDecodeError { module: broken, .. },
+                BcsJsonMismatch::FunctionMapMismatch { module },
+                BcsJsonMismatch::MissingTypeOrigin { datatype, .. },
+                BcsJsonMismatch::MissingLinkage { address },
+            ] if broken == "broken"
+                && module == "test"
+                && datatype == "Pool"
+                && *address == AccountAddress::from_hex_literal("0xab").unwrap().to_canonical_string(true)
+        ));
+        assert!(mismatches[1].is_derived());
+        assert!(!mismatches[3].is_derived());
+    }
+
+    #[test]
+    fn validate_legacy_schema_checks_function_signatures() {
+        let mut builder = TestModuleBuilder::new();
+        builder.add_struct("Pool", AbilitySet::EMPTY | Ability::Key, vec![], &[]);
+        builder.add_function("swap", 1, &[]);
+        let mut bcs_json = BcsJsonSchema::from(&test_package(&[builder.build()], 1));
+        // a version 2 file: no enum map and no type parameters or native/empty body flags
+        bcs_json.enum_map.clear();
+        let swap = bcs_json
+            .function_map
+            .get_mut("test")
+            .unwrap()
+            .get_mut("swap")
+            .unwrap();
+        swap.type_parameters.clear();
+        swap.has_empty_body = false;
+        let legacy_schema_version = DERIVED_MAPS_SCHEMA_VERSION - 1;
+        assert!(matches!(
+            bcs_json.validate(legacy_schema_version).as_slice(),
+            [BcsJsonMismatch::MissingTypeOrigin { .. }]
+        ));
+
+        // the fields older versions have are still compared
+        bcs_json
+            .function_map
+            .get_mut("test")
+            .unwrap()
+            .get_mut("swap")
+            .unwrap()
+            .is_entry = true;
+        assert!(matches!(
+            bcs_json.validate(legacy_schema_version).as_slice(),
+            [
+                BcsJsonMismatch::FunctionMapMismatch { module },
+                BcsJsonMismatch::MissingTypeOrigin { .. },
+            ] if module == "test"
+        ));
+    }
+
+    #[test]
+    fn reads_bcs_json_without_struct_and_enum_maps() {
+        let bcs_json = BcsJsonSchema::from(&test_package(&[TestModuleBuilder::new().build()], 1));
+        let mut value = serde_json::to_value(&bcs_json).unwrap();
+        let object = value.as_object_mut().unwrap();
+        object.remove("structMap");
+        object.remove("enumMap");
+        let legacy: BcsJsonSchema = serde_json::from_value(value).unwrap();
+        assert!(legacy.get_struct_map().is_empty());
+        assert!(legacy.get_enum_map().is_empty());
+        assert_eq!(legacy.get_module_map(), bcs_json.get_module_map());
+    }
+}
@@ -1,19 +1,61 @@
-use move_binary_format::file_format::{Bytecode, CompiledModule, FunctionHandle};
-use serde::Serialize;
+use move_binary_format::file_format::{
+    Bytecode, CodeOffset, CompiledModule, FunctionDefinition, FunctionHandle, SignatureToken,
+};
+use move_core_types::account_address::AccountAddress;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
 use std::collections::{BTreeMap, BTreeSet};
+use sui_types::base_types::ObjectID;
 use sui_types::move_package::MovePackage;
 
+use crate::bcs_json::format_signature_token;
+
+// bump when the shape of call_graph.json changes; see store_layout.rs
+pub const CALL_GRAPH_SCHEMA_VERSION: u32 = 3;
+
+#[derive(Clone, Copy)]
 pub enum CallGraphType {
+    // callee addresses as written in the bytecode, i.e. original package ids
     Original,
-    // Linkage,
+    // callee addresses resolved through the linkage table to the package version actually called
+    Linkage,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
 pub struct ModuleCallGraph {
     module_name: String,
     call_graph: BTreeMap<String, BTreeSet<String>>,
+    // added in schema version 2
+    #[serde(default)]
+    linkage_call_graph: BTreeMap<String, BTreeSet<String>>,
+    // added in schema version 3, every call of every function in bytecode order
+    #[serde(default)]
+    call_sites: BTreeMap<String, FunctionCallSites>,
 }
-#[derive(Debug, Serialize)]
+
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+pub struct FunctionCallSites {
+    // None for native functions
+    code_unit: Option<CodeUnitInfo>,
+    call_sites: Vec<CallSite>,
+}
+
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+pub struct CodeUnitInfo {
+    code_length: usize,
+    locals_count: usize,
+}
+
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+pub struct CallSite {
+    // index of the Call or CallGeneric instruction in the caller's code
+    offset: CodeOffset,
+    callee: String,
+    linkage_callee: String,
+    // formatted like bcs.json signatures, type parameters of the caller are T0, T1, ...
+    type_arguments: Vec<String>,
+}
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
 pub struct PackageCallGraph {
     package_id: String,
     module_call_graphs: Vec<ModuleCallGraph>,
@@ -30,6 +72,8 @@ impl From<&MovePackage> for PackageCallGraph {
             let mut module_call_graph = ModuleCallGraph {
                 module_name: module_name.clone(),
                 call_graph: BTreeMap::new(),
+                linkage_call_graph: BTreeMap::new(),
+                call_sites: BTreeMap::new(),
             };
 
             let module = CompiledModule::deserialize_with_defaults(module_bytes).unwrap();
@@ -37,43 +81,44 @@ impl From<&MovePackage> for PackageCallGraph {
             for function_def in function_defs {
                 let caller_handle = module.function_handle_at(function_def.function);
                 let caller_name = module.identifier_at(caller_handle.name).to_string();
+                let mut caller_graph_original = BTreeSet::new();
+                let mut caller_graph_linkage = BTreeSet::new();
+                let mut function_call_sites = FunctionCallSites {
+                    code_unit: function_def.code.as_ref().map(|code_unit| CodeUnitInfo {
+                        code_length: code_unit.code.len(),
+                        locals_count: module.signature_at(code_unit.locals).len(),
+                    }),
+                    call_sites: Vec::new(),
+                };
+
+                for_each_call(&module, function_def, |call| {
+                    let callee =
+                        get_full_function_name(pkg, &module, call.handle, CallGraphType::Original);
+                    let linkage_callee =
+                        get_full_function_name(pkg, &module, call.handle, CallGraphType::Linkage);
+                    caller_graph_original.insert(callee.clone());
+                    caller_graph_linkage.insert(linkage_callee.clone());
+                    function_call_sites.call_sites.push(CallSite {
+                        offset: call.offset,
+                        callee,
+                        linkage_callee,
+                        type_arguments: call
+                            .type_arguments
+                            .iter()
+                            .map(|type_argument| format_signature_token(&module, type_argument))
+                            .collect(),
+                    });
+                });
+
                 module_call_graph
                     .call_graph
-                    .insert(caller_name.clone(), BTreeSet::new());
-                let caller_graph_original: &mut BTreeSet<String> = module_call_graph
-                    .call_graph
-                    .get_mut(&caller_name.to_string())
-                    .unwrap();
-
-                if let Some(code_unit) = &function_def.code {
-                    let code = &code_unit.code;
-                    for instruction in code {
-                        match instruction {
-                            Bytecode::Call(func_handle_index) => {
-                                let callee_handle = module.function_handle_at(*func_handle_index);
-                                caller_graph_original.insert(get_full_function_name(
-                                    &pkg,
-                                    &module,
-                                    &callee_handle,
-                                    CallGraphType::Original,
-                                ));
-                            }
-                            Bytecode::CallGeneric(func_instantiation_index) => {
-                                let callee_instantiation =
-                                    module.function_instantiation_at(*func_instantiation_index);
-                                let callee_handle =
-                                    module.function_handle_at(callee_instantiation.handle);
-                                caller_graph_original.insert(get_full_function_name(
-                                    &pkg,
-                                    &module,
-                                    &callee_handle,
-                                    CallGraphType::Original,
-                                ));
-                            }
-                            _ => {}
-                        }
-                    }
-                }
+                    .insert(caller_name.clone(), caller_graph_original);
+                module_call_graph
+                    .linkage_call_graph
+                    .insert(caller_name.clone(), caller_graph_linkage);
+                module_call_graph
+                    .call_sites
+                    .insert(caller_name, function_call_sites);
             }
             package_call_graph
                 .module_call_graphs
@@ -83,8 +128,116 @@ impl From<&MovePackage> for PackageCallGraph {
     }
 }
 
+// a Call or CallGeneric instruction of a function body
+pub struct CallInstruction<'a> {
+    pub offset: CodeOffset,
+    pub handle: &'a FunctionHandle,
+    // empty for non generic calls
+    pub type_arguments: &'a [SignatureToken],
+}
+
+// visits the calls of a function in bytecode order, native functions have none
+pub fn for_each_call<'a>(
+    module: &'a CompiledModule,
+    function_def: &'a FunctionDefinition,
+    mut f: impl FnMut(CallInstruction<'a>),
+) {
+    let Some(code_unit) = &function_def.code else {
+        return;
+    };
+    for (offset, instruction) in code_unit.code.iter().enumerate() {
+        let offset = offset as CodeOffset;
+        match instruction {
+            Bytecode::Call(func_handle_index) => f(CallInstruction {
+                offset,
+                handle: module.function_handle_at(*func_handle_index),
+                type_arguments: &[],
+            }),
+            Bytecode::CallGeneric(func_instantiation_index) => {
+                let callee_instantiation =
+                    module.function_instantiation_at(*func_instantiation_index);
+                f(CallInstruction {
+                    offset,
+                    handle: module.function_handle_at(callee_instantiation.handle),
+                    type_arguments: &module.signature_at(callee_instantiation.type_parameters).0,
+                })
+            }
+            _ => {}
+        }
+    }
+}
+
+impl PackageCallGraph {
+    pub fn get_package_id(&self) -> &str {
+        &self.package_id
+    }
+    pub fn get_module_call_graphs(&self) -> &Vec<ModuleCallGraph> {
+        &self.module_call_graphs
+    }
+    // call graphs written before schema version 2 have no linkage_call_graph
+    pub fn has_linkage_call_graph(&self) -> bool {
+        self.module_call_graphs.iter().all(|module_call_graph| {
+            module_call_graph.linkage_call_graph.len() == module_call_graph.call_graph.len()
+        })
+    }
+    // call graphs written before schema version 3 have no call_sites
+    pub fn has_call_sites(&self) -> bool {
+        self.module_call_graphs.iter().all(|module_call_graph| {
+            module_call_graph.call_sites.len() == module_call_graph.call_graph.len()
+        })
+    }
+}
+
+impl ModuleCallGraph {
+    pub fn get_module_name(&self) -> &str {
+        &self.module_name
+    }
+    pub fn get_call_graph(&self) -> &BTreeMap<String, BTreeSet<String>> {
+        &self.call_graph
+    }
+    pub fn get_linkage_call_graph(&self) -> &BTreeMap<String, BTreeSet<String>> {
+        &self.linkage_call_graph
+    }
+    pub fn get_call_sites(&self) -> &BTreeMap<String, FunctionCallSites> {
+        &self.call_sites
+    }
+}
+
+impl FunctionCallSites {
+    pub fn get_code_unit(&self) -> Option<&CodeUnitInfo> {
+        self.code_unit.as_ref()
+    }
+    pub fn get_call_sites(&self) -> &Vec<CallSite> {
+        &self.call_sites
+    }
+}
+
+impl CodeUnitInfo {
+    pub fn get_code_length(&self) -> usize {
+        self.code_length
+    }
+    pub fn get_locals_count(&self) -> usize {
+        self.locals_count
+    }
+}
+
+impl CallSite {
+    pub fn get_offset(&self) -> CodeOffset {
+        self.offset
+    }
+    pub fn get_callee(&self) -> &str {
+        &self.callee
+    }
+    pub fn get_linkage_callee(&self) -> &str {
+        &self.linkage_callee
+    }
+    pub fn get_type_arguments(&self) -> &Vec<String> {
+        &self.type_arguments
+    }
+}
+
 fn get_full_function_name(
-    _package: &MovePackage,
+    package: &MovePackage,
     module: &CompiledModule,
     func_handle: &FunctionHandle,
     call_graph_type: CallGraphType,
@@ -103,6 +256,149 @@ fn get_full_function_name(
                 bytecode_func_name.to_string()
             )
         }
+        CallGraphType::Linkage => {
+            format!(
+                "{}::{}::{}",
+                resolve_package_id(package, bytecode_package_id).to_canonical_string(true),
+                bytecode_module_name.to_string(),
+                bytecode_func_name.to_string()
+            )
+        }
     };
     full_name
 }
+
+// the package version a call to the given bytecode address goes to. Calls within the package
+// go to this version, system packages are upgraded in place and have no linkage table entry
+pub fn resolve_package_id(package: &MovePackage, bytecode_address: &AccountAddress) -> ObjectID {
+    let bytecode_object_id = ObjectID::from(*bytecode_address);
+    if bytecode_object_id == package.original_package_id() {
+        return package.id();
+    }
+    package
+        .linkage_table()
+        .get(&bytecode_object_id)
+        .map(|upgrade_info| upgrade_info.upgraded_id)
+        .unwrap_or(bytecode_object_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use sui_types::base_types::SequenceNumber;
+    use sui_types::move_package::UpgradeInfo;
+
+    use super::*;
+    use crate::test_utils::{test_package, TestModuleBuilder};
+
+    #[test]
+    fn original_and_linkage_call_graphs() {
+        // version 2 of 0x5 calling itself, a dependency upgraded to 0x8 and the framework
+        let mut builder =
+            TestModuleBuilder::at(AccountAddress::from_hex_literal("0x5").unwrap(), "pool");
+        builder.add_function(
+            "swap",
+            0,
+            &[
+                ("pool", "fee", vec![]),
+                ("0x7::math", "mul", vec![]),
+                ("coin", "value", vec![]),
+            ],
+        );
+        builder.add_function("fee", 0, &[]);
+        let module = builder.build();
+        let mut module_bytes = Vec::new();
+        module
+            .serialize_with_version(module.version, &mut module_bytes)
+            .unwrap();
+        let pkg = MovePackage::new(
+            ObjectID::from_hex_literal("0x6").unwrap(),
+            SequenceNumber::from_u64(2),
+            BTreeMap::from([("pool".to_string(), module_bytes)]),
+            u64::MAX,
+            vec![],
+            BTreeMap::from([(
+                ObjectID::from_hex_literal("0x7").unwrap(),
+                UpgradeInfo {
+                    upgraded_id: ObjectID::from_hex_literal("0x8").unwrap(),
+                    upgraded_version: SequenceNumber::from_u64(3),
+                },
+            )]),
+        )
+        .unwrap();
+        let call_graph = PackageCallGraph::from(&pkg);
+        let module_call_graph = &call_graph.module_call_graphs[0];
+        let name = |address: &str, function: &str| {
+            format!(
+                "{}::{}",
+                ObjectID::from_hex_literal(address)
+                    .unwrap()
+                    .to_canonical_string(true),
+                function
+            )
+        };
+
+        assert_eq!(
+            module_call_graph.call_graph["swap"],
+            BTreeSet::from([
+                name("0x5", "pool::fee"),
+                name("0x7", "math::mul"),
+                name("0x2", "coin::value"),
+            ])
+        );
+        assert_eq!(
+            module_call_graph.linkage_call_graph["swap"],
+            BTreeSet::from([
+                name("0x6", "pool::fee"),
+                name("0x8", "math::mul"),
+                name("0x2", "coin::value"),
+            ])
+        );
+        assert!(module_call_graph.linkage_call_graph["fee"].is_empty());
+    }
+
+    #[test]
+    fn call_sites_with_offsets_and_type_arguments() {
+        let mut builder = TestModuleBuilder::new();
+        builder.add_function(
+            "swap",
+            1,
+            &[
+                ("test", "fee", vec![]),
+                ("coin", "value", vec![SignatureToken::TypeParameter(0)]),
+                (
+                    "test",
+                    "fee",
+                    vec![SignatureToken::Vector(Box::new(SignatureToken::U64))],
+                ),
+            ],
+        );
+        let call_graph = PackageCallGraph::from(&test_package(&[builder.build()], 1));
+        let function_call_sites = &call_graph.module_call_graphs[0].call_sites["swap"];
+
+        let code_unit = function_call_sites.get_code_unit().unwrap();
+        // three calls and the final Ret
+        assert_eq!(code_unit.get_code_length(), 4);
+        assert_eq!(code_unit.get_locals_count(), 0);
+        let call_sites: Vec<(CodeOffset, &str, &Vec<String>)> = function_call_sites
+            .get_call_sites()
+            .iter()
+            .map(|call_site| {
+                (
+                    call_site.get_offset(),
+                    call_site.get_callee().rsplit_once("::").unwrap().1,
+                    call_site.get_type_arguments(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            call_sites,
+            vec![
+                (0, "fee", &vec![]),
+                (1, "value", &vec!["T0".to_string()]),
+                (2, "fee", &vec!["vector<u64>".to_string()]),
+            ]
+        );
+        // the call graph itself only keeps distinct callees
+        assert_eq!(call_graph.module_call_graphs[0].call_graph["swap"].len(), 2);
+    }
+}
@@ -0,0 +1,588 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use move_core_types::account_address::AccountAddress;
+use sui_types::is_system_package;
+
+use crate::bcs_json::BcsJsonSchema;
+use crate::global_call_graph::{
+    canonical_function_name, load_linkage_call_graph, GlobalCallGraph, GlobalCallGraphError,
+};
+use crate::package_id_io::{PackageIoError, PackagesDir};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Dot,
+    GraphMl,
+    Mermaid,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ExportOptions {
+    pub cluster_by_module: bool,
+    pub color_by_visibility: bool,
+    // replaces every system package function by one node per module and drops its callees
+    pub collapse_framework: bool,
+}
+
+// fill colors by visibility, entry functions get their own color whatever their visibility
+const ENTRY_COLOR: &str = "#59a14f";
+const PUBLIC_COLOR: &str = "#4e79a7";
+const FRIEND_COLOR: &str = "#f28e2b";
+const PRIVATE_COLOR: &str = "#bab0ac";
+const UNKNOWN_COLOR: &str = "#ffffff";
+
+#[derive(Debug, Clone)]
+struct ExportNode {
+    package_id: String,
+    module_name: String,
+    // None for a collapsed framework module
+    function_name: Option<String>,
+    // PUBLIC, FRIEND or PRIVATE, None if the package is not in the store
+    visibility: Option<String>,
+    is_entry: bool,
+}
+
+impl ExportNode {
+    fn from_function_name(function: &str) -> Option<Self> {
+        let mut parts = function.splitn(3, "::");
+        Some(ExportNode {
+            package_id: parts.next()?.to_string(),
+            module_name: parts.next()?.to_string(),
+            function_name: Some(parts.next()?.to_string()),
+            visibility: None,
+            is_entry: false,
+        })
+    }
+
+    fn is_framework(&self) -> bool {
+        AccountAddress::from_hex_literal(&self.package_id)
+            .is_ok_and(|address| is_system_package(address))
+    }
+
+    fn module_key(&self) -> String {
+        format!("{}::{}", self.package_id, self.module_name)
+    }
+
+    fn label(&self, cluster_by_module: bool) -> String {
+        match (&self.function_name, cluster_by_module) {
+            (Some(function_name), true) => function_name.clone(),
+            (Some(function_name), false) => format!(
+                "{}::{}::{}",
+                short_address(&self.package_id),
+                self.module_name,
+                function_name
+            ),
+            (None, _) => format!("{}::{}", short_address(&self.package_id), self.module_name),
+        }
+    }
+
+    fn color(&self) -> &'static str {
+        if self.is_entry {
+            return ENTRY_COLOR;
+        }
+        match self.visibility.as_deref() {
+            Some("PUBLIC") => PUBLIC_COLOR,
+            Some("FRIEND") => FRIEND_COLOR,
+            Some("PRIVATE") => PRIVATE_COLOR,
+            _ => UNKNOWN_COLOR,
+        }
+    }
+}
+
+// a call graph with nodes keyed by fully qualified function, ready to be rendered
+#[derive(Debug, Default)]
+pub struct CallGraphExport {
+    nodes: BTreeMap<String, ExportNode>,
+    edges: BTreeSet<(String, String)>,
+}
+
+impl CallGraphExport {
+    // the linkage call graph of one package, including the external functions it calls
+    pub fn from_package(
+        packages_dir: &PackagesDir,
+        package_id: &str,
+    ) -> Result<Self, PackageIoError> {
+        let call_graph = load_linkage_call_graph(packages_dir, package_id)?;
+        let mut export = CallGraphExport::default();
+        for module_call_graph in call_graph.get_module_call_graphs() {
+            for (caller, callees) in module_call_graph.get_linkage_call_graph() {
+                let caller = format!(
+                    "{}::{}::{}",
+                    call_graph.get_package_id(),
+                    module_call_graph.get_module_name(),
+                    caller
+                );
+                export.add_node(&caller);
+                for callee in callees {
+                    export.add_edge(&caller, callee);
+                }
+            }
+        }
+        Ok(export)
+    }
+
+    // the part of the cross-package graph reachable from a function, or calling it
+    pub fn from_global_call_graph(
+        global_call_graph: &GlobalCallGraph,
+        function: &str,
+        callers: bool,
+    ) -> Result<Self, GlobalCallGraphError> {
+        let mut subgraph_nodes = if callers {
+            global_call_graph.callers(function)?
+        } else {
+            global_call_graph.reachable(function)?
+        };
+        subgraph_nodes.insert(canonical_function_name(function)?);
+        let mut export = CallGraphExport::default();
+        for node in &subgraph_nodes {
+            export.add_node(node);
+            for callee in global_call_graph
+                .get_edges()
+                .get(node)
+                .into_iter()
+                .flatten()
+            {
+                if subgraph_nodes.contains(callee) {
+                    export.add_edge(node, callee);
+                }
+            }
+        }
+        Ok(export)
+    }
+
+    fn add_node(&mut self, function: &str) {
+        if self.nodes.contains_key(function) {
+            return;
+        }
+        if let Some(node) = ExportNode::from_function_name(function) {
+            self.nodes.insert(function.to_string(), node);
+        }
+    }
+
+    fn add_edge(&mut self, caller: &str, callee: &str) {
+        self.add_node(caller);
+        self.add_node(callee);
+        self.edges.insert((caller.to_string(), callee.to_string()));
+    }
+
+    // fills in visibility and entry status from the bcs.json of every package in the graph,
+    // packages missing from the store are left unannotated
+    pub fn annotate(&mut self, packages_dir: &PackagesDir) {
+        let mut bcs_jsons: BTreeMap<String, Option<BcsJsonSchema>> = BTreeMap::new();
+        for node in self.nodes.values_mut() {
+            let bcs_json = bcs_jsons
+                .entry(node.package_id.clone())
+                .or_insert_with(|| packages_dir.load_bcs_json(&node.package_id).ok());
+            let function_entry = bcs_json.as_ref().and_then(|bcs_json| {
+                bcs_json
+                    .get_function_map()
+                    .get(&node.module_name)?
+                    .get(node.function_name.as_ref()?)
+            });
+            if let Some(function_entry) = function_entry {
+                node.visibility = Some(function_entry.get_visibility().to_string());
+                node.is_entry = function_entry.get_is_entry();
+            }
+        }
+    }
+
+    fn collapse_framework(&self) -> CallGraphExport {
+        let node_key = |function: &str| -> String {
+            match self.nodes.get(function) {
+                Some(node) if node.is_framework() => node.module_key(),
+                _ => function.to_string(),
+            }
+        };
+        let mut collapsed = CallGraphExport::default();
+        for (function, node) in &self.nodes {
+            if node.is_framework() {
+                collapsed
+                    .nodes
+                    .entry(node.module_key())
+                    .or_insert_with(|| ExportNode {
+                        function_name: None,
+                        visibility: None,
+                        is_entry: false,
+                        ..node.clone()
+                    });
+            } else {
+                collapsed.nodes.insert(function.clone(), node.clone());
+            }
+        }
+        for (caller, callee) in &self.edges {
+            let caller_is_framework = self
+                .nodes
+                .get(caller)
+                .is_some_and(|node| node.is_framework());
+            if !caller_is_framework {
+                collapsed.edges.insert((node_key(caller), node_key(callee)));
+            }
+        }
+        collapsed
+    }
+
+    pub fn render(&self, format: ExportFormat, options: &ExportOptions) -> String {
+        if options.collapse_framework {
+            return self.collapse_framework().render(
+                format,
+                &ExportOptions {
+                    collapse_framework: false,
+                    ..*options
+                },
+            );
+        }
+        // node ids are positional so labels never need to be valid identifiers
+        let node_ids: BTreeMap<&String, String> = self
+            .nodes
+            .keys()
+            .enumerate()
+            .map(|(index, function)| (function, format!("n{}", index)))
+            .collect();
+        let mut modules: BTreeMap<String, Vec<&String>> = BTreeMap::new();
+        for (function, node) in &self.nodes {
+            modules.entry(node.module_key()).or_default().push(function);
+        }
+        match format {
+            ExportFormat::Dot => self.render_dot(&node_ids, &modules, options),
+            ExportFormat::GraphMl => self.render_graphml(&node_ids, &modules, options),
+            ExportFormat::Mermaid => self.render_mermaid(&node_ids, &modules, options),
+        }
+    }
+
+    fn render_dot(
+        &self,
+        node_ids: &BTreeMap<&String, String>,
+        modules: &BTreeMap<String, Vec<&String>>,
+        options: &ExportOptions,
+    ) -> String {
+        let mut lines = vec![
+            "digraph call_graph {".to_string(),
+            "  rankdir=LR;".to_string(),
+            "  node [shape=box];".to_string(),
+        ];
+        let dot_node = |function: &String, indent: &str| -> String {
+            let node = &self.nodes[function];
+            let mut attributes = vec![format!(
+                "label=\"{}\"",
+                escape_dot(&node.label(options.cluster_by_module))
+            )];
+            if options.color_by_visibility {
+                attributes.push("style=filled".to_string());
+                attributes.push(format!("fillcolor=\"{}\"", node.color()));
+            }
+            format!(
+                "{}{} [{}];",
+                indent,
+                node_ids[function],
+                attributes.join(", ")
+            )
+        };
+        if options.cluster_by_module {
+            for (index, (module_key, functions)) in modules.iter().enumerate() {
+                lines.push(format!("  subgraph cluster_{} {{", index));
+                lines.push(format!(
+                    "    label=\"{}\";",
+                    escape_dot(&module_label(module_key))
+                ));
+                for function in functions {
+                    lines.push(dot_node(function, "    "));
+                }
+                lines.push("  }".to_string());
+            }
+        } else {
+            for function in self.nodes.keys() {
+                lines.push(dot_node(function, "  "));
+            }
+        }
+        for (caller, callee) in &self.edges {
+            lines.push(format!("  {} -> {};", node_ids[caller], node_ids[callee]));
+        }
+        lines.push("}".to_string());
+        lines.join("\n")
+    }
+
+    fn render_graphml(
+        &self,
+        node_ids: &BTreeMap<&String, String>,
+        modules: &BTreeMap<String, Vec<&String>>,
+        options: &ExportOptions,
+    ) -> String {
+        let mut lines = vec![
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>".to_string(),
+            "<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">".to_string(),
+            "  <key id=\"label\" for=\"node\" attr.name=\"label\" attr.type=\"string\"/>"
+                .to_string(),
+            "  <key id=\"module\" for=\"node\" attr.name=\"module\" attr.type=\"string\"/>"
+                .to_string(),
+            "  <key id=\"visibility\" for=\"node\" attr.name=\"visibility\" attr.type=\"string\"/>"
+                .to_string(),
+            "  <key id=\"entry\" for=\"node\" attr.name=\"entry\" attr.type=\"boolean\"/>"
+                .to_string(),
+            "  <key id=\"color\" for=\"node\" attr.name=\"color\" attr.type=\"string\"/>"
+                .to_string(),
+            "  <graph id=\"call_graph\" edgedefault=\"directed\">".to_string(),
+        ];
+        let graphml_node = |lines: &mut Vec<String>, function: &String, indent: &str| {
+            let node = &self.nodes[function];
+            lines.push(format!("{}<node id=\"{}\">", indent, node_ids[function]));
+            lines.push(format!(
+                "{}  <data key=\"label\">{}</data>",
+                indent,
+                escape_xml(&node.label(options.cluster_by_module))
+            ));
+            lines.push(format!(
+                "{}  <data key=\"module\">{}</data>",
+                indent,
+                escape_xml(&node.module_key())
+            ));
+            if let Some(visibility) = &node.visibility {
+                lines.push(format!(
+                    "{}  <data key=\"visibility\">{}</data>",
+                    indent, visibility
+                ));
+            }
+            lines.push(format!(
+                "{}  <data key=\"entry\">{}</data>",
+                indent, node.is_entry
+            ));
+            if options.color_by_visibility {
+                lines.push(format!(
+                    "{}  <data key=\"color\">{}</data>",
+                    indent,
+                    node.color()
+                ));
+            }
+            lines.push(format!("{}</node>", indent));
+        };
+        if options.cluster_by_module {
+            // nested graphs, one per module
+            for (index, (module_key, functions)) in modules.iter().enumerate() {
+                lines.push(format!("    <node id=\"m{}\">", index));
+                lines.push(format!(
+                    "      <data key=\"label\">{}</data>",
+                    escape_xml(&module_label(module_key))
+                ));
+                lines.push(format!(
+                    "      <graph id=\"m{}:\" edgedefault=\"directed\">",
+                    index
+                ));
+                for function in functions {
+                    graphml_node(&mut lines, function, "        ");
+                }
+                lines.push("      </graph>".to_string());
+                lines.push("    </node>".to_string());
+            }
+        } else {
+            for function in self.nodes.keys() {
+                graphml_node(&mut lines, function, "    ");
+            }
+        }
+        for (caller, callee) in &self.edges {
+            lines.push(format!(
+                "    <edge source=\"{}\" target=\"{}\"/>",
+                node_ids[caller], node_ids[callee]
+            ));
+        }
+        lines.push("  </graph>".to_string());
+        lines.push("</graphml>".to_string());
+        lines.join("\n")
+    }
+
+    fn render_mermaid(
+        &self,
+        node_ids: &BTreeMap<&String, String>,
+        modules: &BTreeMap<String, Vec<&String>>,
+        options: &ExportOptions,
+    ) -> String {
+        let mut lines = vec!["flowchart LR".to_string()];
+        let mermaid_node = |function: &String, indent: &str| -> String {
+            let node = &self.nodes[function];
+            format!(
+                "{}{}[\"{}\"]",
+                indent,
+                node_ids[function],
+                escape_mermaid(&node.label(options.cluster_by_module))
+            )
+        };
+        if options.cluster_by_module {
+            for (index, (module_key, functions)) in modules.iter().enumerate() {
+                lines.push(format!(
+                    "  subgraph m{}[\"{}\"]",
+                    index,
+                    escape_mermaid(&module_label(module_key))
+                ));
+                for function in functions {
+                    lines.push(mermaid_node(function, "    "));
+                }
+                lines.push("  end".to_string());
+            }
+        } else {
+            for function in self.nodes.keys() {
+                lines.push(mermaid_node(function, "  "));
+            }
+        }
+        for (caller, callee) in &self.edges {
+            lines.push(format!("  {} --> {}", node_ids[caller], node_ids[callee]));
+        }
+        if options.color_by_visibility {
+            let mut nodes_by_color: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+            for (function, node) in &self.nodes {
+                nodes_by_color
+                    .entry(node.color())
+                    .or_default()
+                    .push(&node_ids[function]);
+            }
+            for (index, (color, ids)) in nodes_by_color.iter().enumerate() {
+                lines.push(format!("  classDef c{} fill:{}", index, color));
+                lines.push(format!("  class {} c{}", ids.join(","), index));
+            }
+        }
+        lines.join("\n")
+    }
+}
+
+fn short_address(package_id: &str) -> String {
+    match AccountAddress::from_hex_literal(package_id) {
+        Ok(address) => {
+            let hex = address.short_str_lossless();
+            if hex.len() <= 8 {
+                format!("0x{}", hex)
+            } else {
+                format!("0x{}…", &hex[..6])
+            }
+        }
+        Err(_) => package_id.to_string(),
+    }
+}
+
+fn module_label(module_key: &str) -> String {
+    match module_key.split_once("::") {
+        Some((package_id, module_name)) => {
+            format!("{}::{}", short_address(package_id), module_name)
+        }
+        None => module_key.to_string(),
+    }
+}
+
+fn escape_dot(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn escape_xml(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn escape_mermaid(s: &str) -> String {
+    s.replace('"', "#quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 0x5::pool::swap calls 0x5::pool::fee and 0x2::coin::value, which sort as n2, n1 and n0
+    fn test_export() -> CallGraphExport {
+        let swap = canonical_function_name("0x5::pool::swap").unwrap();
+        let mut export = CallGraphExport::default();
+        export.add_edge(&swap, &canonical_function_name("0x5::pool::fee").unwrap());
+        export.add_edge(&swap, &canonical_function_name("0x2::coin::value").unwrap());
+        let swap = export.nodes.get_mut(&swap).unwrap();
+        swap.visibility = Some("PUBLIC".to_string());
+        swap.is_entry = true;
+        export
+    }
+
+    fn render(format: ExportFormat, options: ExportOptions) -> Vec<String> {
+        test_export()
+            .render(format, &options)
+            .lines()
+            .map(|line| line.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn render_dot() {
+        let lines = render(ExportFormat::Dot, ExportOptions::default());
+        assert_eq!(lines[0], "digraph call_graph {");
+        assert!(lines.contains(&"  n0 [label=\"0x2::coin::value\"];".to_string()));
+        assert!(lines.contains(&"  n2 [label=\"0x5::pool::swap\"];".to_string()));
+        assert!(lines.contains(&"  n2 -> n0;".to_string()));
+        assert!(lines.contains(&"  n2 -> n1;".to_string()));
+
+        let lines = render(
+            ExportFormat::Dot,
+            ExportOptions {
+                cluster_by_module: true,
+                color_by_visibility: true,
+                ..Default::default()
+            },
+        );
+        assert!(lines.contains(&"  subgraph cluster_1 {".to_string()));
+        assert!(lines.contains(&"    label=\"0x5::pool\";".to_string()));
+        assert!(lines.contains(&format!(
+            "    n2 [label=\"swap\", style=filled, fillcolor=\"{}\"];",
+            ENTRY_COLOR
+        )));
+        assert!(lines.contains(&format!(
+            "    n1 [label=\"fee\", style=filled, fillcolor=\"{}\"];",
+            UNKNOWN_COLOR
+        )));
+    }
+
+    #[test]
+    fn render_graphml() {
+        let lines = render(ExportFormat::GraphMl, ExportOptions::default());
+        assert!(lines.contains(&"    <node id=\"n2\">".to_string()));
+        assert!(lines.contains(&"      <data key=\"label\">0x5::pool::swap</data>".to_string()));
+        assert!(lines.contains(&"      <data key=\"visibility\">PUBLIC</data>".to_string()));
+        assert!(lines.contains(&"      <data key=\"entry\">true</data>".to_string()));
+        assert!(lines.contains(&"    <edge source=\"n2\" target=\"n0\"/>".to_string()));
+        assert_eq!(lines.last().unwrap(), "</graphml>");
+    }
+
+    #[test]
+    fn render_mermaid() {
+        let lines = render(
+            ExportFormat::Mermaid,
+            ExportOptions {
+                color_by_visibility: true,
+                ..Default::default()
+            },
+        );
+        assert_eq!(lines[0], "flowchart LR");
+        assert!(lines.contains(&"  n1[\"0x5::pool::fee\"]".to_string()));
+        assert!(lines.contains(&"  n2 --> n1".to_string()));
+        assert!(lines.contains(&"  classDef c0 fill:#59a14f".to_string()));
+        assert!(lines.contains(&"  class n2 c0".to_string()));
+        assert!(lines.contains(&"  class n0,n1 c1".to_string()));
+    }
+
+    #[test]
+    fn collapse_framework() {
+        let lines = render(
+            ExportFormat::Mermaid,
+            ExportOptions {
+                collapse_framework: true,
+                ..Default::default()
+            },
+        );
+        assert!(lines.contains(&"  n0[\"0x2::coin\"]".to_string()));
+        assert!(lines.contains(&"  n2 --> n0".to_string()));
+        assert!(!lines.iter().any(|line| line.contains("value")));
+    }
+
+    #[test]
+    fn escaping() {
+        assert_eq!(escape_dot("a\"b\\c"), "a\\\"b\\\\c");
+        assert_eq!(escape_xml("<T & \"U\">"), "&lt;T &amp; &quot;U&quot;&gt;");
+        assert_eq!(escape_mermaid("say \"hi\""), "say #quot;hi#quot;");
+        let long_address = AccountAddress::from_hex_literal("0xabcdef123").unwrap();
+        assert_eq!(
+            short_address(&long_address.to_canonical_string(true)),
+            "0xabcdef…"
+        );
+    }
+}
@@ -0,0 +1,596 @@
+use move_binary_format::file_format::{
+    Bytecode, CodeOffset, CodeUnit, CompiledModule, JumpTableInner,
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use sui_types::move_package::MovePackage;
+
+// bump when the shape of cfg.json changes; see store_layout.rs
+pub const CFG_SCHEMA_VERSION: u32 = 2;
+
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+pub struct PackageCfg {
+    package_id: String,
+    module_cfgs: Vec<ModuleCfg>,
+}
+
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+pub struct ModuleCfg {
+    module_name: String,
+    // native functions have no code and are left out
+    function_cfgs: BTreeMap<String, FunctionCfg>,
+}
+
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+pub struct FunctionCfg {
+    // ordered by start offset, block 0 is the entry block
+    blocks: Vec<BasicBlock>,
+    edges: Vec<CfgEdge>,
+    loops: Vec<CfgLoop>,
+    abort_sites: Vec<CfgAbortSite>,
+}
+
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+pub struct BasicBlock {
+    id: usize,
+    start_offset: CodeOffset,
+    // inclusive
+    end_offset: CodeOffset,
+}
+
+// a function's cfg with the instructions of its blocks, for sui-packages-cfg exports. cfg.json
+// only keeps offsets, the instructions are in the bytecode
+#[derive(Debug, Serialize)]
+pub struct AnnotatedFunctionCfg<'a> {
+    #[serde(flatten)]
+    cfg: &'a FunctionCfg,
+    // per block, Debug formatting of each instruction, pool indices are not resolved
+    block_instructions: Vec<Vec<String>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+pub enum CfgEdgeKind {
+    // the next block, when the last instruction does not transfer control
+    Fallthrough,
+    Branch,
+    BranchTrue,
+    BranchFalse,
+    // one arm of a VariantSwitch, see variant
+    VariantSwitch,
+}
+
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+pub struct CfgEdge {
+    from: usize,
+    to: usize,
+    kind: CfgEdgeKind,
+    // variant tag for VariantSwitch edges
+    #[serde(skip_serializing_if = "Option::is_none")]
+    variant: Option<usize>,
+}
+
+// a natural loop, found from a back edge of a depth first traversal from the entry block
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+pub struct CfgLoop {
+    header: usize,
+    back_edge_from: usize,
+    // blocks of the loop, header included
+    body: BTreeSet<usize>,
+}
+
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+pub struct CfgAbortSite {
+    block: usize,
+    // abort codes are decoded in constants.json
+    code_offset: CodeOffset,
+}
+
+impl From<&MovePackage> for PackageCfg {
+    fn from(pkg: &MovePackage) -> Self {
+        let mut package_cfg = PackageCfg {
+            package_id: pkg.id().to_canonical_string(true),
+            module_cfgs: Vec::new(),
+        };
+        for (module_name, module_bytes) in pkg.serialized_module_map() {
+            let module = CompiledModule::deserialize_with_defaults(module_bytes).unwrap();
+            let mut module_cfg = ModuleCfg {
+                module_name: module_name.clone(),
+                function_cfgs: BTreeMap::new(),
+            };
+            for function_def in module.function_defs() {
+                let Some(code_unit) = &function_def.code else {
+                    continue;
+                };
+                let function_handle = module.function_handle_at(function_def.function);
+                module_cfg.function_cfgs.insert(
+                    module.identifier_at(function_handle.name).to_string(),
+                    FunctionCfg::from(code_unit),
+                );
+            }
+            package_cfg.module_cfgs.push(module_cfg);
+        }
+        package_cfg
+    }
+}
+
+impl From<&CodeUnit> for FunctionCfg {
+    fn from(code_unit: &CodeUnit) -> Self {
+        let code = &code_unit.code;
+        let branch_targets =
+            |instruction: &Bytecode| -> Vec<(CodeOffset, CfgEdgeKind, Option<usize>)> {
+                match instruction {
+                    Bytecode::Branch(target) => vec![(*target, CfgEdgeKind::Branch, None)],
+                    Bytecode::BrTrue(target) => vec![(*target, CfgEdgeKind::BranchTrue, None)],
+                    Bytecode::BrFalse(target) => vec![(*target, CfgEdgeKind::BranchFalse, None)],
+                    Bytecode::VariantSwitch(jump_table_index) => {
+                        let JumpTableInner::Full(offsets) =
+                            &code_unit.jump_tables[jump_table_index.0 as usize].jump_table;
+                        offsets
+                            .iter()
+                            .enumerate()
+                            .map(|(variant, target)| {
+                                (*target, CfgEdgeKind::VariantSwitch, Some(variant))
+                            })
+                            .collect()
+                    }
+                    _ => Vec::new(),
+                }
+            };
+        let ends_block = |instruction: &Bytecode| -> bool {
+            matches!(
+                instruction,
+                Bytecode::Branch(_)
+                    | Bytecode::BrTrue(_)
+                    | Bytecode::BrFalse(_)
+                    | Bytecode::VariantSwitch(_)
+                    | Bytecode::Ret
+                    | Bytecode::Abort
+            )
+        };
+        // BrTrue and BrFalse fall through when not taken
+        let falls_through = |instruction: &Bytecode| -> bool {
+            !matches!(
+                instruction,
+                Bytecode::Branch(_) | Bytecode::VariantSwitch(_) | Bytecode::Ret | Bytecode::Abort
+            )
+        };
+
+        let mut block_starts = BTreeSet::from([0 as CodeOffset]);
+        for (offset, instruction) in code.iter().enumerate() {
+            for (target, _, _) in branch_targets(instruction) {
+                block_starts.insert(target);
+            }
+            if ends_block(instruction) && offset + 1 < code.len() {
+                block_starts.insert((offset + 1) as CodeOffset);
+            }
+        }
+        let block_starts: Vec<CodeOffset> = block_starts
+            .into_iter()
+            .filter(|start| (*start as usize) < code.len())
+            .collect();
+        let block_of_offset: BTreeMap<CodeOffset, usize> = block_starts
+            .iter()
+            .enumerate()
+            .map(|(id, start)| (*start, id))
+            .collect();
+
+        let mut function_cfg = FunctionCfg {
+            blocks: Vec::new(),
+            edges: Vec::new(),
+            loops: Vec::new(),
+            abort_sites: Vec::new(),
+        };
+        for (id, start_offset) in block_starts.iter().enumerate() {
+            let end_offset = block_starts
+                .get(id + 1)
+                .map(|next_start| next_start - 1)
+                .unwrap_or((code.len() - 1) as CodeOffset);
+            let last_instruction = &code[end_offset as usize];
+            for (target, kind, variant) in branch_targets(last_instruction) {
+                function_cfg.edges.push(CfgEdge {
+                    from: id,
+                    to: block_of_offset[&target],
+                    kind,
+                    variant,
+                });
+            }
+            if falls_through(last_instruction) && id + 1 < block_starts.len() {
+                function_cfg.edges.push(CfgEdge {
+                    from: id,
+                    to: id + 1,
+                    kind: CfgEdgeKind::Fallthrough,
+                    variant: None,
+                });
+            }
+            if let Bytecode::Abort = last_instruction {
+                function_cfg.abort_sites.push(CfgAbortSite {
+                    block: id,
+                    code_offset: end_offset,
+                });
+            }
+            function_cfg.blocks.push(BasicBlock {
+                id,
+                start_offset: *start_offset,
+                end_offset,
+            });
+        }
+        function_cfg.loops = function_cfg.find_loops();
+        function_cfg
+    }
+}
+
+impl FunctionCfg {
+    fn successors(&self) -> BTreeMap<usize, BTreeSet<usize>> {
+        let mut successors: BTreeMap<usize, BTreeSet<usize>> = BTreeMap::new();
+        for edge in &self.edges {
+            successors.entry(edge.from).or_default().insert(edge.to);
+        }
+        successors
+    }
+
+    fn find_loops(&self) -> Vec<CfgLoop> {
+        if self.blocks.is_empty() {
+            return Vec::new();
+        }
+        let successors = self.successors();
+        let mut predecessors: BTreeMap<usize, BTreeSet<usize>> = BTreeMap::new();
+        for edge in &self.edges {
+            predecessors.entry(edge.to).or_default().insert(edge.from);
+        }
+
+        // iterative depth first traversal, an edge to a block still on the path is a back edge
+        let mut back_edges = Vec::new();
+        let mut visited = BTreeSet::from([0]);
+        let mut on_path = BTreeSet::from([0]);
+        let mut stack: Vec<(usize, Vec<usize>)> = vec![(
+            0,
+            successors.get(&0).into_iter().flatten().copied().collect(),
+        )];
+        while let Some((block, pending)) = stack.last_mut() {
+            let block = *block;
+            match pending.pop() {
+                Some(next) if on_path.contains(&next) => back_edges.push((block, next)),
+                Some(next) if visited.insert(next) => {
+                    on_path.insert(next);
+                    stack.push((
+                        next,
+                        successors
+                            .get(&next)
+                            .into_iter()
+                            .flatten()
+                            .copied()
+                            .collect(),
+                    ));
+                }
+                Some(_) => {}
+                None => {
+                    on_path.remove(&block);
+                    stack.pop();
+                }
+            }
+        }
+
+        back_edges
+            .into_iter()
+            .map(|(from, header)| {
+                // the header plus every block reaching the back edge without going through it
+                let mut body = BTreeSet::from([header, from]);
+                let mut worklist = vec![from];
+                while let Some(block) = worklist.pop() {
+                    if block == header {
+                        continue;
+                    }
+                    for predecessor in predecessors.get(&block).into_iter().flatten() {
+                        if body.insert(*predecessor) {
+                            worklist.push(*predecessor);
+                        }
+                    }
+                }
+                CfgLoop {
+                    header,
+                    back_edge_from: from,
+                    body,
+                }
+            })
+            .collect()
+    }
+
+    // code is the code unit the cfg was built from
+    pub fn annotate<'a>(&'a self, code: &[Bytecode]) -> AnnotatedFunctionCfg<'a> {
+        AnnotatedFunctionCfg {
+            cfg: self,
+            block_instructions: self
+                .blocks
+                .iter()
+                .map(|block| {
+                    code[block.start_offset as usize..=block.end_offset as usize]
+                        .iter()
+                        .map(|instruction| format!("{:?}", instruction))
+                        .collect()
+                })
+                .collect(),
+        }
+    }
+
+    pub fn get_blocks(&self) -> &Vec<BasicBlock> {
+        &self.blocks
+    }
+    pub fn get_edges(&self) -> &Vec<CfgEdge> {
+        &self.edges
+    }
+    pub fn get_loops(&self) -> &Vec<CfgLoop> {
+        &self.loops
+    }
+    pub fn get_abort_sites(&self) -> &Vec<CfgAbortSite> {
+        &self.abort_sites
+    }
+}
+
+impl AnnotatedFunctionCfg<'_> {
+    // Graphviz rendering, loop headers are doubled and abort blocks are red
+    pub fn to_dot(&self, name: &str) -> String {
+        let loop_headers: BTreeSet<usize> = self
+            .cfg
+            .loops
+            .iter()
+            .map(|cfg_loop| cfg_loop.header)
+            .collect();
+        let abort_blocks: BTreeSet<usize> =
+            self.cfg.abort_sites.iter().map(|site| site.block).collect();
+        let mut lines = vec![
+            format!("digraph \"{}\" {{", name.replace('"', "\\\"")),
+            "  node [shape=box, fontname=\"monospace\"];".to_string(),
+        ];
+        for (block, instructions) in self.cfg.blocks.iter().zip(&self.block_instructions) {
+            let mut label = format!(
+                "B{} [{}..{}]\\l",
+                block.id, block.start_offset, block.end_offset
+            );
+            for (offset, instruction) in instructions.iter().enumerate() {
+                label.push_str(&format!(
+                    "{}: {}\\l",
+                    block.start_offset as usize + offset,
+                    instruction.replace('"', "\\\"")
+                ));
+            }
+            let mut attributes = vec![format!("label=\"{}\"", label)];
+            if loop_headers.contains(&block.id) {
+                attributes.push("peripheries=2".to_string());
+            }
+            if abort_blocks.contains(&block.id) {
+                attributes.push("color=red".to_string());
+            }
+            lines.push(format!("  b{} [{}];", block.id, attributes.join(", ")));
+        }
+        let back_edges: BTreeSet<(usize, usize)> = self
+            .cfg
+            .loops
+            .iter()
+            .map(|cfg_loop| (cfg_loop.back_edge_from, cfg_loop.header))
+            .collect();
+        for edge in &self.cfg.edges {
+            let label = match (edge.kind, edge.variant) {
+                (CfgEdgeKind::Fallthrough, _) | (CfgEdgeKind::Branch, _) => None,
+                (CfgEdgeKind::BranchTrue, _) => Some("true".to_string()),
+                (CfgEdgeKind::BranchFalse, _) => Some("false".to_string()),
+                (CfgEdgeKind::VariantSwitch, variant) => {
+                    Some(format!("variant {}", variant.unwrap_or_default()))
+                }
+            };
+            let mut attributes = Vec::new();
+            if let Some(label) = label {
+                attributes.push(format!("label=\"{}\"", label));
+            }
+            if edge.kind == CfgEdgeKind::Fallthrough {
+                attributes.push("style=dashed".to_string());
+            }
+            if back_edges.contains(&(edge.from, edge.to)) {
+                attributes.push("color=blue".to_string());
+            }
+            lines.push(format!(
+                "  b{} -> b{} [{}];",
+                edge.from,
+                edge.to,
+                attributes.join(", ")
+            ));
+        }
+        lines.push("}".to_string());
+        lines.join("\n")
+    }
+}
+
+impl PackageCfg {
+    pub fn get_package_id(&self) -> &str {
+        &self.package_id
+    }
+    pub fn get_module_cfgs(&self) -> &Vec<ModuleCfg> {
+        &self.module_cfgs
+    }
+}
+
+impl ModuleCfg {
+    pub fn get_module_name(&self) -> &str {
+        &self.module_name
+    }
+    pub fn get_function_cfgs(&self) -> &BTreeMap<String, FunctionCfg> {
+        &self.function_cfgs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use move_binary_format::file_format::{
+        EnumDefinitionIndex, SignatureIndex, VariantJumpTable, VariantJumpTableIndex,
+    };
+
+    fn code_unit(code: Vec<Bytecode>) -> CodeUnit {
+        CodeUnit {
+            locals: SignatureIndex(0),
+            code,
+            jump_tables: vec![],
+        }
+    }
+
+    fn block_ranges(function_cfg: &FunctionCfg) -> Vec<(CodeOffset, CodeOffset)> {
+        function_cfg
+            .blocks
+            .iter()
+            .map(|block| (block.start_offset, block.end_offset))
+            .collect()
+    }
+
+    fn edges(function_cfg: &FunctionCfg) -> Vec<(usize, usize, CfgEdgeKind, Option<usize>)> {
+        function_cfg
+            .edges
+            .iter()
+            .map(|edge| (edge.from, edge.to, edge.kind, edge.variant))
+            .collect()
+    }
+
+    #[test]
+    fn straight_line_code_is_one_block() {
+        let function_cfg = FunctionCfg::from(&code_unit(vec![
+            Bytecode::LdU64(1),
+            Bytecode::Pop,
+            Bytecode::Ret,
+        ]));
+        assert_eq!(block_ranges(&function_cfg), vec![(0, 2)]);
+        assert!(function_cfg.edges.is_empty());
+        assert!(function_cfg.loops.is_empty());
+        assert!(function_cfg.abort_sites.is_empty());
+    }
+
+    #[test]
+    fn while_loop() {
+        // while (true) { 1; }
+        let function_cfg = FunctionCfg::from(&code_unit(vec![
+            Bytecode::LdTrue,
+            Bytecode::BrFalse(5),
+            Bytecode::LdU64(1),
+            Bytecode::Pop,
+            Bytecode::Branch(0),
+            Bytecode::Ret,
+        ]));
+        assert_eq!(block_ranges(&function_cfg), vec![(0, 1), (2, 4), (5, 5)]);
+        assert_eq!(
+            edges(&function_cfg),
+            vec![
+                (0, 2, CfgEdgeKind::BranchFalse, None),
+                (0, 1, CfgEdgeKind::Fallthrough, None),
+                (1, 0, CfgEdgeKind::Branch, None),
+            ]
+        );
+        assert_eq!(function_cfg.loops.len(), 1);
+        let cfg_loop = &function_cfg.loops[0];
+        assert_eq!(cfg_loop.header, 0);
+        assert_eq!(cfg_loop.back_edge_from, 1);
+        assert_eq!(cfg_loop.body, BTreeSet::from([0, 1]));
+    }
+
+    #[test]
+    fn nested_loops() {
+        // outer loop 0..=6 around inner loop 2..=4
+        let function_cfg = FunctionCfg::from(&code_unit(vec![
+            Bytecode::LdTrue,
+            Bytecode::BrFalse(7),
+            Bytecode::LdTrue,
+            Bytecode::BrFalse(5),
+            Bytecode::Branch(2),
+            Bytecode::LdU64(1),
+            Bytecode::Branch(0),
+            Bytecode::Ret,
+        ]));
+        assert_eq!(
+            block_ranges(&function_cfg),
+            vec![(0, 1), (2, 3), (4, 4), (5, 6), (7, 7)]
+        );
+        let mut loops: Vec<(usize, usize, BTreeSet<usize>)> = function_cfg
+            .loops
+            .iter()
+            .map(|cfg_loop| {
+                (
+                    cfg_loop.header,
+                    cfg_loop.back_edge_from,
+                    cfg_loop.body.clone(),
+                )
+            })
+            .collect();
+        loops.sort();
+        assert_eq!(
+            loops,
+            vec![
+                (0, 3, BTreeSet::from([0, 1, 2, 3])),
+                (1, 2, BTreeSet::from([1, 2])),
+            ]
+        );
+    }
+
+    #[test]
+    fn abort_ends_block_without_fallthrough() {
+        // assert!(cond, 0)
+        let function_cfg = FunctionCfg::from(&code_unit(vec![
+            Bytecode::LdTrue,
+            Bytecode::BrTrue(4),
+            Bytecode::LdU64(0),
+            Bytecode::Abort,
+            Bytecode::Ret,
+        ]));
+        assert_eq!(block_ranges(&function_cfg), vec![(0, 1), (2, 3), (4, 4)]);
+        assert_eq!(
+            edges(&function_cfg),
+            vec![
+                (0, 2, CfgEdgeKind::BranchTrue, None),
+                (0, 1, CfgEdgeKind::Fallthrough, None),
+            ]
+        );
+        assert_eq!(function_cfg.abort_sites.len(), 1);
+        assert_eq!(function_cfg.abort_sites[0].block, 1);
+        assert_eq!(function_cfg.abort_sites[0].code_offset, 3);
+        assert!(function_cfg.loops.is_empty());
+    }
+
+    #[test]
+    fn variant_switch() {
+        let mut code_unit = code_unit(vec![
+            Bytecode::ImmBorrowLoc(0),
+            Bytecode::VariantSwitch(VariantJumpTableIndex(0)),
+            Bytecode::Ret,
+            Bytecode::Ret,
+        ]);
+        code_unit.jump_tables.push(VariantJumpTable {
+            head_enum: EnumDefinitionIndex(0),
+            jump_table: JumpTableInner::Full(vec![3, 2]),
+        });
+        let function_cfg = FunctionCfg::from(&code_unit);
+        assert_eq!(block_ranges(&function_cfg), vec![(0, 1), (2, 2), (3, 3)]);
+        assert_eq!(
+            edges(&function_cfg),
+            vec![
+                (0, 2, CfgEdgeKind::VariantSwitch, Some(0)),
+                (0, 1, CfgEdgeKind::VariantSwitch, Some(1)),
+            ]
+        );
+    }
+
+    #[test]
+    fn annotate_splits_instructions_by_block() {
+        let code_unit = code_unit(vec![
+            Bytecode::LdTrue,
+            Bytecode::BrFalse(3),
+            Bytecode::Ret,
+            Bytecode::Ret,
+        ]);
+        let function_cfg = FunctionCfg::from(&code_unit);
+        let annotated_cfg = function_cfg.annotate(&code_unit.code);
+        assert_eq!(
+            annotated_cfg.block_instructions,
+            vec![
+                vec!["LdTrue".to_string(), "BrFalse(3)".to_string()],
+                vec!["Ret".to_string()],
+                vec!["Ret".to_string()],
+            ]
+        );
+    }
+}
@@ -0,0 +1,104 @@
+use sui_json_rpc_types::{
+    SuiMoveNormalizedFunction, SuiMoveNormalizedStructType, SuiMoveNormalizedType,
+    SuiMoveVisibility,
+};
+use thiserror::Error;
+
+use crate::package_id_io::PackageIoError;
+
+pub mod move_interface;
+pub mod rust;
+pub mod typescript;
+
+#[derive(Error, Debug)]
+pub enum CodegenError {
+    #[error("Error loading package {0}: {1}")]
+    PackageLoadError(String, PackageIoError),
+    #[error("Invalid address {0}")]
+    InvalidAddress(String),
+    #[error("Datatype {0} not found in the store")]
+    UnresolvedDatatype(String),
+    #[error("Error writing {0}: {1}")]
+    IoError(String, std::io::Error),
+}
+
+// addresses as written by the normalized module format
+pub const MOVE_STDLIB_ADDRESS: &str = "0x1";
+pub const SUI_FRAMEWORK_ADDRESS: &str = "0x2";
+
+// functions that can be the target of a moveCall in a programmable transaction
+pub fn is_callable(function: &SuiMoveNormalizedFunction) -> bool {
+    matches!(function.visibility, SuiMoveVisibility::Public) || function.is_entry
+}
+
+// &TxContext and &mut TxContext are filled in by the runtime and never passed by the caller
+pub fn is_tx_context(t: &SuiMoveNormalizedType) -> bool {
+    match t {
+        SuiMoveNormalizedType::Reference(inner)
+        | SuiMoveNormalizedType::MutableReference(inner) => {
+            is_datatype(inner, SUI_FRAMEWORK_ADDRESS, "tx_context", "TxContext")
+        }
+        _ => false,
+    }
+}
+
+pub fn is_datatype(t: &SuiMoveNormalizedType, address: &str, module: &str, name: &str) -> bool {
+    match t {
+        SuiMoveNormalizedType::Struct { inner } => {
+            inner.address == address && inner.module == module && inner.name == name
+        }
+        _ => false,
+    }
+}
+
+// the parameters a caller has to provide, without the trailing TxContext
+pub fn caller_parameters(function: &SuiMoveNormalizedFunction) -> &[SuiMoveNormalizedType] {
+    match function.parameters.last() {
+        Some(last) if is_tx_context(last) => &function.parameters[..function.parameters.len() - 1],
+        _ => &function.parameters,
+    }
+}
+
+// Move source spelling of a type, with the addresses of the normalized module format
+pub fn format_move_type(t: &SuiMoveNormalizedType) -> String {
+    match t {
+        SuiMoveNormalizedType::Bool => "bool".to_string(),
+        SuiMoveNormalizedType::U8 => "u8".to_string(),
+        SuiMoveNormalizedType::U16 => "u16".to_string(),
+        SuiMoveNormalizedType::U32 => "u32".to_string(),
+        SuiMoveNormalizedType::U64 => "u64".to_string(),
+        SuiMoveNormalizedType::U128 => "u128".to_string(),
+        SuiMoveNormalizedType::U256 => "u256".to_string(),
+        SuiMoveNormalizedType::Address => "address".to_string(),
+        SuiMoveNormalizedType::Signer => "signer".to_string(),
+        SuiMoveNormalizedType::Vector(inner) => format!("vector<{}>", format_move_type(inner)),
+        SuiMoveNormalizedType::TypeParameter(index) => format!("T{}", index),
+        SuiMoveNormalizedType::Reference(inner) => format!("&{}", format_move_type(inner)),
+        SuiMoveNormalizedType::MutableReference(inner) => {
+            format!("&mut {}", format_move_type(inner))
+        }
+        SuiMoveNormalizedType::Struct { inner } => {
+            let SuiMoveNormalizedStructType {
+                address,
+                module,
+                name,
+                type_arguments,
+            } = &**inner;
+            if type_arguments.is_empty() {
+                format!("{}::{}::{}", address, module, name)
+            } else {
+                format!(
+                    "{}::{}::{}<{}>",
+                    address,
+                    module,
+                    name,
+                    type_arguments
+                        .iter()
+                        .map(format_move_type)
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )
+            }
+        }
+    }
+}
@@ -0,0 +1,436 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use move_core_types::account_address::AccountAddress;
+use sui_json_rpc_types::{
+    SuiMoveAbility, SuiMoveAbilitySet, SuiMoveNormalizedField, SuiMoveNormalizedModule,
+    SuiMoveNormalizedStructType, SuiMoveNormalizedType, SuiMoveStructTypeParameter,
+    SuiMoveVisibility,
+};
+use sui_types::base_types::ObjectID;
+use sui_types::{
+    is_system_package, BRIDGE_ADDRESS, DEEPBOOK_ADDRESS, SUI_FRAMEWORK_ADDRESS, SUI_SYSTEM_ADDRESS,
+};
+
+use crate::codegen::{format_move_type, CodegenError};
+use crate::normalized_modules::normalize_module;
+use crate::package_id_io::PackagesDir;
+
+// Move 2024 keywords, legacy packages may use them as identifiers
+const MOVE_KEYWORDS: [&str; 30] = [
+    "abort", "acquires", "as", "break", "const", "continue", "copy", "else", "enum", "false",
+    "for", "friend", "fun", "if", "let", "loop", "macro", "match", "module", "move", "mut",
+    "native", "public", "return", "spec", "struct", "true", "type", "use", "while",
+];
+
+// system packages by address, with their package name and directory in the Sui repository.
+// Sui is always a dependency, it brings MoveStdlib along
+const SYSTEM_PACKAGE_DEPENDENCIES: [(AccountAddress, &str, &str); 4] = [
+    (SUI_FRAMEWORK_ADDRESS, "Sui", "sui-framework"),
+    (SUI_SYSTEM_ADDRESS, "SuiSystem", "sui-system"),
+    (BRIDGE_ADDRESS, "Bridge", "bridge"),
+    (DEEPBOOK_ADDRESS, "DeepBook", "deepbook"),
+];
+
+// writes a compilable interface package for the package and, next to it, one for every
+// non-system package in its linkage table, at the versions the linkage table resolves to.
+// returns the written package directories
+pub fn write_move_interface(
+    packages_dir: &PackagesDir,
+    package_id: &str,
+    out_dir: &Path,
+) -> Result<Vec<PathBuf>, CodegenError> {
+    let bcs_json = packages_dir
+        .load_bcs_json(package_id)
+        .map_err(|e| CodegenError::PackageLoadError(package_id.to_string(), e))?;
+    let root_storage_id = ObjectID::from_hex_literal(package_id)
+        .map_err(|_| CodegenError::InvalidAddress(package_id.to_string()))?;
+    // modules are compiled at the original id, which the bytecode keeps across upgrades
+    let root_original_id = packages_dir
+        .load_package_modules(package_id)
+        .map_err(|e| CodegenError::PackageLoadError(package_id.to_string(), e))?
+        .values()
+        .next()
+        .map(|module| ObjectID::from(*module.address()))
+        .unwrap_or(root_storage_id);
+
+    // original id -> storage id of every package to generate
+    let mut packages: BTreeMap<ObjectID, ObjectID> = bcs_json
+        .get_linkage_table()
+        .iter()
+        .filter(|(original_id, _)| !is_system_package(**original_id))
+        .map(|(original_id, upgrade_info)| (*original_id, upgrade_info.upgraded_id))
+        .collect();
+    packages.insert(root_original_id, root_storage_id);
+
+    let mut written = Vec::new();
+    for (original_id, storage_id) in &packages {
+        let storage_id = storage_id.to_canonical_string(true);
+        let dependency_bcs_json = packages_dir
+            .load_bcs_json(&storage_id)
+            .map_err(|e| CodegenError::PackageLoadError(storage_id.clone(), e))?;
+        let compiled_modules = packages_dir
+            .load_package_modules(&storage_id)
+            .map_err(|e| CodegenError::PackageLoadError(storage_id.clone(), e))?;
+        let modules: BTreeMap<String, SuiMoveNormalizedModule> = compiled_modules
+            .iter()
+            .map(|(module_name, module)| (module_name.clone(), normalize_module(module)))
+            .collect();
+        let system_dependencies: BTreeSet<AccountAddress> = compiled_modules
+            .values()
+            .flat_map(|module| module.immediate_dependencies())
+            .map(|module_id| *module_id.address())
+            .collect();
+        // the root linkage table is authoritative, dependencies resolve to its versions
+        let dependencies: Vec<String> = dependency_bcs_json
+            .get_linkage_table()
+            .keys()
+            .filter(|dependency_id| packages.contains_key(dependency_id))
+            .map(|dependency_id| package_name(dependency_id))
+            .collect();
+
+        let package_name = package_name(original_id);
+        let package_dir = out_dir.join(&package_name);
+        let sources_dir = package_dir.join("sources");
+        fs::create_dir_all(&sources_dir)
+            .map_err(|e| CodegenError::IoError(package_dir.display().to_string(), e))?;
+        let move_toml = move_toml(
+            &package_name,
+            original_id,
+            &storage_id,
+            &system_dependencies,
+            &dependencies,
+        );
+        fs::write(package_dir.join("Move.toml"), move_toml)
+            .map_err(|e| CodegenError::IoError(package_dir.display().to_string(), e))?;
+        for (module_name, module) in &modules {
+            let module_file = sources_dir.join(format!("{}.move", module_name));
+            let module_source = module_source(
+                &package_name,
+                &storage_id,
+                dependency_bcs_json.get_version(),
+                module,
+            );
+            fs::write(&module_file, module_source)
+                .map_err(|e| CodegenError::IoError(module_file.display().to_string(), e))?;
+        }
+        written.push(package_dir);
+    }
+    Ok(written)
+}
+
+fn package_name(original_id: &ObjectID) -> String {
+    format!(
+        "pkg_{}",
+        AccountAddress::from(*original_id)
+            .to_hex_literal()
+            .trim_start_matches("0x")
+    )
+}
+
+// sources are compiled at the original id, the bytecode links against published-at
+fn move_toml(
+    package_name: &str,
+    original_id: &ObjectID,
+    storage_id: &str,
+    referenced_addresses: &BTreeSet<AccountAddress>,
+    dependencies: &[String],
+) -> String {
+    let mut lines = vec![
+        "[package]".to_string(),
+        format!("name = \"{}\"", package_name),
+        "edition = \"2024\"".to_string(),
+        format!("published-at = \"{}\"", storage_id),
+        String::new(),
+        "[dependencies]".to_string(),
+    ];
+    for (address, name, subdir) in SYSTEM_PACKAGE_DEPENDENCIES {
+        if address == SUI_FRAMEWORK_ADDRESS || referenced_addresses.contains(&address) {
+            lines.push(format!(
+                "{} = {{ git = \"https://github.com/MystenLabs/sui.git\", subdir = \"crates/sui-framework/packages/{}\", rev = \"framework/mainnet\" }}",
+                name, subdir
+            ));
+        }
+    }
+    for dependency in dependencies {
+        lines.push(format!(
+            "{} = {{ local = \"../{}\" }}",
+            dependency, dependency
+        ));
+    }
+    lines.push(String::new());
+    lines.push("[addresses]".to_string());
+    lines.push(format!(
+        "{} = \"{}\"",
+        package_name,
+        original_id.to_canonical_string(true)
+    ));
+    lines.push(String::new());
+    lines.join("\n")
+}
+
+// every datatype, since all of them can be used by other packages, and the public functions
+// with abort 0 bodies. other functions cannot be called from Move code outside the package
+fn module_source(
+    package_name: &str,
+    storage_id: &str,
+    version: u64,
+    module: &SuiMoveNormalizedModule,
+) -> String {
+    let mut lines = vec![
+        format!(
+            "// interface of package {} version {} generated by sui-packages-codegen, do not edit",
+            storage_id, version
+        ),
+        format!("module {}::{};", package_name, escape_name(&module.name)),
+        String::new(),
+    ];
+    for (struct_name, normalized_struct) in &module.structs {
+        lines.push(format!(
+            "public struct {}{}{} {{",
+            escape_name(struct_name),
+            datatype_type_parameters(&normalized_struct.type_parameters),
+            abilities(&normalized_struct.abilities)
+        ));
+        write_fields(&mut lines, &normalized_struct.fields, "    ");
+        lines.push("}".to_string());
+        lines.push(String::new());
+    }
+    for (enum_name, normalized_enum) in &module.enums {
+        lines.push(format!(
+            "public enum {}{}{} {{",
+            escape_name(enum_name),
+            datatype_type_parameters(&normalized_enum.type_parameters),
+            abilities(&normalized_enum.abilities)
+        ));
+        // variant order defines the variant tags, so it has to match the bytecode
+        let variant_names: Vec<&String> = match &normalized_enum.variant_declaration_order {
+            Some(order) => order.iter().collect(),
+            None => normalized_enum.variants.keys().collect(),
+        };
+        for variant_name in variant_names {
+            let fields = &normalized_enum.variants[variant_name];
+            if fields.is_empty() {
+                lines.push(format!("    {},", escape_name(variant_name)));
+                continue;
+            }
+            lines.push(format!("    {} {{", escape_name(variant_name)));
+            write_fields(&mut lines, fields, "        ");
+            lines.push("    },".to_string());
+        }
+        lines.push("}".to_string());
+        lines.push(String::new());
+    }
+    for (function_name, function) in &module.exposed_functions {
+        if !matches!(function.visibility, SuiMoveVisibility::Public) {
+            continue;
+        }
+        let type_parameters = if function.type_parameters.is_empty() {
+            String::new()
+        } else {
+            format!(
+                "<{}>",
+                function
+                    .type_parameters
+                    .iter()
+                    .enumerate()
+                    .map(|(index, constraints)| format!(
+                        "T{}{}",
+                        index,
+                        constraints_list(constraints)
+                    ))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )
+        };
+        let parameters = function
+            .parameters
+            .iter()
+            .enumerate()
+            .map(|(index, t)| format!("p{}: {}", index, source_type(t)))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let return_ = match function.return_.len() {
+            0 => String::new(),
+            1 => format!(": {}", source_type(&function.return_[0])),
+            _ => format!(
+                ": ({})",
+                function
+                    .return_
+                    .iter()
+                    .map(source_type)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        };
+        lines.push(format!(
+            "public {}fun {}{}({}){} {{",
+            if function.is_entry { "entry " } else { "" },
+            escape_name(function_name),
+            type_parameters,
+            parameters,
+            return_
+        ));
+        lines.push("    abort 0".to_string());
+        lines.push("}".to_string());
+        lines.push(String::new());
+    }
+    lines.join("\n")
+}
+
+fn write_fields(lines: &mut Vec<String>, fields: &[SuiMoveNormalizedField], indent: &str) {
+    for field in fields {
+        lines.push(format!(
+            "{}{}: {},",
+            indent,
+            escape_name(&field.name),
+            source_type(&field.type_)
+        ));
+    }
+}
+
+fn datatype_type_parameters(type_parameters: &[SuiMoveStructTypeParameter]) -> String {
+    if type_parameters.is_empty() {
+        return String::new();
+    }
+    format!(
+        "<{}>",
+        type_parameters
+            .iter()
+            .enumerate()
+            .map(|(index, type_parameter)| format!(
+                "{}T{}{}",
+                if type_parameter.is_phantom {
+                    "phantom "
+                } else {
+                    ""
+                },
+                index,
+                constraints_list(&type_parameter.constraints)
+            ))
+            .collect::<Vec<_>>()
+            .join(", ")
+    )
+}
+
+fn ability_names(abilities: &SuiMoveAbilitySet) -> Vec<&'static str> {
+    abilities
+        .abilities
+        .iter()
+        .map(|ability| match ability {
+            SuiMoveAbility::Copy => "copy",
+            SuiMoveAbility::Drop => "drop",
+            SuiMoveAbility::Store => "store",
+            SuiMoveAbility::Key => "key",
+        })
+        .collect()
+}
+
+fn abilities(abilities: &SuiMoveAbilitySet) -> String {
+    let names = ability_names(abilities);
+    if names.is_empty() {
+        String::new()
+    } else {
+        format!(" has {}", names.join(", "))
+    }
+}
+
+fn constraints_list(constraints: &SuiMoveAbilitySet) -> String {
+    let names = ability_names(constraints);
+    if names.is_empty() {
+        String::new()
+    } else {
+        format!(": {}", names.join(" + "))
+    }
+}
+
+// like format_move_type, with identifiers escaped for Move 2024
+fn source_type(t: &SuiMoveNormalizedType) -> String {
+    match t {
+        SuiMoveNormalizedType::Vector(inner) => format!("vector<{}>", source_type(inner)),
+        SuiMoveNormalizedType::Reference(inner) => format!("&{}", source_type(inner)),
+        SuiMoveNormalizedType::MutableReference(inner) => format!("&mut {}", source_type(inner)),
+        SuiMoveNormalizedType::Struct { inner } => {
+            let SuiMoveNormalizedStructType {
+                address,
+                module,
+                name,
+                type_arguments,
+            } = &**inner;
+            let path = format!(
+                "{}::{}::{}",
+                address,
+                escape_name(module),
+                escape_name(name)
+            );
+            if type_arguments.is_empty() {
+                path
+            } else {
+                format!(
+                    "{}<{}>",
+                    path,
+                    type_arguments
+                        .iter()
+                        .map(source_type)
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )
+            }
+        }
+        t => format_move_type(t),
+    }
+}
+
+fn escape_name(name: &str) -> String {
+    if MOVE_KEYWORDS.contains(&name) {
+        format!("`{}`", name)
+    } else {
+        name.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common_types::MovePackageWithMetadata;
+    use crate::test_utils::{test_dir, test_package, write_test_package, TestModuleBuilder};
+
+    #[test]
+    fn move_toml_depends_on_referenced_system_packages() {
+        let mut builder = TestModuleBuilder::new();
+        builder.add_function(
+            "stake",
+            0,
+            &[
+                ("0x3::sui_system", "request_add_stake", vec![]),
+                ("0xdee9::clob_v2", "swap_exact_base_for_quote", vec![]),
+            ],
+        );
+        let package = test_package(&[builder.build()], 1);
+        let package_id = package.id().to_canonical_string(true);
+        let prefix = test_dir("move_interface");
+        let packages_dir = PackagesDir::new(prefix.join("packages"));
+        write_test_package(
+            &packages_dir,
+            &MovePackageWithMetadata {
+                package,
+                checkpoint: 1,
+                transaction_digest: String::new(),
+                sender: None,
+            },
+        );
+
+        let package_dirs =
+            write_move_interface(&packages_dir, &package_id, &prefix.join("out")).unwrap();
+        assert_eq!(package_dirs.len(), 1);
+        let move_toml = fs::read_to_string(package_dirs[0].join("Move.toml")).unwrap();
+        let dependencies: Vec<&str> = move_toml
+            .lines()
+            .filter_map(|line| line.split_once(" = { git"))
+            .map(|(name, _)| name)
+            .collect();
+
+        assert_eq!(dependencies, vec!["Sui", "SuiSystem", "DeepBook"]);
+    }
+}
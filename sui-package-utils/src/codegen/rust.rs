@@ -0,0 +1,739 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use move_core_types::account_address::AccountAddress;
+use sui_json_rpc_types::{
+    SuiMoveNormalizedEnum, SuiMoveNormalizedField, SuiMoveNormalizedFunction,
+    SuiMoveNormalizedModule, SuiMoveNormalizedStruct, SuiMoveNormalizedStructType,
+    SuiMoveNormalizedType, SuiMoveStructTypeParameter,
+};
+use sui_types::base_types::ObjectID;
+use sui_types::move_package::UpgradeInfo;
+
+use crate::codegen::{
+    caller_parameters, format_move_type, is_callable, is_datatype, CodegenError,
+    MOVE_STDLIB_ADDRESS, SUI_FRAMEWORK_ADDRESS,
+};
+use crate::normalized_modules::normalize_module;
+use crate::package_id_io::PackagesDir;
+
+// Move identifiers that have to be escaped in Rust
+const RUST_KEYWORDS: [&str; 52] = [
+    "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "crate",
+    "do", "dyn", "else", "enum", "extern", "false", "final", "fn", "for", "gen", "if", "impl",
+    "in", "let", "loop", "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref",
+    "return", "self", "Self", "static", "struct", "super", "trait", "true", "try", "type",
+    "typeof", "unsafe", "unsized", "use", "virtual", "where", "while", "yield",
+];
+// keywords that cannot be raw identifiers
+const NON_RAW_KEYWORDS: [&str; 4] = ["crate", "self", "Self", "super"];
+
+const TYPE_TAG: &str = "move_core_types::language_storage::TypeTag";
+const ARGUMENT: &str = "sui_types::transaction::Argument";
+const IDENTIFIER: &str = "move_core_types::identifier::Identifier";
+
+// (address, module, name) of a datatype, with the address as written in the bytecode
+type DatatypeKey = (String, String, String);
+
+#[derive(Clone, Copy)]
+enum DatatypeDef<'a> {
+    Struct(&'a SuiMoveNormalizedStruct),
+    Enum(&'a SuiMoveNormalizedEnum),
+}
+
+impl<'a> DatatypeDef<'a> {
+    fn type_parameters(&self) -> &'a [SuiMoveStructTypeParameter] {
+        match *self {
+            DatatypeDef::Struct(normalized_struct) => &normalized_struct.type_parameters,
+            DatatypeDef::Enum(normalized_enum) => &normalized_enum.type_parameters,
+        }
+    }
+
+    // variants in declaration order, which is the BCS variant index
+    fn variants(&self) -> Vec<(&'a str, &'a [SuiMoveNormalizedField])> {
+        let DatatypeDef::Enum(normalized_enum) = *self else {
+            return Vec::new();
+        };
+        match &normalized_enum.variant_declaration_order {
+            Some(order) => order
+                .iter()
+                .filter_map(|name| {
+                    normalized_enum
+                        .variants
+                        .get(name)
+                        .map(|fields| (name.as_str(), fields.as_slice()))
+                })
+                .collect(),
+            None => normalized_enum
+                .variants
+                .iter()
+                .map(|(name, fields)| (name.as_str(), fields.as_slice()))
+                .collect(),
+        }
+    }
+
+    fn field_types(&self) -> Vec<&'a SuiMoveNormalizedType> {
+        match *self {
+            DatatypeDef::Struct(normalized_struct) => normalized_struct
+                .fields
+                .iter()
+                .map(|field| &field.type_)
+                .collect(),
+            DatatypeDef::Enum(_) => self
+                .variants()
+                .into_iter()
+                .flat_map(|(_, fields)| fields.iter().map(|field| &field.type_))
+                .collect(),
+        }
+    }
+}
+
+// modules of the package and of the dependencies its datatypes reach, loaded from the store
+struct DatatypeResolver<'a> {
+    packages_dir: &'a PackagesDir,
+    linkage_table: &'a BTreeMap<ObjectID, UpgradeInfo>,
+    root_address: String,
+    packages: BTreeMap<String, BTreeMap<String, SuiMoveNormalizedModule>>,
+}
+
+impl DatatypeResolver<'_> {
+    fn load_package(&mut self, address: &str) -> Result<(), CodegenError> {
+        if self.packages.contains_key(address) {
+            return Ok(());
+        }
+        let original_id = ObjectID::from(
+            AccountAddress::from_hex_literal(address)
+                .map_err(|_| CodegenError::InvalidAddress(address.to_string()))?,
+        );
+        // system packages are upgraded in place and have no linkage table entry
+        let storage_id = self
+            .linkage_table
+            .get(&original_id)
+            .map(|upgrade_info| upgrade_info.upgraded_id)
+            .unwrap_or(original_id);
+        let modules =
+            load_normalized_modules(self.packages_dir, &storage_id.to_canonical_string(true))?;
+        self.packages.insert(address.to_string(), modules);
+        Ok(())
+    }
+
+    fn get(&self, (address, module, name): &DatatypeKey) -> Option<DatatypeDef<'_>> {
+        let module = self.packages.get(address)?.get(module)?;
+        module
+            .structs
+            .get(name)
+            .map(DatatypeDef::Struct)
+            .or_else(|| module.enums.get(name).map(DatatypeDef::Enum))
+    }
+
+    // type parameters that survive in Rust: phantom and unused ones have no Rust counterpart
+    fn kept_type_parameters(&self, key: &DatatypeKey) -> Vec<usize> {
+        let Some(def) = self.get(key) else {
+            return Vec::new();
+        };
+        let mut used = BTreeSet::new();
+        for t in def.field_types() {
+            self.collect_used_type_parameters(t, &mut used);
+        }
+        (0..def.type_parameters().len())
+            .filter(|index| used.contains(index))
+            .collect()
+    }
+
+    fn collect_used_type_parameters(&self, t: &SuiMoveNormalizedType, used: &mut BTreeSet<usize>) {
+        match t {
+            SuiMoveNormalizedType::TypeParameter(index) => {
+                used.insert(*index as usize);
+            }
+            SuiMoveNormalizedType::Vector(inner)
+            | SuiMoveNormalizedType::Reference(inner)
+            | SuiMoveNormalizedType::MutableReference(inner) => {
+                self.collect_used_type_parameters(inner, used)
+            }
+            SuiMoveNormalizedType::Struct { inner } if is_option(t) => {
+                if let Some(type_argument) = inner.type_arguments.first() {
+                    self.collect_used_type_parameters(type_argument, used);
+                }
+            }
+            _ if is_builtin(t) => {}
+            SuiMoveNormalizedType::Struct { inner } => {
+                let SuiMoveNormalizedStructType {
+                    address,
+                    module,
+                    name,
+                    type_arguments,
+                } = &**inner;
+                let key = (address.clone(), module.clone(), name.clone());
+                for index in self.kept_type_parameters(&key) {
+                    if let Some(type_argument) = type_arguments.get(index) {
+                        self.collect_used_type_parameters(type_argument, used);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn load_normalized_modules(
+    packages_dir: &PackagesDir,
+    package_id: &str,
+) -> Result<BTreeMap<String, SuiMoveNormalizedModule>, CodegenError> {
+    Ok(packages_dir
+        .load_package_modules(package_id)
+        .map_err(|e| CodegenError::PackageLoadError(package_id.to_string(), e))?
+        .iter()
+        .map(|(module_name, module)| (module_name.clone(), normalize_module(module)))
+        .collect())
+}
+
+// one Rust module per package: a module per Move module with a BCS compatible serde type per
+// datatype and a programmable transaction call builder per public or entry function.
+// datatypes of dependencies used in fields are generated under `external`
+pub fn generate_rust(packages_dir: &PackagesDir, package_id: &str) -> Result<String, CodegenError> {
+    let bcs_json = packages_dir
+        .load_bcs_json(package_id)
+        .map_err(|e| CodegenError::PackageLoadError(package_id.to_string(), e))?;
+    let modules = load_normalized_modules(packages_dir, package_id)?;
+    let root_address = modules
+        .values()
+        .next()
+        .map(|module| module.address.clone())
+        .unwrap_or_default();
+    let type_origins: BTreeMap<(String, String), String> = bcs_json
+        .get_type_origin_table()
+        .iter()
+        .map(|type_origin| {
+            (
+                (
+                    type_origin.module_name.clone(),
+                    type_origin.datatype_name.clone(),
+                ),
+                type_origin.package.to_canonical_string(true),
+            )
+        })
+        .collect();
+
+    let mut pending: Vec<DatatypeKey> = modules
+        .values()
+        .flat_map(|module| {
+            module
+                .structs
+                .keys()
+                .chain(module.enums.keys())
+                .map(|name| (root_address.clone(), module.name.clone(), name.clone()))
+        })
+        .collect();
+    let mut resolver = DatatypeResolver {
+        packages_dir,
+        linkage_table: bcs_json.get_linkage_table(),
+        root_address: root_address.clone(),
+        packages: BTreeMap::from([(root_address.clone(), modules)]),
+    };
+    let mut datatypes: BTreeSet<DatatypeKey> = pending.iter().cloned().collect();
+    while let Some(key) = pending.pop() {
+        let field_types: Vec<SuiMoveNormalizedType> = resolver
+            .get(&key)
+            .ok_or_else(|| CodegenError::UnresolvedDatatype(format_key(&key)))?
+            .field_types()
+            .into_iter()
+            .cloned()
+            .collect();
+        let mut referenced = Vec::new();
+        for t in &field_types {
+            collect_datatypes(&mut resolver, t, &mut referenced)?;
+        }
+        for referenced_key in referenced {
+            if datatypes.insert(referenced_key.clone()) {
+                pending.push(referenced_key);
+            }
+        }
+    }
+
+    let mut lines = vec![
+        format!(
+            "// generated by sui-packages-codegen from package {} version {}, do not edit",
+            package_id,
+            bcs_json.get_version()
+        ),
+        "#![allow(clippy::all, dead_code, non_camel_case_types, non_snake_case)]".to_string(),
+        String::new(),
+        format!("pub const PACKAGE_ID: &str = \"{}\";", package_id),
+        String::new(),
+    ];
+    for (module_name, module) in resolver.packages.get(&root_address).into_iter().flatten() {
+        lines.push(format!("pub mod {} {{", escape_name(module_name)));
+        for key in datatypes
+            .iter()
+            .filter(|(address, module, _)| *address == root_address && module == module_name)
+        {
+            write_datatype(
+                &mut lines,
+                &resolver,
+                key,
+                1,
+                type_origins.get(&(key.1.clone(), key.2.clone())),
+            );
+        }
+        for (function_name, function) in &module.exposed_functions {
+            if is_callable(function) {
+                write_call_builder(&mut lines, module_name, function_name, function);
+            }
+        }
+        lines.push("}".to_string());
+        lines.push(String::new());
+    }
+
+    let external_datatypes: Vec<&DatatypeKey> = datatypes
+        .iter()
+        .filter(|(address, _, _)| *address != root_address)
+        .collect();
+    if !external_datatypes.is_empty() {
+        lines.push("pub mod external {".to_string());
+        let mut current_module: Option<(&str, &str)> = None;
+        for key in external_datatypes {
+            let (address, module, _) = key;
+            if current_module.map(|(a, _)| a) != Some(address.as_str()) {
+                if current_module.is_some() {
+                    lines.push("        }".to_string());
+                    lines.push("    }".to_string());
+                }
+                lines.push(format!("    pub mod {} {{", package_module_name(address)));
+                lines.push(format!("        pub mod {} {{", escape_name(module)));
+            } else if current_module.map(|(_, m)| m) != Some(module.as_str()) {
+                lines.push("        }".to_string());
+                lines.push(format!("        pub mod {} {{", escape_name(module)));
+            }
+            current_module = Some((address, module));
+            write_datatype(&mut lines, &resolver, key, 3, None);
+        }
+        lines.push("        }".to_string());
+        lines.push("    }".to_string());
+        lines.push("}".to_string());
+    }
+    Ok(lines.join("\n"))
+}
+
+// datatypes a type refers to that need a generated Rust type, phantom arguments are skipped
+fn collect_datatypes(
+    resolver: &mut DatatypeResolver,
+    t: &SuiMoveNormalizedType,
+    referenced: &mut Vec<DatatypeKey>,
+) -> Result<(), CodegenError> {
+    match t {
+        SuiMoveNormalizedType::Vector(inner)
+        | SuiMoveNormalizedType::Reference(inner)
+        | SuiMoveNormalizedType::MutableReference(inner) => {
+            collect_datatypes(resolver, inner, referenced)
+        }
+        SuiMoveNormalizedType::Struct { inner } if is_option(t) => {
+            match inner.type_arguments.first() {
+                Some(type_argument) => collect_datatypes(resolver, type_argument, referenced),
+                None => Ok(()),
+            }
+        }
+        _ if is_builtin(t) => Ok(()),
+        SuiMoveNormalizedType::Struct { inner } => {
+            let SuiMoveNormalizedStructType {
+                address,
+                module,
+                name,
+                type_arguments,
+            } = &**inner;
+            resolver.load_package(address)?;
+            let key = (address.clone(), module.clone(), name.clone());
+            let phantoms: Vec<bool> = resolver
+                .get(&key)
+                .ok_or_else(|| CodegenError::UnresolvedDatatype(format_key(&key)))?
+                .type_parameters()
+                .iter()
+                .map(|type_parameter| type_parameter.is_phantom)
+                .collect();
+            referenced.push(key);
+            for (type_argument, is_phantom) in type_arguments.iter().zip(phantoms) {
+                if !is_phantom {
+                    collect_datatypes(resolver, type_argument, referenced)?;
+                }
+            }
+            Ok(())
+        }
+        _ => Ok(()),
+    }
+}
+
+fn write_datatype(
+    lines: &mut Vec<String>,
+    resolver: &DatatypeResolver,
+    key: &DatatypeKey,
+    depth: usize,
+    type_origin: Option<&String>,
+) {
+    let Some(def) = resolver.get(key) else {
+        return;
+    };
+    let indent = "    ".repeat(depth);
+    let (_, module_name, name) = key;
+    let generics = generic_list(&resolver.kept_type_parameters(key));
+    lines.push(format!(
+        "{}#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]",
+        indent
+    ));
+    match &def {
+        DatatypeDef::Struct(normalized_struct) => {
+            lines.push(format!(
+                "{}pub struct {}{} {{",
+                indent,
+                escape_name(name),
+                generics
+            ));
+            for field in &normalized_struct.fields {
+                lines.push(format!(
+                    "{}    pub {}: {},",
+                    indent,
+                    escape_name(&field.name),
+                    rust_type(resolver, &field.type_, depth)
+                ));
+            }
+        }
+        DatatypeDef::Enum(_) => {
+            lines.push(format!(
+                "{}pub enum {}{} {{",
+                indent,
+                escape_name(name),
+                generics
+            ));
+            for (variant_name, fields) in def.variants() {
+                if fields.is_empty() {
+                    lines.push(format!("{}    {},", indent, escape_name(variant_name)));
+                    continue;
+                }
+                lines.push(format!("{}    {} {{", indent, escape_name(variant_name)));
+                for field in fields {
+                    lines.push(format!(
+                        "{}        {}: {},",
+                        indent,
+                        escape_name(&field.name),
+                        rust_type(resolver, &field.type_, depth)
+                    ));
+                }
+                lines.push(format!("{}    }},", indent));
+            }
+        }
+    }
+    lines.push(format!("{}}}", indent));
+
+    // the canonical type tag uses the package version that introduced the type, and includes
+    // phantom type arguments
+    if let Some(type_origin) = type_origin {
+        let type_parameter_count = def.type_parameters().len();
+        lines.push(format!(
+            "{}impl{} {}{} {{",
+            indent,
+            generics,
+            escape_name(name),
+            generics
+        ));
+        lines.push(format!(
+            "{}    pub fn struct_tag(type_params: [{}; {}]) -> move_core_types::language_storage::StructTag {{",
+            indent, TYPE_TAG, type_parameter_count
+        ));
+        lines.push(format!(
+            "{}        move_core_types::language_storage::StructTag {{",
+            indent
+        ));
+        lines.push(format!(
+            "{}            address: move_core_types::account_address::AccountAddress::from_hex_literal(\"{}\").unwrap(),",
+            indent, type_origin
+        ));
+        lines.push(format!(
+            "{}            module: {}::new(\"{}\").unwrap(),",
+            indent, IDENTIFIER, module_name
+        ));
+        lines.push(format!(
+            "{}            name: {}::new(\"{}\").unwrap(),",
+            indent, IDENTIFIER, name
+        ));
+        lines.push(format!(
+            "{}            type_params: type_params.to_vec(),",
+            indent
+        ));
+        lines.push(format!("{}        }}", indent));
+        lines.push(format!("{}    }}", indent));
+        lines.push(format!("{}}}", indent));
+    }
+    lines.push(String::new());
+}
+
+fn write_call_builder(
+    lines: &mut Vec<String>,
+    module_name: &str,
+    function_name: &str,
+    function: &SuiMoveNormalizedFunction,
+) {
+    let parameters = caller_parameters(function);
+    lines.push(format!(
+        "    // {}({}){}",
+        function_name,
+        parameters
+            .iter()
+            .map(format_move_type)
+            .collect::<Vec<_>>()
+            .join(", "),
+        match function.return_.len() {
+            0 => String::new(),
+            1 => format!(": {}", format_move_type(&function.return_[0])),
+            _ => format!(
+                ": ({})",
+                function
+                    .return_
+                    .iter()
+                    .map(format_move_type)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        }
+    ));
+    lines.push(format!("    pub fn {}(", escape_name(function_name)));
+    lines.push(
+        "        builder: &mut sui_types::programmable_transaction_builder::ProgrammableTransactionBuilder,"
+            .to_string(),
+    );
+    if !function.type_parameters.is_empty() {
+        lines.push(format!(
+            "        type_arguments: [{}; {}],",
+            TYPE_TAG,
+            function.type_parameters.len()
+        ));
+    }
+    for index in 0..parameters.len() {
+        lines.push(format!("        arg{}: {},", index, ARGUMENT));
+    }
+    lines.push(format!("    ) -> {} {{", ARGUMENT));
+    lines.push("        builder.programmable_move_call(".to_string());
+    lines.push(
+        "            sui_types::base_types::ObjectID::from_hex_literal(super::PACKAGE_ID).unwrap(),"
+            .to_string(),
+    );
+    lines.push(format!(
+        "            {}::new(\"{}\").unwrap(),",
+        IDENTIFIER, module_name
+    ));
+    lines.push(format!(
+        "            {}::new(\"{}\").unwrap(),",
+        IDENTIFIER, function_name
+    ));
+    lines.push(if function.type_parameters.is_empty() {
+        "            vec![],".to_string()
+    } else {
+        "            type_arguments.to_vec(),".to_string()
+    });
+    lines.push(format!(
+        "            vec![{}],",
+        (0..parameters.len())
+            .map(|index| format!("arg{}", index))
+            .collect::<Vec<_>>()
+            .join(", ")
+    ));
+    lines.push("        )".to_string());
+    lines.push("    }".to_string());
+    lines.push(String::new());
+}
+
+// paths are relative to the generated file so it can be mounted anywhere in a crate
+fn rust_type(resolver: &DatatypeResolver, t: &SuiMoveNormalizedType, depth: usize) -> String {
+    match t {
+        SuiMoveNormalizedType::Bool => "bool".to_string(),
+        SuiMoveNormalizedType::U8 => "u8".to_string(),
+        SuiMoveNormalizedType::U16 => "u16".to_string(),
+        SuiMoveNormalizedType::U32 => "u32".to_string(),
+        SuiMoveNormalizedType::U64 => "u64".to_string(),
+        SuiMoveNormalizedType::U128 => "u128".to_string(),
+        SuiMoveNormalizedType::U256 => "move_core_types::u256::U256".to_string(),
+        SuiMoveNormalizedType::Address | SuiMoveNormalizedType::Signer => {
+            "move_core_types::account_address::AccountAddress".to_string()
+        }
+        SuiMoveNormalizedType::Vector(inner) => {
+            format!("::std::vec::Vec<{}>", rust_type(resolver, inner, depth))
+        }
+        SuiMoveNormalizedType::TypeParameter(index) => format!("T{}", index),
+        SuiMoveNormalizedType::Reference(inner)
+        | SuiMoveNormalizedType::MutableReference(inner) => rust_type(resolver, inner, depth),
+        // a Move Option is a vector of at most one element, which BCS encodes like a Rust Option
+        SuiMoveNormalizedType::Struct { inner } if is_option(t) => format!(
+            "::std::option::Option<{}>",
+            inner
+                .type_arguments
+                .first()
+                .map(|type_argument| rust_type(resolver, type_argument, depth))
+                .unwrap_or_else(|| "()".to_string())
+        ),
+        t if is_datatype(t, MOVE_STDLIB_ADDRESS, "string", "String")
+            || is_datatype(t, MOVE_STDLIB_ADDRESS, "ascii", "String") =>
+        {
+            "::std::string::String".to_string()
+        }
+        t if is_datatype(t, SUI_FRAMEWORK_ADDRESS, "object", "ID") => {
+            "sui_types::id::ID".to_string()
+        }
+        t if is_datatype(t, SUI_FRAMEWORK_ADDRESS, "object", "UID") => {
+            "sui_types::id::UID".to_string()
+        }
+        t if is_datatype(t, SUI_FRAMEWORK_ADDRESS, "balance", "Balance") => {
+            "sui_types::balance::Balance".to_string()
+        }
+        SuiMoveNormalizedType::Struct { inner } => {
+            let SuiMoveNormalizedStructType {
+                address,
+                module,
+                name,
+                type_arguments,
+            } = &**inner;
+            let key = (address.clone(), module.clone(), name.clone());
+            let path = if *address == resolver.root_address {
+                format!(
+                    "{}{}::{}",
+                    "super::".repeat(depth),
+                    escape_name(module),
+                    escape_name(name)
+                )
+            } else {
+                format!(
+                    "{}external::{}::{}::{}",
+                    "super::".repeat(depth),
+                    package_module_name(address),
+                    escape_name(module),
+                    escape_name(name)
+                )
+            };
+            let kept_type_arguments: Vec<String> = resolver
+                .kept_type_parameters(&key)
+                .into_iter()
+                .filter_map(|index| type_arguments.get(index))
+                .map(|type_argument| rust_type(resolver, type_argument, depth))
+                .collect();
+            if kept_type_arguments.is_empty() {
+                path
+            } else {
+                format!("{}<{}>", path, kept_type_arguments.join(", "))
+            }
+        }
+    }
+}
+
+fn is_option(t: &SuiMoveNormalizedType) -> bool {
+    is_datatype(t, MOVE_STDLIB_ADDRESS, "option", "Option")
+}
+
+// framework types mapped to existing Rust types instead of being generated
+fn is_builtin(t: &SuiMoveNormalizedType) -> bool {
+    is_option(t)
+        || is_datatype(t, MOVE_STDLIB_ADDRESS, "string", "String")
+        || is_datatype(t, MOVE_STDLIB_ADDRESS, "ascii", "String")
+        || is_datatype(t, SUI_FRAMEWORK_ADDRESS, "object", "ID")
+        || is_datatype(t, SUI_FRAMEWORK_ADDRESS, "object", "UID")
+        || is_datatype(t, SUI_FRAMEWORK_ADDRESS, "balance", "Balance")
+}
+
+fn generic_list(type_parameters: &[usize]) -> String {
+    if type_parameters.is_empty() {
+        return String::new();
+    }
+    format!(
+        "<{}>",
+        type_parameters
+            .iter()
+            .map(|index| format!("T{}", index))
+            .collect::<Vec<_>>()
+            .join(", ")
+    )
+}
+
+// the Rust module name of a package, also the file name main_codegen writes it to
+pub fn package_module_name(address: &str) -> String {
+    format!("pkg_{}", address.trim_start_matches("0x"))
+}
+
+fn format_key((address, module, name): &DatatypeKey) -> String {
+    format!("{}::{}::{}", address, module, name)
+}
+
+fn escape_name(name: &str) -> String {
+    if NON_RAW_KEYWORDS.contains(&name) {
+        format!("{}_", name)
+    } else if RUST_KEYWORDS.contains(&name) {
+        format!("r#{}", name)
+    } else {
+        name.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use move_binary_format::file_format::{
+        Ability, AbilitySet, DatatypeTyParameter, SignatureToken,
+    };
+
+    use super::*;
+    use crate::common_types::MovePackageWithMetadata;
+    use crate::test_utils::{test_dir, test_package, write_test_package, TestModuleBuilder};
+
+    #[test]
+    fn datatypes_in_declaration_order_without_unused_type_parameters() {
+        let mut builder = TestModuleBuilder::new();
+        let type_parameter = |is_phantom| DatatypeTyParameter {
+            constraints: AbilitySet::EMPTY,
+            is_phantom,
+        };
+        builder.add_struct(
+            "Holder",
+            AbilitySet::EMPTY | Ability::Store,
+            vec![
+                type_parameter(true),
+                type_parameter(false),
+                type_parameter(false),
+            ],
+            &[(
+                "items",
+                SignatureToken::Vector(Box::new(SignatureToken::TypeParameter(2))),
+            )],
+        );
+        builder.add_enum(
+            "Status",
+            AbilitySet::EMPTY | Ability::Store,
+            vec![],
+            &[
+                ("Zero", vec![]),
+                ("Active", vec![("since", SignatureToken::U64)]),
+                ("Closed", vec![]),
+            ],
+        );
+        let package = test_package(&[builder.build()], 1);
+        let package_id = package.id().to_canonical_string(true);
+        let packages_dir = PackagesDir::new(test_dir("codegen_rust"));
+        write_test_package(
+            &packages_dir,
+            &MovePackageWithMetadata {
+                package,
+                checkpoint: 1,
+                transaction_digest: String::new(),
+                sender: None,
+            },
+        );
+
+        let rust = generate_rust(&packages_dir, &package_id).unwrap();
+        let lines: Vec<&str> = rust.lines().map(str::trim).collect();
+
+        // the phantom and the unused type parameter are dropped
+        assert!(lines.contains(&"pub struct Holder<T2> {"));
+        assert!(lines.contains(&"pub items: ::std::vec::Vec<T2>,"));
+        // variants keep their declaration order, which is the BCS variant index
+        let variant_position =
+            |variant: &str| lines.iter().position(|line| line.starts_with(variant));
+        assert!(lines.contains(&"pub enum Status {"));
+        assert!(variant_position("Zero,") < variant_position("Active {"));
+        assert!(variant_position("Active {") < variant_position("Closed,"));
+        assert_eq!(
+            package_module_name(&package_id),
+            format!("pkg_{}", package_id.trim_start_matches("0x"))
+        );
+    }
+}
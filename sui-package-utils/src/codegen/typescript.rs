@@ -0,0 +1,549 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use move_binary_format::file_format::CompiledModule;
+use sui_json_rpc_types::{
+    SuiMoveNormalizedFunction, SuiMoveNormalizedModule, SuiMoveNormalizedStruct,
+    SuiMoveNormalizedStructType, SuiMoveNormalizedType,
+};
+use sui_types::move_package::MovePackage;
+
+use crate::codegen::{
+    caller_parameters, is_callable, is_datatype, MOVE_STDLIB_ADDRESS, SUI_FRAMEWORK_ADDRESS,
+};
+use crate::normalized_modules::PackageNormalizedModules;
+
+// Move identifiers that cannot be used as TypeScript namespace or function names
+const RESERVED_WORDS: [&str; 46] = [
+    "await",
+    "break",
+    "case",
+    "catch",
+    "class",
+    "const",
+    "continue",
+    "debugger",
+    "default",
+    "delete",
+    "do",
+    "else",
+    "enum",
+    "export",
+    "extends",
+    "false",
+    "finally",
+    "for",
+    "function",
+    "if",
+    "implements",
+    "import",
+    "in",
+    "instanceof",
+    "interface",
+    "let",
+    "new",
+    "null",
+    "package",
+    "private",
+    "protected",
+    "public",
+    "return",
+    "static",
+    "super",
+    "switch",
+    "this",
+    "throw",
+    "true",
+    "try",
+    "typeof",
+    "var",
+    "void",
+    "while",
+    "with",
+    "yield",
+];
+
+// helpers emitted once per file, prefixed with $ since Move identifiers cannot contain it
+const TYPESCRIPT_HELPERS: &str = r#"function $isTransactionArgument(arg: unknown): arg is TransactionArgument {
+  return typeof arg === "function" || (typeof arg === "object" && arg !== null && "$kind" in arg);
+}
+
+function $pure<T>(tx: Transaction, schema: BcsType<any, T>, arg: T | TransactionArgument): TransactionArgument {
+  return $isTransactionArgument(arg) ? arg : tx.pure(schema.serialize(arg as T));
+}
+"#;
+
+// how a Move parameter is passed to moveCall
+enum ArgumentKind {
+    // BCS schema and the TypeScript type it accepts
+    Pure(String, String),
+    Object,
+    ObjectVector,
+    // type parameters and anything else only available as a result of another call
+    Raw,
+}
+
+// one TypeScript module per package: a namespace per Move module with an interface per struct
+// and a moveCall builder per public or entry function
+pub fn generate_typescript(pkg: &MovePackage) -> String {
+    let normalized_modules = PackageNormalizedModules::from(pkg);
+    let type_origins: BTreeMap<(String, String), String> = pkg
+        .type_origin_table()
+        .iter()
+        .map(|type_origin| {
+            (
+                (
+                    type_origin.module_name.clone(),
+                    type_origin.datatype_name.clone(),
+                ),
+                type_origin.package.to_canonical_string(true),
+            )
+        })
+        .collect();
+
+    // (address, module, name) of the datatypes with the key ability. Every datatype the package
+    // refers to has a handle carrying its abilities, including the ones of other packages
+    let mut key_types: BTreeSet<(String, String, String)> = BTreeSet::new();
+    for module_bytes in pkg.serialized_module_map().values() {
+        let module = CompiledModule::deserialize_with_defaults(module_bytes).unwrap();
+        for datatype_handle in module.datatype_handles() {
+            if !datatype_handle.abilities.has_key() {
+                continue;
+            }
+            let module_handle = module.module_handle_at(datatype_handle.module);
+            key_types.insert((
+                module
+                    .address_identifier_at(module_handle.address)
+                    .to_hex_literal(),
+                module.identifier_at(module_handle.name).to_string(),
+                module.identifier_at(datatype_handle.name).to_string(),
+            ));
+        }
+    }
+
+    let mut lines = vec![
+        format!(
+            "// generated by sui-packages-codegen from package {} version {}, do not edit",
+            pkg.id().to_canonical_string(true),
+            pkg.version().value()
+        ),
+        "import { bcs, type BcsType } from \"@mysten/sui/bcs\";".to_string(),
+        "import type {".to_string(),
+        "  Transaction,".to_string(),
+        "  TransactionArgument,".to_string(),
+        "  TransactionObjectInput,".to_string(),
+        "  TransactionResult,".to_string(),
+        "} from \"@mysten/sui/transactions\";".to_string(),
+        String::new(),
+        format!(
+            "export const PACKAGE_ID = \"{}\";",
+            pkg.id().to_canonical_string(true)
+        ),
+        format!(
+            "export const ORIGINAL_PACKAGE_ID = \"{}\";",
+            pkg.original_package_id().to_canonical_string(true)
+        ),
+        String::new(),
+        TYPESCRIPT_HELPERS.to_string(),
+    ];
+    for (module_name, module) in &normalized_modules.0 {
+        lines.push(format!("export namespace {} {{", escape_name(module_name)));
+        for (struct_name, normalized_struct) in &module.structs {
+            write_struct(
+                &mut lines,
+                &normalized_modules,
+                module,
+                struct_name,
+                normalized_struct,
+                type_origins.get(&(module_name.clone(), struct_name.clone())),
+            );
+        }
+        for (function_name, function) in &module.exposed_functions {
+            if is_callable(function) {
+                write_function(&mut lines, &key_types, module_name, function_name, function);
+            }
+        }
+        lines.push("}".to_string());
+        lines.push(String::new());
+    }
+    lines.join("\n")
+}
+
+fn write_struct(
+    lines: &mut Vec<String>,
+    normalized_modules: &PackageNormalizedModules,
+    module: &SuiMoveNormalizedModule,
+    struct_name: &str,
+    normalized_struct: &SuiMoveNormalizedStruct,
+    type_origin: Option<&String>,
+) {
+    lines.push(format!(
+        "  export interface {}{} {{",
+        struct_name,
+        type_parameter_list(normalized_struct.type_parameters.len())
+    ));
+    for field in &normalized_struct.fields {
+        lines.push(format!(
+            "    {}: {};",
+            field.name,
+            field_type(normalized_modules, module, &field.type_)
+        ));
+    }
+    lines.push("  }".to_string());
+    // the type is defined by the package version that introduced it, not the current one
+    if let Some(type_origin) = type_origin {
+        lines.push(format!(
+            "  export const {}Type = \"{}::{}::{}\";",
+            struct_name, type_origin, module.name, struct_name
+        ));
+    }
+    lines.push(String::new());
+}
+
+fn write_function(
+    lines: &mut Vec<String>,
+    key_types: &BTreeSet<(String, String, String)>,
+    module_name: &str,
+    function_name: &str,
+    function: &SuiMoveNormalizedFunction,
+) {
+    let arguments: Vec<ArgumentKind> = caller_parameters(function)
+        .iter()
+        .map(|t| argument_kind(key_types, t))
+        .collect();
+    lines.push(format!("  export function {}(", escape_name(function_name)));
+    lines.push("    tx: Transaction,".to_string());
+    for (index, argument) in arguments.iter().enumerate() {
+        let ts_type = match argument {
+            ArgumentKind::Pure(_, ts_type) => format!("{} | TransactionArgument", ts_type),
+            ArgumentKind::Object => "TransactionObjectInput".to_string(),
+            ArgumentKind::ObjectVector => {
+                "TransactionObjectInput[] | TransactionArgument".to_string()
+            }
+            ArgumentKind::Raw => "TransactionArgument".to_string(),
+        };
+        lines.push(format!("    arg{}: {},", index, ts_type));
+    }
+    if !function.type_parameters.is_empty() {
+        lines.push(format!(
+            "    typeArguments: [{}],",
+            vec!["string"; function.type_parameters.len()].join(", ")
+        ));
+    }
+    lines.push("  ): TransactionResult {".to_string());
+    lines.push("    return tx.moveCall({".to_string());
+    lines.push(format!(
+        "      target: `${{PACKAGE_ID}}::{}::{}`,",
+        module_name, function_name
+    ));
+    if !function.type_parameters.is_empty() {
+        lines.push("      typeArguments,".to_string());
+    }
+    lines.push("      arguments: [".to_string());
+    for (index, argument) in arguments.iter().enumerate() {
+        let arg = format!("arg{}", index);
+        lines.push(format!(
+            "        {},",
+            match argument {
+                ArgumentKind::Pure(schema, _) => format!("$pure(tx, {}, {})", schema, arg),
+                ArgumentKind::Object => format!("tx.object({})", arg),
+                ArgumentKind::ObjectVector => format!(
+                    "$isTransactionArgument({0}) ? {0} : tx.makeMoveVec({{ elements: {0}.map((e) => tx.object(e)) }})",
+                    arg
+                ),
+                ArgumentKind::Raw => arg,
+            }
+        ));
+    }
+    lines.push("      ],".to_string());
+    lines.push("    });".to_string());
+    lines.push("  }".to_string());
+    lines.push(String::new());
+}
+
+fn argument_kind(
+    key_types: &BTreeSet<(String, String, String)>,
+    t: &SuiMoveNormalizedType,
+) -> ArgumentKind {
+    if let Some((schema, ts_type)) = pure_schema(t) {
+        return ArgumentKind::Pure(schema, ts_type);
+    }
+    match t {
+        // pure values can be borrowed as well, so only the referenced type matters
+        SuiMoveNormalizedType::Reference(inner)
+        | SuiMoveNormalizedType::MutableReference(inner) => match argument_kind(key_types, inner) {
+            ArgumentKind::Pure(schema, ts_type) => ArgumentKind::Pure(schema, ts_type),
+            ArgumentKind::Object => ArgumentKind::Object,
+            _ => ArgumentKind::Raw,
+        },
+        // only objects can be passed by id. Option, String and other values without key whose
+        // content is not pure are only available as a result of another call
+        t if is_object(key_types, t) => ArgumentKind::Object,
+        SuiMoveNormalizedType::Vector(inner) if is_object(key_types, inner) => {
+            ArgumentKind::ObjectVector
+        }
+        _ => ArgumentKind::Raw,
+    }
+}
+
+fn is_object(key_types: &BTreeSet<(String, String, String)>, t: &SuiMoveNormalizedType) -> bool {
+    match t {
+        SuiMoveNormalizedType::Struct { inner } => key_types.contains(&(
+            inner.address.clone(),
+            inner.module.clone(),
+            inner.name.clone(),
+        )),
+        _ => false,
+    }
+}
+
+// BCS schema and accepted TypeScript type of the types that can be passed as pure inputs
+fn pure_schema(t: &SuiMoveNormalizedType) -> Option<(String, String)> {
+    let (schema, ts_type) = match t {
+        SuiMoveNormalizedType::Bool => ("bcs.bool()", "boolean"),
+        SuiMoveNormalizedType::U8 => ("bcs.u8()", "number"),
+        SuiMoveNormalizedType::U16 => ("bcs.u16()", "number"),
+        SuiMoveNormalizedType::U32 => ("bcs.u32()", "number"),
+        SuiMoveNormalizedType::U64 => ("bcs.u64()", "bigint | number | string"),
+        SuiMoveNormalizedType::U128 => ("bcs.u128()", "bigint | number | string"),
+        SuiMoveNormalizedType::U256 => ("bcs.u256()", "bigint | number | string"),
+        SuiMoveNormalizedType::Address => ("bcs.Address", "string"),
+        SuiMoveNormalizedType::Vector(inner) => {
+            let (inner_schema, inner_ts_type) = pure_schema(inner)?;
+            let ts_type = if matches!(**inner, SuiMoveNormalizedType::U8) {
+                "Uint8Array | number[]".to_string()
+            } else {
+                format!("({})[]", inner_ts_type)
+            };
+            return Some((format!("bcs.vector({})", inner_schema), ts_type));
+        }
+        t if is_datatype(t, MOVE_STDLIB_ADDRESS, "string", "String")
+            || is_datatype(t, MOVE_STDLIB_ADDRESS, "ascii", "String") =>
+        {
+            ("bcs.string()", "string")
+        }
+        t if is_datatype(t, SUI_FRAMEWORK_ADDRESS, "object", "ID") => ("bcs.Address", "string"),
+        SuiMoveNormalizedType::Struct { inner }
+            if is_datatype(t, MOVE_STDLIB_ADDRESS, "option", "Option") =>
+        {
+            let (inner_schema, inner_ts_type) = pure_schema(inner.type_arguments.first()?)?;
+            return Some((
+                format!("bcs.option({})", inner_schema),
+                format!("{} | null", inner_ts_type),
+            ));
+        }
+        _ => return None,
+    };
+    Some((schema.to_string(), ts_type.to_string()))
+}
+
+// the JSON shape of a field as returned when reading an object with showContent
+fn field_type(
+    normalized_modules: &PackageNormalizedModules,
+    module: &SuiMoveNormalizedModule,
+    t: &SuiMoveNormalizedType,
+) -> String {
+    match t {
+        SuiMoveNormalizedType::Bool => "boolean".to_string(),
+        SuiMoveNormalizedType::U8 | SuiMoveNormalizedType::U16 | SuiMoveNormalizedType::U32 => {
+            "number".to_string()
+        }
+        // 64 bit and wider integers do not fit a JSON number
+        SuiMoveNormalizedType::U64
+        | SuiMoveNormalizedType::U128
+        | SuiMoveNormalizedType::U256
+        | SuiMoveNormalizedType::Address
+        | SuiMoveNormalizedType::Signer => "string".to_string(),
+        SuiMoveNormalizedType::Vector(inner) => {
+            format!("({})[]", field_type(normalized_modules, module, inner))
+        }
+        SuiMoveNormalizedType::TypeParameter(index) => format!("T{}", index),
+        SuiMoveNormalizedType::Reference(inner)
+        | SuiMoveNormalizedType::MutableReference(inner) => {
+            field_type(normalized_modules, module, inner)
+        }
+        t if is_datatype(t, MOVE_STDLIB_ADDRESS, "string", "String")
+            || is_datatype(t, MOVE_STDLIB_ADDRESS, "ascii", "String")
+            || is_datatype(t, SUI_FRAMEWORK_ADDRESS, "object", "ID") =>
+        {
+            "string".to_string()
+        }
+        t if is_datatype(t, SUI_FRAMEWORK_ADDRESS, "object", "UID") => "{ id: string }".to_string(),
+        SuiMoveNormalizedType::Struct { inner }
+            if is_datatype(t, MOVE_STDLIB_ADDRESS, "option", "Option") =>
+        {
+            match inner.type_arguments.first() {
+                Some(type_argument) => format!(
+                    "{} | null",
+                    field_type(normalized_modules, module, type_argument)
+                ),
+                None => "unknown".to_string(),
+            }
+        }
+        SuiMoveNormalizedType::Struct { inner } => {
+            let SuiMoveNormalizedStructType {
+                address,
+                module: struct_module,
+                name,
+                type_arguments,
+            } = &**inner;
+            // only structs of this package have a generated interface
+            let is_local_struct = *address == module.address
+                && normalized_modules
+                    .0
+                    .get(struct_module)
+                    .is_some_and(|m| m.structs.contains_key(name));
+            if !is_local_struct {
+                return "unknown".to_string();
+            }
+            let qualified_name = if *struct_module == module.name {
+                name.clone()
+            } else {
+                format!("{}.{}", escape_name(struct_module), name)
+            };
+            if type_arguments.is_empty() {
+                qualified_name
+            } else {
+                format!(
+                    "{}<{}>",
+                    qualified_name,
+                    type_arguments
+                        .iter()
+                        .map(|t| field_type(normalized_modules, module, t))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )
+            }
+        }
+    }
+}
+
+fn type_parameter_list(count: usize) -> String {
+    if count == 0 {
+        return String::new();
+    }
+    format!(
+        "<{}>",
+        (0..count)
+            .map(|index| format!("T{}", index))
+            .collect::<Vec<_>>()
+            .join(", ")
+    )
+}
+
+fn escape_name(name: &str) -> String {
+    if RESERVED_WORDS.contains(&name) {
+        format!("{}_", name)
+    } else {
+        name.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use move_binary_format::file_format::{Ability, AbilitySet, SignatureToken, Visibility};
+
+    use super::*;
+    use crate::test_utils::{test_package, TestModuleBuilder};
+
+    fn datatype(
+        address: &str,
+        module: &str,
+        name: &str,
+        type_arguments: Vec<SuiMoveNormalizedType>,
+    ) -> SuiMoveNormalizedType {
+        SuiMoveNormalizedType::Struct {
+            inner: Box::new(SuiMoveNormalizedStructType {
+                address: address.to_string(),
+                module: module.to_string(),
+                name: name.to_string(),
+                type_arguments,
+            }),
+        }
+    }
+
+    #[test]
+    fn structs_and_callable_functions() {
+        let mut builder = TestModuleBuilder::new();
+        builder.add_struct(
+            "Pool",
+            AbilitySet::EMPTY | Ability::Store,
+            vec![],
+            &[
+                ("value", SignatureToken::U64),
+                (
+                    "bytes",
+                    SignatureToken::Vector(Box::new(SignatureToken::U8)),
+                ),
+            ],
+        );
+        builder.add_function("new", 0, &[]);
+        builder.add_function("swap", 1, &[]);
+        builder.add_function_def("helper", Visibility::Private, false, 0, &[]);
+        builder.add_function_def("run", Visibility::Private, true, 0, &[]);
+        let typescript = generate_typescript(&test_package(&[builder.build()], 1));
+        let lines: Vec<&str> = typescript.lines().collect();
+
+        assert!(lines.contains(&"export namespace test {"));
+        assert!(lines.contains(&"  export interface Pool {"));
+        assert!(lines.contains(&"    value: string;"));
+        assert!(lines.contains(&"    bytes: (number)[];"));
+        // no type origins in the test package
+        assert!(!typescript.contains("PoolType"));
+        // reserved words get a trailing underscore, the target keeps the Move name
+        assert!(lines.contains(&"  export function new_("));
+        assert!(lines.contains(&"      target: `${PACKAGE_ID}::test::new`,"));
+        assert!(lines.contains(&"    typeArguments: [string],"));
+        assert!(lines.contains(&"  export function run("));
+        assert!(!typescript.contains("helper"));
+    }
+
+    #[test]
+    fn argument_kinds() {
+        let string = datatype(MOVE_STDLIB_ADDRESS, "string", "String", vec![]);
+        let pool = datatype("0x0", "test", "Pool", vec![]);
+        let key_types =
+            BTreeSet::from([("0x0".to_string(), "test".to_string(), "Pool".to_string())]);
+        let bytes = SuiMoveNormalizedType::Vector(Box::new(SuiMoveNormalizedType::U8));
+        let option_u64 = datatype(
+            MOVE_STDLIB_ADDRESS,
+            "option",
+            "Option",
+            vec![SuiMoveNormalizedType::U64],
+        );
+
+        assert!(matches!(
+            argument_kind(&key_types, &bytes),
+            ArgumentKind::Pure(schema, ts_type)
+                if schema == "bcs.vector(bcs.u8())" && ts_type == "Uint8Array | number[]"
+        ));
+        assert!(matches!(
+            argument_kind(&key_types, &option_u64),
+            ArgumentKind::Pure(schema, ts_type)
+                if schema == "bcs.option(bcs.u64())" && ts_type == "bigint | number | string | null"
+        ));
+        assert!(matches!(
+            argument_kind(&key_types, &SuiMoveNormalizedType::Reference(Box::new(string))),
+            ArgumentKind::Pure(schema, _) if schema == "bcs.string()"
+        ));
+        assert!(matches!(
+            argument_kind(
+                &key_types,
+                &SuiMoveNormalizedType::MutableReference(Box::new(pool.clone()))
+            ),
+            ArgumentKind::Object
+        ));
+        assert!(matches!(
+            argument_kind(&key_types, &SuiMoveNormalizedType::Vector(Box::new(pool))),
+            ArgumentKind::ObjectVector
+        ));
+        // values without key cannot be passed by id
+        assert!(matches!(
+            argument_kind(&key_types, &datatype("0x0", "test", "Receipt", vec![])),
+            ArgumentKind::Raw
+        ));
+        assert!(matches!(
+            argument_kind(&key_types, &SuiMoveNormalizedType::TypeParameter(0)),
+            ArgumentKind::Raw
+        ));
+    }
+}
@@ -0,0 +1,375 @@
+use move_binary_format::file_format::{Bytecode, CompiledModule, Constant, FunctionDefinition};
+use move_core_types::runtime_value::MoveValue;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use sui_types::move_package::MovePackage;
+
+use crate::bcs_json::format_signature_token;
+
+// bump when the shape of constants.json changes; see store_layout.rs
+pub const CONSTANTS_SCHEMA_VERSION: u32 = 2;
+
+// clever error abort codes: | tag bit | reserved (15) | line number (16) | identifier (16) | constant (16) |
+const CLEVER_ERROR_TAG_BIT: u64 = 1 << 63;
+const CLEVER_ERROR_NO_INDEX: u16 = u16::MAX;
+
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+pub struct PackageConstants {
+    package_id: String,
+    module_constants: Vec<ModuleConstants>,
+}
+
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+pub struct ModuleConstants {
+    module_name: String,
+    constants: Vec<ConstantEntry>,
+    // function name -> abort sites in that function
+    abort_codes: BTreeMap<String, Vec<AbortSite>>,
+}
+
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+pub struct ConstantEntry {
+    index: u16,
+    #[serde(rename = "type")]
+    type_: String,
+    value: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+pub struct AbortSite {
+    code_offset: u16,
+    // None when the abort code is computed at runtime
+    abort_code: Option<String>,
+    constant_index: Option<u16>,
+    clever_error: Option<CleverError>,
+}
+
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+pub struct CleverError {
+    line_number: u16,
+    error_name: Option<String>,
+    error_value: Option<String>,
+}
+
+impl From<&MovePackage> for PackageConstants {
+    fn from(pkg: &MovePackage) -> Self {
+        let mut package_constants = PackageConstants {
+            package_id: pkg.id().to_canonical_string(true),
+            module_constants: Vec::new(),
+        };
+        for (module_name, module_bytes) in pkg.serialized_module_map() {
+            let module = CompiledModule::deserialize_with_defaults(module_bytes).unwrap();
+            package_constants
+                .module_constants
+                .push(move_module_to_module_constants(module_name, &module));
+        }
+        package_constants
+    }
+}
+
+pub fn move_module_to_module_constants(
+    module_name: &str,
+    module: &CompiledModule,
+) -> ModuleConstants {
+    let constants = module
+        .constant_pool()
+        .iter()
+        .enumerate()
+        .map(|(index, constant)| ConstantEntry {
+            index: index as u16,
+            type_: format_signature_token(module, &constant.type_),
+            value: format_constant(constant),
+        })
+        .collect();
+    let mut abort_codes: BTreeMap<String, Vec<AbortSite>> = BTreeMap::new();
+    for function_def in module.function_defs() {
+        let function_handle = module.function_handle_at(function_def.function);
+        let function_name = module.identifier_at(function_handle.name).to_string();
+        let abort_sites = get_abort_sites(module, function_def);
+        if !abort_sites.is_empty() {
+            abort_codes.insert(function_name, abort_sites);
+        }
+    }
+    ModuleConstants {
+        module_name: module_name.to_string(),
+        constants,
+        abort_codes,
+    }
+}
+
+// the compiler loads the abort code right before the Abort instruction, anything else is
+// computed at runtime and reported without a code. Only abort codes are decoded as clever errors,
+// plain u64 constants may have the tag bit set without being one
+fn get_abort_sites(module: &CompiledModule, function_def: &FunctionDefinition) -> Vec<AbortSite> {
+    let mut abort_sites = Vec::new();
+    let Some(code_unit) = &function_def.code else {
+        return abort_sites;
+    };
+    for (offset, instruction) in code_unit.code.iter().enumerate() {
+        if *instruction != Bytecode::Abort {
+            continue;
+        }
+        let (abort_code, constant_index) = match offset.checked_sub(1).map(|i| &code_unit.code[i]) {
+            Some(Bytecode::LdU64(code)) => (Some(*code), None),
+            Some(Bytecode::LdConst(index)) => {
+                (constant_as_u64(module.constant_at(*index)), Some(index.0))
+            }
+            _ => (None, None),
+        };
+        abort_sites.push(AbortSite {
+            code_offset: offset as u16,
+            abort_code: abort_code.map(|code| code.to_string()),
+            constant_index,
+            clever_error: abort_code.and_then(|code| decode_clever_error(module, code)),
+        });
+    }
+    abort_sites
+}
+
+fn decode_clever_error(module: &CompiledModule, code: u64) -> Option<CleverError> {
+    if code & CLEVER_ERROR_TAG_BIT == 0 {
+        return None;
+    }
+    let line_number = ((code >> 32) & 0xffff) as u16;
+    let identifier_index = ((code >> 16) & 0xffff) as u16;
+    let constant_index = (code & 0xffff) as u16;
+    let error_name = get_constant(module, identifier_index)
+        .and_then(|constant| constant.deserialize_constant())
+        .and_then(|value| match value {
+            MoveValue::Vector(bytes) => bytes
+                .into_iter()
+                .map(|b| match b {
+                    MoveValue::U8(b) => Some(b),
+                    _ => None,
+                })
+                .collect::<Option<Vec<u8>>>()
+                .and_then(|bytes| String::from_utf8(bytes).ok()),
+            _ => None,
+        });
+    let error_value = get_constant(module, constant_index).and_then(format_constant);
+    Some(CleverError {
+        line_number,
+        error_name,
+        error_value,
+    })
+}
+
+fn get_constant(module: &CompiledModule, index: u16) -> Option<&Constant> {
+    if index == CLEVER_ERROR_NO_INDEX {
+        return None;
+    }
+    module.constant_pool().get(index as usize)
+}
+
+fn constant_as_u64(constant: &Constant) -> Option<u64> {
+    match constant.deserialize_constant()? {
+        MoveValue::U64(value) => Some(value),
+        _ => None,
+    }
+}
+
+fn format_constant(constant: &Constant) -> Option<String> {
+    constant
+        .deserialize_constant()
+        .map(|value| format_move_value(&value))
+}
+
+// renders a constant the way it would be written in Move source
+pub fn format_move_value(value: &MoveValue) -> String {
+    match value {
+        MoveValue::Bool(b) => b.to_string(),
+        MoveValue::U8(v) => format!("{}u8", v),
+        MoveValue::U16(v) => format!("{}u16", v),
+        MoveValue::U32(v) => format!("{}u32", v),
+        MoveValue::U64(v) => format!("{}u64", v),
+        MoveValue::U128(v) => format!("{}u128", v),
+        MoveValue::U256(v) => format!("{}u256", v),
+        MoveValue::Address(a) => format!("@{}", a.to_canonical_string(true)),
+        MoveValue::Vector(values) => {
+            let bytes = values
+                .iter()
+                .map(|v| match v {
+                    MoveValue::U8(b) => Some(*b),
+                    _ => None,
+                })
+                .collect::<Option<Vec<u8>>>();
+            match bytes {
+                Some(bytes) if !bytes.is_empty() => format_byte_string(&bytes),
+                _ => format!(
+                    "vector[{}]",
+                    values
+                        .iter()
+                        .map(format_move_value)
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ),
+            }
+        }
+        other => format!("{:?}", other),
+    }
+}
+
+// b"..." when every byte is printable ASCII, x"..." otherwise
+fn format_byte_string(bytes: &[u8]) -> String {
+    if bytes.iter().all(|b| (0x20..0x7f).contains(b)) {
+        let mut s = String::from("b\"");
+        for b in bytes {
+            match b {
+                b'"' => s.push_str("\\\""),
+                b'\\' => s.push_str("\\\\"),
+                _ => s.push(*b as char),
+            }
+        }
+        s.push('"');
+        return s;
+    }
+    format!(
+        "x\"{}\"",
+        bytes
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect::<String>()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use move_binary_format::file_format::{
+        CodeUnit, ConstantPoolIndex, FunctionHandleIndex, SignatureIndex, SignatureToken,
+        Visibility,
+    };
+
+    const ERROR_NAME_INDEX: u16 = 0;
+    const ERROR_VALUE_INDEX: u16 = 1;
+    const TAGGED_U64_INDEX: u16 = 2;
+
+    fn clever_error_code(line_number: u16, identifier_index: u16, constant_index: u16) -> u64 {
+        CLEVER_ERROR_TAG_BIT
+            | ((line_number as u64) << 32)
+            | ((identifier_index as u64) << 16)
+            | (constant_index as u64)
+    }
+
+    fn test_module() -> CompiledModule {
+        CompiledModule {
+            constant_pool: vec![
+                Constant {
+                    type_: SignatureToken::Vector(Box::new(SignatureToken::U8)),
+                    data: bcs::to_bytes(&b"ENotOwner".to_vec()).unwrap(),
+                },
+                Constant {
+                    type_: SignatureToken::U64,
+                    data: bcs::to_bytes(&7u64).unwrap(),
+                },
+                Constant {
+                    type_: SignatureToken::U64,
+                    data: bcs::to_bytes(&clever_error_code(1, 0, 1)).unwrap(),
+                },
+            ],
+            ..Default::default()
+        }
+    }
+
+    fn function_with_code(code: Vec<Bytecode>) -> FunctionDefinition {
+        FunctionDefinition {
+            function: FunctionHandleIndex(0),
+            visibility: Visibility::Public,
+            is_entry: false,
+            acquires_global_resources: vec![],
+            code: Some(CodeUnit {
+                locals: SignatureIndex(0),
+                code,
+                jump_tables: vec![],
+            }),
+        }
+    }
+
+    #[test]
+    fn decodes_clever_error() {
+        let module = test_module();
+        let code = clever_error_code(42, ERROR_NAME_INDEX, ERROR_VALUE_INDEX);
+        let clever_error = decode_clever_error(&module, code).unwrap();
+        assert_eq!(clever_error.line_number, 42);
+        assert_eq!(clever_error.error_name.as_deref(), Some("ENotOwner"));
+        assert_eq!(clever_error.error_value.as_deref(), Some("7u64"));
+    }
+
+    #[test]
+    fn decodes_clever_error_without_constants() {
+        let module = test_module();
+        let code = clever_error_code(3, CLEVER_ERROR_NO_INDEX, CLEVER_ERROR_NO_INDEX);
+        let clever_error = decode_clever_error(&module, code).unwrap();
+        assert_eq!(clever_error.line_number, 3);
+        assert_eq!(clever_error.error_name, None);
+        assert_eq!(clever_error.error_value, None);
+    }
+
+    #[test]
+    fn plain_abort_code_is_not_clever_error() {
+        assert!(decode_clever_error(&test_module(), 7).is_none());
+    }
+
+    #[test]
+    fn abort_sites() {
+        let module = test_module();
+        let function_def = function_with_code(vec![
+            Bytecode::LdU64(clever_error_code(10, ERROR_NAME_INDEX, ERROR_VALUE_INDEX)),
+            Bytecode::Abort,
+            Bytecode::LdConst(ConstantPoolIndex(ERROR_VALUE_INDEX)),
+            Bytecode::Abort,
+            Bytecode::CopyLoc(0),
+            Bytecode::Abort,
+        ]);
+        let abort_sites = get_abort_sites(&module, &function_def);
+        assert_eq!(abort_sites.len(), 3);
+
+        assert_eq!(abort_sites[0].code_offset, 1);
+        assert_eq!(abort_sites[0].constant_index, None);
+        let clever_error = abort_sites[0].clever_error.as_ref().unwrap();
+        assert_eq!(clever_error.line_number, 10);
+        assert_eq!(clever_error.error_name.as_deref(), Some("ENotOwner"));
+
+        assert_eq!(abort_sites[1].code_offset, 3);
+        assert_eq!(abort_sites[1].abort_code.as_deref(), Some("7"));
+        assert_eq!(abort_sites[1].constant_index, Some(ERROR_VALUE_INDEX));
+        assert!(abort_sites[1].clever_error.is_none());
+
+        assert_eq!(abort_sites[2].code_offset, 5);
+        assert_eq!(abort_sites[2].abort_code, None);
+        assert!(abort_sites[2].clever_error.is_none());
+    }
+
+    #[test]
+    fn constant_entries() {
+        let constants = move_module_to_module_constants("test", &test_module());
+        let values = constants
+            .constants
+            .iter()
+            .map(|constant| constant.value.as_deref().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(values[..2], ["b\"ENotOwner\"", "7u64"]);
+        assert_eq!(constants.constants[1].type_, "u64");
+    }
+
+    #[test]
+    fn tagged_u64_constant_is_a_plain_value() {
+        let module = test_module();
+        let constants = move_module_to_module_constants("test", &module);
+        assert!(constants.abort_codes.is_empty());
+        assert_eq!(
+            constants.constants[TAGGED_U64_INDEX as usize]
+                .value
+                .as_deref(),
+            Some(format!("{}u64", clever_error_code(1, 0, 1)).as_str())
+        );
+    }
+
+    #[test]
+    fn byte_strings() {
+        assert_eq!(format_byte_string(b"ENotOwner"), "b\"ENotOwner\"");
+        assert_eq!(format_byte_string(b"a\"b\\c"), "b\"a\\\"b\\\\c\"");
+        assert_eq!(format_byte_string(&[0, 0xff]), "x\"00ff\"");
+    }
+}
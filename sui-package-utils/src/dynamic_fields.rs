@@ -0,0 +1,379 @@
+use move_binary_format::file_format::CompiledModule;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use sui_types::move_package::MovePackage;
+use sui_types::SUI_FRAMEWORK_ADDRESS;
+
+use crate::bcs_json::{format_signature_token, has_type_parameter};
+use crate::call_graph::for_each_call;
+
+// bump when the shape of dynamic_fields.json changes; see store_layout.rs
+pub const DYNAMIC_FIELDS_SCHEMA_VERSION: u32 = 1;
+
+// framework modules storing values under a key, and the functions of each that fix the key and
+// value types. Type arguments are always <key, value>, dynamic_field::exists_ and bag::contains
+// only name the key
+const DYNAMIC_FIELD_FUNCTIONS: [(&str, &[&str]); 6] = [
+    (
+        "dynamic_field",
+        &[
+            "add",
+            "borrow",
+            "borrow_mut",
+            "remove",
+            "remove_if_exists",
+            "exists_",
+            "exists_with_type",
+        ],
+    ),
+    (
+        "dynamic_object_field",
+        &[
+            "add",
+            "borrow",
+            "borrow_mut",
+            "remove",
+            "exists_",
+            "exists_with_type",
+        ],
+    ),
+    (
+        "table",
+        &["new", "add", "borrow", "borrow_mut", "remove", "contains"],
+    ),
+    (
+        "object_table",
+        &["new", "add", "borrow", "borrow_mut", "remove", "contains"],
+    ),
+    (
+        "bag",
+        &[
+            "add",
+            "borrow",
+            "borrow_mut",
+            "remove",
+            "contains",
+            "contains_with_type",
+        ],
+    ),
+    (
+        "object_bag",
+        &[
+            "add",
+            "borrow",
+            "borrow_mut",
+            "remove",
+            "contains",
+            "contains_with_type",
+        ],
+    ),
+];
+
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+pub struct PackageDynamicFields {
+    package_id: String,
+    // one entry per framework module, key type and value type, sorted. Generic usages get one
+    // entry per calling function, a type parameter means something different in each of them
+    usages: Vec<DynamicFieldUsage>,
+}
+
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+pub struct DynamicFieldUsage {
+    // the framework module called, e.g. dynamic_object_field or table
+    storage: String,
+    // formatted like bcs.json signatures
+    key_type: String,
+    // None when only calls naming the key type were found, e.g. dynamic_field::exists_. Such
+    // calls are otherwise merged into every entry with the same key type
+    value_type: Option<String>,
+    // the key or value type is built from a type parameter of the calling function
+    generic: bool,
+    // <module>::<function> of this package -> framework functions it calls
+    functions: BTreeMap<String, BTreeSet<String>>,
+}
+
+impl From<&MovePackage> for PackageDynamicFields {
+    fn from(pkg: &MovePackage) -> Self {
+        let mut usages: BTreeMap<UsageKey, DynamicFieldUsage> = BTreeMap::new();
+        for (module_name, module_bytes) in pkg.serialized_module_map() {
+            let module = CompiledModule::deserialize_with_defaults(module_bytes).unwrap();
+            add_module_usages(module_name, &module, &mut usages);
+        }
+        PackageDynamicFields {
+            package_id: pkg.id().to_canonical_string(true),
+            usages: merge_key_only_usages(usages),
+        }
+    }
+}
+
+// storage, key type, value type and, for generic usages only, the calling function
+type UsageKey = (String, String, Option<String>, Option<String>);
+
+fn add_module_usages(
+    module_name: &str,
+    module: &CompiledModule,
+    usages: &mut BTreeMap<UsageKey, DynamicFieldUsage>,
+) {
+    for function_def in module.function_defs() {
+        let function_handle = module.function_handle_at(function_def.function);
+        let caller = format!(
+            "{}::{}",
+            module_name,
+            module.identifier_at(function_handle.name)
+        );
+        for_each_call(module, function_def, |call| {
+            let callee_module = module.module_handle_at(call.handle.module);
+            if *module.address_identifier_at(callee_module.address) != SUI_FRAMEWORK_ADDRESS {
+                return;
+            }
+            let storage = module.identifier_at(callee_module.name).as_str();
+            let callee_name = module.identifier_at(call.handle.name).as_str();
+            let is_dynamic_field_call =
+                DYNAMIC_FIELD_FUNCTIONS
+                    .iter()
+                    .any(|(framework_module, functions)| {
+                        *framework_module == storage && functions.contains(&callee_name)
+                    });
+            if !is_dynamic_field_call {
+                return;
+            }
+            let Some(key_type) = call.type_arguments.first() else {
+                return;
+            };
+            let value_type = call.type_arguments.get(1);
+            let generic =
+                has_type_parameter(key_type) || value_type.is_some_and(has_type_parameter);
+            let usage = usages
+                .entry((
+                    storage.to_string(),
+                    format_signature_token(module, key_type),
+                    value_type.map(|value_type| format_signature_token(module, value_type)),
+                    generic.then(|| caller.clone()),
+                ))
+                .or_insert_with_key(|(storage, key_type, value_type, _)| DynamicFieldUsage {
+                    storage: storage.clone(),
+                    key_type: key_type.clone(),
+                    value_type: value_type.clone(),
+                    generic,
+                    functions: BTreeMap::new(),
+                });
+            usage
+                .functions
+                .entry(caller.clone())
+                .or_default()
+                .insert(callee_name.to_string());
+        });
+    }
+}
+
+// moves the callers of key-only usages into the usages of the same storage and key type that do
+// name a value type, key-only usages without such a match are kept as they are
+fn merge_key_only_usages(
+    mut usages: BTreeMap<UsageKey, DynamicFieldUsage>,
+) -> Vec<DynamicFieldUsage> {
+    let key_only: Vec<UsageKey> = usages
+        .keys()
+        .filter(|(_, _, value_type, _)| value_type.is_none())
+        .cloned()
+        .collect();
+    for key_only_key in key_only {
+        let (storage, key_type, _, generic_caller) = &key_only_key;
+        let matching: Vec<UsageKey> = usages
+            .keys()
+            .filter(
+                |(other_storage, other_key_type, value_type, other_generic_caller)| {
+                    other_storage == storage
+                        && other_key_type == key_type
+                        && value_type.is_some()
+                        && other_generic_caller == generic_caller
+                },
+            )
+            .cloned()
+            .collect();
+        if matching.is_empty() {
+            continue;
+        }
+        let key_only_usage = usages.remove(&key_only_key).unwrap();
+        for matching_key in matching {
+            let usage = usages.get_mut(&matching_key).unwrap();
+            for (caller, callees) in &key_only_usage.functions {
+                usage
+                    .functions
+                    .entry(caller.clone())
+                    .or_default()
+                    .extend(callees.iter().cloned());
+            }
+        }
+    }
+    usages.into_values().collect()
+}
+
+impl PackageDynamicFields {
+    pub fn get_package_id(&self) -> &str {
+        &self.package_id
+    }
+    pub fn get_usages(&self) -> &Vec<DynamicFieldUsage> {
+        &self.usages
+    }
+}
+
+impl DynamicFieldUsage {
+    pub fn get_storage(&self) -> &str {
+        &self.storage
+    }
+    pub fn get_key_type(&self) -> &str {
+        &self.key_type
+    }
+    pub fn get_value_type(&self) -> Option<&str> {
+        self.value_type.as_deref()
+    }
+    pub fn get_generic(&self) -> bool {
+        self.generic
+    }
+    pub fn get_functions(&self) -> &BTreeMap<String, BTreeSet<String>> {
+        &self.functions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use move_binary_format::file_format::SignatureToken;
+
+    use super::*;
+    use crate::test_utils::TestModuleBuilder;
+
+    type UsageSummary = (
+        String,
+        String,
+        Option<String>,
+        bool,
+        Vec<(String, Vec<String>)>,
+    );
+
+    fn summary(usage: &DynamicFieldUsage) -> UsageSummary {
+        (
+            usage.storage.clone(),
+            usage.key_type.clone(),
+            usage.value_type.clone(),
+            usage.generic,
+            usage
+                .functions
+                .iter()
+                .map(|(caller, callees)| (caller.clone(), callees.iter().cloned().collect()))
+                .collect(),
+        )
+    }
+
+    fn expected(
+        storage: &str,
+        key_type: &str,
+        value_type: Option<&str>,
+        generic: bool,
+        functions: &[(&str, &[&str])],
+    ) -> UsageSummary {
+        (
+            storage.to_string(),
+            key_type.to_string(),
+            value_type.map(|value_type| value_type.to_string()),
+            generic,
+            functions
+                .iter()
+                .map(|(caller, callees)| {
+                    (
+                        caller.to_string(),
+                        callees.iter().map(|callee| callee.to_string()).collect(),
+                    )
+                })
+                .collect(),
+        )
+    }
+
+    fn usages(module: &CompiledModule) -> Vec<UsageSummary> {
+        let mut usages = BTreeMap::new();
+        add_module_usages("test", module, &mut usages);
+        merge_key_only_usages(usages).iter().map(summary).collect()
+    }
+
+    #[test]
+    fn merges_key_only_calls() {
+        let mut builder = TestModuleBuilder::new();
+        builder.add_function(
+            "put",
+            0,
+            &[(
+                "dynamic_field",
+                "add",
+                vec![SignatureToken::U64, SignatureToken::U8],
+            )],
+        );
+        builder.add_function(
+            "check",
+            0,
+            &[
+                ("dynamic_field", "exists_", vec![SignatureToken::U64]),
+                ("bag", "contains", vec![SignatureToken::Bool]),
+                ("coin", "value", vec![SignatureToken::U64]),
+            ],
+        );
+        assert_eq!(
+            usages(&builder.build()),
+            vec![
+                expected(
+                    "bag",
+                    "bool",
+                    None,
+                    false,
+                    &[("test::check", &["contains"])]
+                ),
+                expected(
+                    "dynamic_field",
+                    "u64",
+                    Some("u8"),
+                    false,
+                    &[("test::check", &["exists_"]), ("test::put", &["add"])]
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn keeps_generic_usages_per_caller() {
+        let key = SignatureToken::TypeParameter(0);
+        let mut builder = TestModuleBuilder::new();
+        builder.add_function(
+            "put",
+            1,
+            &[
+                ("table", "contains", vec![key.clone()]),
+                ("table", "add", vec![key.clone(), SignatureToken::U64]),
+            ],
+        );
+        builder.add_function(
+            "get",
+            1,
+            &[("table", "borrow", vec![key.clone(), SignatureToken::U64])],
+        );
+        builder.add_function("has", 1, &[("table", "contains", vec![key])]);
+        assert_eq!(
+            usages(&builder.build()),
+            vec![
+                expected("table", "T0", None, true, &[("test::has", &["contains"])]),
+                expected(
+                    "table",
+                    "T0",
+                    Some("u64"),
+                    true,
+                    &[("test::get", &["borrow"])]
+                ),
+                expected(
+                    "table",
+                    "T0",
+                    Some("u64"),
+                    true,
+                    &[("test::put", &["add", "contains"])]
+                ),
+            ]
+        );
+    }
+}
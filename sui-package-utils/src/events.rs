@@ -0,0 +1,202 @@
+use move_binary_format::file_format::{CodeOffset, CompiledModule, SignatureToken};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use sui_types::move_package::MovePackage;
+use sui_types::SUI_FRAMEWORK_ADDRESS;
+
+use crate::bcs_json::{format_signature_token, move_module_to_bcs_struct_map, BcsStructEntry};
+use crate::call_graph::for_each_call;
+
+// bump when the shape of events.json changes; see store_layout.rs
+pub const EVENTS_SCHEMA_VERSION: u32 = 1;
+
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+pub struct PackageEvents {
+    package_id: String,
+    // keyed by <original package id>::<module>::<struct>, without type arguments
+    event_types: BTreeMap<String, EventType>,
+}
+
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+pub struct EventType {
+    // same shape as the structMap entries of bcs.json. The verifier only allows emitting types
+    // of the calling module, None if the bytecode does it anyway
+    definition: Option<BcsStructEntry>,
+    // the types emitted, with type arguments formatted like bcs.json signatures
+    instantiations: BTreeSet<String>,
+    // <module>::<function> -> offsets of the event::emit calls
+    emitters: BTreeMap<String, Vec<CodeOffset>>,
+}
+
+impl From<&MovePackage> for PackageEvents {
+    fn from(pkg: &MovePackage) -> Self {
+        let mut package_events = PackageEvents {
+            package_id: pkg.id().to_canonical_string(true),
+            event_types: BTreeMap::new(),
+        };
+        for (module_name, module_bytes) in pkg.serialized_module_map() {
+            let module = CompiledModule::deserialize_with_defaults(module_bytes).unwrap();
+            let mut struct_map = move_module_to_bcs_struct_map(&module);
+            for function_def in module.function_defs() {
+                let function_handle = module.function_handle_at(function_def.function);
+                let emitter = format!(
+                    "{}::{}",
+                    module_name,
+                    module.identifier_at(function_handle.name)
+                );
+                for_each_call(&module, function_def, |call| {
+                    let callee_module = module.module_handle_at(call.handle.module);
+                    let is_emit = *module.address_identifier_at(callee_module.address)
+                        == SUI_FRAMEWORK_ADDRESS
+                        && module.identifier_at(callee_module.name).as_str() == "event"
+                        && module.identifier_at(call.handle.name).as_str() == "emit";
+                    let Some(event_type) = call.type_arguments.first().filter(|_| is_emit) else {
+                        return;
+                    };
+                    let datatype_index = match event_type {
+                        SignatureToken::Datatype(datatype_index) => *datatype_index,
+                        SignatureToken::DatatypeInstantiation(instantiation) => instantiation.0,
+                        // emit<T> forwarding a type parameter is rejected by the verifier
+                        _ => return,
+                    };
+                    let datatype_handle = module.datatype_handle_at(datatype_index);
+                    let datatype_module = module.module_handle_at(datatype_handle.module);
+                    let datatype_name = module.identifier_at(datatype_handle.name).to_string();
+                    let event_type_entry = package_events
+                        .event_types
+                        .entry(format!(
+                            "{}::{}::{}",
+                            module
+                                .address_identifier_at(datatype_module.address)
+                                .to_canonical_string(true),
+                            module.identifier_at(datatype_module.name),
+                            datatype_name
+                        ))
+                        .or_insert_with(|| EventType {
+                            definition: None,
+                            instantiations: BTreeSet::new(),
+                            emitters: BTreeMap::new(),
+                        });
+                    if event_type_entry.definition.is_none()
+                        && datatype_handle.module == module.self_handle_idx()
+                    {
+                        event_type_entry.definition = struct_map.remove(&datatype_name);
+                    }
+                    event_type_entry
+                        .instantiations
+                        .insert(format_signature_token(&module, event_type));
+                    event_type_entry
+                        .emitters
+                        .entry(emitter.clone())
+                        .or_default()
+                        .push(call.offset);
+                });
+            }
+        }
+        package_events
+    }
+}
+
+impl PackageEvents {
+    pub fn get_package_id(&self) -> &str {
+        &self.package_id
+    }
+    pub fn get_event_types(&self) -> &BTreeMap<String, EventType> {
+        &self.event_types
+    }
+}
+
+impl EventType {
+    pub fn get_definition(&self) -> Option<&BcsStructEntry> {
+        self.definition.as_ref()
+    }
+    pub fn get_instantiations(&self) -> &BTreeSet<String> {
+        &self.instantiations
+    }
+    pub fn get_emitters(&self) -> &BTreeMap<String, Vec<CodeOffset>> {
+        &self.emitters
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use move_binary_format::file_format::{Ability, AbilitySet, DatatypeTyParameter};
+    use move_core_types::account_address::AccountAddress;
+
+    use super::*;
+    use crate::test_utils::{test_package, TestModuleBuilder};
+
+    #[test]
+    fn emitted_event_types() {
+        let mut builder = TestModuleBuilder::new();
+        let abilities = AbilitySet::EMPTY | Ability::Copy | Ability::Drop;
+        let minted = builder.add_struct(
+            "Minted",
+            abilities,
+            vec![],
+            &[("amount", SignatureToken::U64)],
+        );
+        let SignatureToken::Datatype(wrapped) = builder.add_struct(
+            "Wrapped",
+            abilities,
+            vec![DatatypeTyParameter {
+                constraints: AbilitySet::EMPTY,
+                is_phantom: false,
+            }],
+            &[],
+        ) else {
+            unreachable!()
+        };
+        let wrapped_u64 =
+            SignatureToken::DatatypeInstantiation(Box::new((wrapped, vec![SignatureToken::U64])));
+        builder.add_function(
+            "mint",
+            0,
+            &[
+                ("event", "emit", vec![minted.clone()]),
+                ("coin", "value", vec![minted.clone()]),
+                ("event", "emit", vec![minted]),
+            ],
+        );
+        builder.add_function("wrap", 0, &[("event", "emit", vec![wrapped_u64])]);
+        // only the framework's event::emit counts
+        builder.add_function(
+            "other",
+            0,
+            &[("0x5::event", "emit", vec![SignatureToken::U8])],
+        );
+        let package_events = PackageEvents::from(&test_package(&[builder.build()], 1));
+        let event_type = |name: &str| {
+            format!(
+                "{}::test::{}",
+                AccountAddress::ZERO.to_canonical_string(true),
+                name
+            )
+        };
+
+        let event_types = package_events.get_event_types();
+        assert_eq!(
+            event_types.keys().cloned().collect::<Vec<_>>(),
+            vec![event_type("Minted"), event_type("Wrapped")]
+        );
+        let minted = &event_types[&event_type("Minted")];
+        assert!(minted.get_definition().is_some());
+        assert_eq!(
+            *minted.get_emitters(),
+            BTreeMap::from([("test::mint".to_string(), vec![0, 2])])
+        );
+        let wrapped = &event_types[&event_type("Wrapped")];
+        assert_eq!(
+            *wrapped.get_instantiations(),
+            BTreeSet::from([format!(
+                "0x{}::test::Wrapped<u64>",
+                AccountAddress::ZERO.to_canonical_string(false)
+            )])
+        );
+        assert_eq!(
+            *wrapped.get_emitters(),
+            BTreeMap::from([("test::wrap".to_string(), vec![0])])
+        );
+    }
+}
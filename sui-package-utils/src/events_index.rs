@@ -0,0 +1,251 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::Path;
+
+use move_core_types::account_address::AccountAddress;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::events::PackageEvents;
+use crate::global_call_graph::{get_changed_packages, StoreIndexUpdate};
+use crate::package_id_io::{PackageIoError, PackagesDir};
+
+// store-level file next to layout.json
+pub const EVENTS_INDEX_FILE: &str = "events_index.json";
+// bump when the shape of events_index.json changes, older files are rebuilt from scratch
+pub const EVENTS_INDEX_SCHEMA_VERSION: u32 = 2;
+
+// event type -> package versions emitting it. Event types are keyed by the original package id
+// of the module defining them, so every version of a package shares the same key
+#[derive(Debug, Default, Deserialize, Serialize, JsonSchema)]
+pub struct EventsIndex {
+    schema_version: u32,
+    // storage id -> indexed version, system packages keep their id across versions
+    packages: BTreeMap<String, u64>,
+    emitters: BTreeMap<String, BTreeSet<String>>,
+}
+
+#[derive(Error, Debug)]
+pub enum EventsIndexError {
+    #[error("Error reading or writing events_index.json: {0}")]
+    IoError(#[from] std::io::Error),
+    #[error("Error parsing events_index.json: {0}")]
+    ParseError(#[from] serde_json::Error),
+    #[error("Invalid event type {0}, expected <address>::<module>::<struct>")]
+    InvalidEventType(String),
+}
+
+impl EventsIndex {
+    // returns an empty index if the store has none yet or it was written by an older version
+    pub fn load(prefix: &Path) -> Result<Self, EventsIndexError> {
+        let index_file = prefix.join(EVENTS_INDEX_FILE);
+        if !index_file.exists() {
+            return Ok(Self::new());
+        }
+        let index: EventsIndex = serde_json::from_str(&fs::read_to_string(index_file)?)?;
+        if index.schema_version != EVENTS_INDEX_SCHEMA_VERSION {
+            return Ok(Self::new());
+        }
+        Ok(index)
+    }
+
+    pub fn save(&self, prefix: &Path) -> Result<(), EventsIndexError> {
+        fs::write(
+            prefix.join(EVENTS_INDEX_FILE),
+            serde_json::to_string_pretty(self)?,
+        )?;
+        Ok(())
+    }
+
+    fn new() -> Self {
+        EventsIndex {
+            schema_version: EVENTS_INDEX_SCHEMA_VERSION,
+            ..Default::default()
+        }
+    }
+
+    pub fn get_packages(&self) -> &BTreeMap<String, u64> {
+        &self.packages
+    }
+
+    pub fn get_emitters(&self) -> &BTreeMap<String, BTreeSet<String>> {
+        &self.emitters
+    }
+
+    // indexes the packages of the store that are not in the index yet or were indexed at another
+    // version, replacing the event types of the previous version
+    pub fn update(
+        &mut self,
+        packages_dir: &PackagesDir,
+    ) -> Result<StoreIndexUpdate, EventsIndexError> {
+        let mut update = StoreIndexUpdate::default();
+        for (package_id, metadata) in
+            get_changed_packages(packages_dir, &self.packages, &mut update)?
+        {
+            match load_package_events(packages_dir, &package_id) {
+                Ok(package_events) => {
+                    self.remove_package(&package_id);
+                    for event_type in package_events.get_event_types().keys() {
+                        self.emitters
+                            .entry(event_type.clone())
+                            .or_default()
+                            .insert(package_id.clone());
+                    }
+                    self.packages.insert(package_id, metadata.version);
+                    update.updated += 1;
+                }
+                Err(e) => update.failures.push((package_id, e.to_string())),
+            }
+        }
+        Ok(update)
+    }
+
+    fn remove_package(&mut self, package_id: &str) {
+        if self.packages.remove(package_id).is_none() {
+            return;
+        }
+        for packages in self.emitters.values_mut() {
+            packages.remove(package_id);
+        }
+        self.emitters.retain(|_, packages| !packages.is_empty());
+    }
+
+    // package versions emitting an event type, e.g. 0x2::coin::CurrencyCreated. Type arguments
+    // are ignored
+    pub fn emitting_packages(
+        &self,
+        event_type: &str,
+    ) -> Result<Option<&BTreeSet<String>>, EventsIndexError> {
+        let base_type = event_type.split('<').next().unwrap_or(event_type);
+        let parts: Vec<&str> = base_type.split("::").collect();
+        let [address, module, name] = parts[..] else {
+            return Err(EventsIndexError::InvalidEventType(event_type.to_string()));
+        };
+        let address = AccountAddress::from_hex_literal(address)
+            .map_err(|_| EventsIndexError::InvalidEventType(event_type.to_string()))?;
+        Ok(self.emitters.get(&format!(
+            "{}::{}::{}",
+            address.to_canonical_string(true),
+            module,
+            name
+        )))
+    }
+}
+
+// events.json is opt-in, packages saved without it are analyzed from bcs.json
+pub fn load_package_events(
+    packages_dir: &PackagesDir,
+    package_id: &str,
+) -> Result<PackageEvents, PackageIoError> {
+    match packages_dir.load_events(package_id) {
+        Ok(package_events) => Ok(package_events),
+        Err(_) => {
+            let package = packages_dir.load_package(package_id)?.package;
+            Ok(PackageEvents::from(&package))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use move_binary_format::file_format::{Ability, AbilitySet};
+
+    use super::*;
+    use crate::common_types::MovePackageWithMetadata;
+    use crate::test_utils::{
+        test_dir, test_package, test_package_with_metadata, test_upgraded_package,
+        write_test_package, TestModuleBuilder,
+    };
+
+    // test_package_with_metadata, which emits nothing, and two versions of 0x7::pool
+    fn write_test_store(name: &str) -> PackagesDir {
+        let packages_dir = PackagesDir::new(test_dir(name));
+        let test_pkg = test_package_with_metadata();
+        write_test_package(&packages_dir, &test_pkg);
+        let mut builder =
+            TestModuleBuilder::at(AccountAddress::from_hex_literal("0x7").unwrap(), "pool");
+        let swapped = builder.add_struct(
+            "Swapped",
+            AbilitySet::EMPTY | Ability::Copy | Ability::Drop,
+            vec![],
+            &[],
+        );
+        builder.add_function("swap", 0, &[("event", "emit", vec![swapped])]);
+        let pool = builder.build();
+        for package in [
+            test_package(&[pool.clone()], 1),
+            test_upgraded_package("0x8", &[pool], 2, &[]),
+        ] {
+            write_test_package(
+                &packages_dir,
+                &MovePackageWithMetadata {
+                    package,
+                    ..test_pkg.clone()
+                },
+            );
+        }
+        packages_dir
+    }
+
+    #[test]
+    fn emitting_packages_of_every_version() {
+        let packages_dir = write_test_store("events-index-update");
+        let mut index = EventsIndex::load(&packages_dir.get_prefix()).unwrap();
+        assert_eq!(index.update(&packages_dir).unwrap().updated, 3);
+        index.save(&packages_dir.get_prefix()).unwrap();
+        let index = EventsIndex::load(&packages_dir.get_prefix()).unwrap();
+
+        let expected: BTreeSet<String> = ["0x7", "0x8"]
+            .into_iter()
+            .map(|id| {
+                AccountAddress::from_hex_literal(id)
+                    .unwrap()
+                    .to_canonical_string(true)
+            })
+            .collect();
+        // short addresses and type arguments are accepted
+        assert_eq!(
+            index.emitting_packages("0x7::pool::Swapped").unwrap(),
+            Some(&expected)
+        );
+        assert_eq!(
+            index.emitting_packages("0x7::pool::Swapped<u64>").unwrap(),
+            Some(&expected)
+        );
+        assert_eq!(index.emitting_packages("0x7::pool::Other").unwrap(), None);
+        assert!(matches!(
+            index.emitting_packages("0x7::pool"),
+            Err(EventsIndexError::InvalidEventType(_))
+        ));
+    }
+
+    #[test]
+    fn reindex_package_upgraded_in_place() {
+        let packages_dir = write_test_store("events-index-in-place");
+        let mut index = EventsIndex::load(&packages_dir.get_prefix()).unwrap();
+        index.update(&packages_dir).unwrap();
+
+        // 0x7::pool at version 2 under the same storage id no longer emits Swapped
+        let mut builder =
+            TestModuleBuilder::at(AccountAddress::from_hex_literal("0x7").unwrap(), "pool");
+        builder.add_function("swap", 0, &[]);
+        write_test_package(
+            &packages_dir,
+            &MovePackageWithMetadata {
+                package: test_package(&[builder.build()], 2),
+                ..test_package_with_metadata()
+            },
+        );
+        let update = index.update(&packages_dir).unwrap();
+        assert_eq!(update.updated, 1);
+        assert!(update.failures.is_empty());
+        let upgraded_id = AccountAddress::from_hex_literal("0x8")
+            .unwrap()
+            .to_canonical_string(true);
+        assert_eq!(
+            index.emitting_packages("0x7::pool::Swapped").unwrap(),
+            Some(&BTreeSet::from([upgraded_id]))
+        );
+    }
+}
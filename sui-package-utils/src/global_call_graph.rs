@@ -0,0 +1,390 @@
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::fs;
+use std::path::Path;
+
+use move_core_types::account_address::AccountAddress;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::call_graph::PackageCallGraph;
+use crate::metadata::PackageMetadata;
+use crate::package_id_io::{PackageIoError, PackagesDir};
+
+// store-level file next to layout.json, merged from every package's call_graph.json
+pub const GLOBAL_CALL_GRAPH_FILE: &str = "global_call_graph.json";
+// bump when the shape of global_call_graph.json changes, older files are rebuilt from scratch
+pub const GLOBAL_CALL_GRAPH_SCHEMA_VERSION: u32 = 2;
+
+// nodes are fully qualified functions at the storage id of the package version that defines
+// them, edges follow the linkage tables so they point at the version actually called
+#[derive(Debug, Default, Deserialize, Serialize, JsonSchema)]
+pub struct GlobalCallGraph {
+    schema_version: u32,
+    // storage id -> version of the packages already merged, so updates only read packages that
+    // were added or upgraded in place since. System packages keep their id across versions
+    packages: BTreeMap<String, u64>,
+    edges: BTreeMap<String, BTreeSet<String>>,
+    #[serde(skip)]
+    reverse_edges: BTreeMap<String, BTreeSet<String>>,
+}
+
+// outcome of merging the store into one of the store-level indexes. A package that cannot be read
+// is reported and left out, so it is retried on the next update
+#[derive(Debug, Default)]
+pub struct StoreIndexUpdate {
+    pub updated: usize,
+    pub failures: Vec<(String, String)>,
+}
+
+// packages of the store that are not in an index yet or were indexed at another version, keyed
+// by storage id with their metadata. Packages whose metadata cannot be read go to the failures
+pub fn get_changed_packages(
+    packages_dir: &PackagesDir,
+    indexed: &BTreeMap<String, u64>,
+    update: &mut StoreIndexUpdate,
+) -> Result<Vec<(String, PackageMetadata)>, std::io::Error> {
+    let mut changed = Vec::new();
+    for package_path in packages_dir.get_package_directories()? {
+        let Some(package_id) = packages_dir.get_package_id_from_dir(&package_path) else {
+            continue;
+        };
+        match packages_dir.load_metadata(&package_id) {
+            Ok(metadata) if indexed.get(&package_id) == Some(&metadata.version) => {}
+            Ok(metadata) => changed.push((package_id, metadata)),
+            Err(e) => update.failures.push((package_id, e.to_string())),
+        }
+    }
+    Ok(changed)
+}
+
+#[derive(Error, Debug)]
+pub enum GlobalCallGraphError {
+    #[error("Error reading or writing global_call_graph.json: {0}")]
+    IoError(#[from] std::io::Error),
+    #[error("Error parsing global_call_graph.json: {0}")]
+    ParseError(#[from] serde_json::Error),
+    #[error("Invalid function name {0}, expected <address>::<module>::<function>")]
+    InvalidFunctionName(String),
+}
+
+impl GlobalCallGraph {
+    // returns an empty graph if the store has none yet or it was written by an older version
+    pub fn load(prefix: &Path) -> Result<Self, GlobalCallGraphError> {
+        let graph_file = prefix.join(GLOBAL_CALL_GRAPH_FILE);
+        if !graph_file.exists() {
+            return Ok(Self::new());
+        }
+        let mut graph: GlobalCallGraph = serde_json::from_str(&fs::read_to_string(graph_file)?)?;
+        if graph.schema_version != GLOBAL_CALL_GRAPH_SCHEMA_VERSION {
+            return Ok(Self::new());
+        }
+        graph.build_reverse_edges();
+        Ok(graph)
+    }
+
+    pub fn save(&self, prefix: &Path) -> Result<(), GlobalCallGraphError> {
+        fs::write(
+            prefix.join(GLOBAL_CALL_GRAPH_FILE),
+            serde_json::to_string_pretty(self)?,
+        )?;
+        Ok(())
+    }
+
+    fn new() -> Self {
+        GlobalCallGraph {
+            schema_version: GLOBAL_CALL_GRAPH_SCHEMA_VERSION,
+            ..Default::default()
+        }
+    }
+
+    fn build_reverse_edges(&mut self) {
+        self.reverse_edges.clear();
+        for (caller, callees) in &self.edges {
+            for callee in callees {
+                self.reverse_edges
+                    .entry(callee.clone())
+                    .or_default()
+                    .insert(caller.clone());
+            }
+        }
+    }
+
+    pub fn get_packages(&self) -> &BTreeMap<String, u64> {
+        &self.packages
+    }
+
+    pub fn get_edges(&self) -> &BTreeMap<String, BTreeSet<String>> {
+        &self.edges
+    }
+
+    // merges the packages of the store that are not in the graph yet or were merged at another
+    // version, replacing the edges of the previous version
+    pub fn update(
+        &mut self,
+        packages_dir: &PackagesDir,
+    ) -> Result<StoreIndexUpdate, GlobalCallGraphError> {
+        let mut update = StoreIndexUpdate::default();
+        for (package_id, metadata) in
+            get_changed_packages(packages_dir, &self.packages, &mut update)?
+        {
+            match load_linkage_call_graph(packages_dir, &package_id) {
+                Ok(call_graph) => {
+                    self.remove_package(&package_id);
+                    self.add_package(&call_graph, metadata.version);
+                    update.updated += 1;
+                }
+                Err(e) => update.failures.push((package_id, e.to_string())),
+            }
+        }
+        Ok(update)
+    }
+
+    pub fn add_package(&mut self, call_graph: &PackageCallGraph, version: u64) {
+        let package_id = call_graph.get_package_id().to_string();
+        for module_call_graph in call_graph.get_module_call_graphs() {
+            for (caller, callees) in module_call_graph.get_linkage_call_graph() {
+                let caller = format!(
+                    "{}::{}::{}",
+                    package_id,
+                    module_call_graph.get_module_name(),
+                    caller
+                );
+                for callee in callees {
+                    self.reverse_edges
+                        .entry(callee.clone())
+                        .or_default()
+                        .insert(caller.clone());
+                }
+                self.edges
+                    .entry(caller)
+                    .or_default()
+                    .extend(callees.iter().cloned());
+            }
+        }
+        self.packages.insert(package_id, version);
+    }
+
+    // drops the edges going out of the functions of a package, edges of other packages pointing
+    // at it stay since their bytecode still calls it
+    pub fn remove_package(&mut self, package_id: &str) {
+        if self.packages.remove(package_id).is_none() {
+            return;
+        }
+        let function_prefix = format!("{}::", package_id);
+        self.edges
+            .retain(|caller, _| !caller.starts_with(&function_prefix));
+        for callers in self.reverse_edges.values_mut() {
+            callers.retain(|caller| !caller.starts_with(&function_prefix));
+        }
+        self.reverse_edges.retain(|_, callers| !callers.is_empty());
+    }
+
+    // every function that calls the given one, directly or through other functions
+    pub fn callers(&self, function: &str) -> Result<BTreeSet<String>, GlobalCallGraphError> {
+        Ok(traverse(
+            &self.reverse_edges,
+            &canonical_function_name(function)?,
+        ))
+    }
+
+    // every function the given one may call, directly or through other functions
+    pub fn reachable(&self, function: &str) -> Result<BTreeSet<String>, GlobalCallGraphError> {
+        Ok(traverse(&self.edges, &canonical_function_name(function)?))
+    }
+}
+
+// call graphs written before the linkage call graph or the call sites existed are regenerated
+// from bcs.json
+pub fn load_linkage_call_graph(
+    packages_dir: &PackagesDir,
+    package_id: &str,
+) -> Result<PackageCallGraph, PackageIoError> {
+    match packages_dir.load_call_graph(package_id) {
+        Ok(call_graph) if call_graph.has_linkage_call_graph() && call_graph.has_call_sites() => {
+            Ok(call_graph)
+        }
+        _ => {
+            let package = packages_dir.load_package(package_id)?.package;
+            Ok(PackageCallGraph::from(&package))
+        }
+    }
+}
+
+fn traverse(edges: &BTreeMap<String, BTreeSet<String>>, start: &str) -> BTreeSet<String> {
+    let mut visited = BTreeSet::new();
+    let mut queue = VecDeque::from([start.to_string()]);
+    while let Some(function) = queue.pop_front() {
+        for next in edges.get(&function).into_iter().flatten() {
+            if visited.insert(next.clone()) {
+                queue.push_back(next.clone());
+            }
+        }
+    }
+    // recursive functions reach themselves, which is not interesting to report
+    visited.remove(start);
+    visited
+}
+
+// accepts short addresses like 0x2::coin::mint and expands them to the 66 char form used in
+// call graphs
+pub fn canonical_function_name(function: &str) -> Result<String, GlobalCallGraphError> {
+    let parts: Vec<&str> = function.split("::").collect();
+    let [address, module, name] = parts[..] else {
+        return Err(GlobalCallGraphError::InvalidFunctionName(
+            function.to_string(),
+        ));
+    };
+    let address = AccountAddress::from_hex_literal(address)
+        .map_err(|_| GlobalCallGraphError::InvalidFunctionName(function.to_string()))?;
+    Ok(format!(
+        "{}::{}::{}",
+        address.to_canonical_string(true),
+        module,
+        name
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common_types::MovePackageWithMetadata;
+    use crate::test_utils::{
+        test_dir, test_package, test_package_with_metadata, write_test_package, TestModuleBuilder,
+    };
+
+    // 0x5::pool::swap calling the given function of 0x0::test, at the given version
+    fn write_pool_package(packages_dir: &PackagesDir, callee: &str, version: u64) {
+        let mut builder =
+            TestModuleBuilder::at(AccountAddress::from_hex_literal("0x5").unwrap(), "pool");
+        builder.add_function("swap", 0, &[("0x0::test", callee, vec![])]);
+        write_test_package(
+            packages_dir,
+            &MovePackageWithMetadata {
+                package: test_package(&[builder.build()], version),
+                ..test_package_with_metadata()
+            },
+        );
+    }
+
+    // 0x0::test from test_package_with_metadata and 0x5::pool calling into it
+    fn write_test_store(name: &str) -> PackagesDir {
+        let packages_dir = PackagesDir::new(test_dir(name));
+        write_test_package(&packages_dir, &test_package_with_metadata());
+        write_pool_package(&packages_dir, "mint", 1);
+        packages_dir
+    }
+
+    fn names(functions: &[&str]) -> BTreeSet<String> {
+        functions
+            .iter()
+            .map(|function| canonical_function_name(function).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn update_and_query() {
+        let packages_dir = write_test_store("global-call-graph-update");
+        let mut graph = GlobalCallGraph::load(&packages_dir.get_prefix()).unwrap();
+        assert_eq!(graph.update(&packages_dir).unwrap().updated, 2);
+        // packages already merged are not read again
+        assert_eq!(graph.update(&packages_dir).unwrap().updated, 0);
+
+        assert_eq!(
+            graph.callers("0x0::test::new").unwrap(),
+            names(&["0x0::test::mint", "0x5::pool::swap"])
+        );
+        assert_eq!(
+            graph.reachable("0x5::pool::swap").unwrap(),
+            names(&["0x0::test::mint", "0x0::test::new"])
+        );
+        assert!(graph.reachable("0x0::test::new").unwrap().is_empty());
+    }
+
+    #[test]
+    fn update_package_upgraded_in_place() {
+        let packages_dir = write_test_store("global-call-graph-in-place");
+        let mut graph = GlobalCallGraph::load(&packages_dir.get_prefix()).unwrap();
+        graph.update(&packages_dir).unwrap();
+
+        // same storage id at a new version, the way system packages are upgraded
+        write_pool_package(&packages_dir, "new", 2);
+        let update = graph.update(&packages_dir).unwrap();
+        assert_eq!(update.updated, 1);
+        assert!(update.failures.is_empty());
+        let pool_id = AccountAddress::from_hex_literal("0x5")
+            .unwrap()
+            .to_canonical_string(true);
+        assert_eq!(graph.get_packages()[&pool_id], 2);
+        // the edges of version 1 are gone, not merged with the new ones
+        assert!(graph.callers("0x0::test::mint").unwrap().is_empty());
+        assert_eq!(
+            graph.reachable("0x5::pool::swap").unwrap(),
+            names(&["0x0::test::new"])
+        );
+        assert_eq!(
+            graph.callers("0x0::test::new").unwrap(),
+            names(&["0x0::test::mint", "0x5::pool::swap"])
+        );
+    }
+
+    #[test]
+    fn remove_package_keeps_edges_into_it() {
+        let packages_dir = write_test_store("global-call-graph-remove");
+        let mut graph = GlobalCallGraph::load(&packages_dir.get_prefix()).unwrap();
+        graph.update(&packages_dir).unwrap();
+
+        graph.remove_package(&AccountAddress::ZERO.to_canonical_string(true));
+        assert_eq!(graph.get_packages().len(), 1);
+        // 0x5::pool::swap still calls mint, whose own edges are gone
+        assert_eq!(
+            graph.callers("0x0::test::mint").unwrap(),
+            names(&["0x5::pool::swap"])
+        );
+        assert!(graph.callers("0x0::test::new").unwrap().is_empty());
+    }
+
+    #[test]
+    fn update_reports_failed_packages() {
+        let packages_dir = write_test_store("global-call-graph-failure");
+        let broken_id = AccountAddress::from_hex_literal("0x6")
+            .unwrap()
+            .to_canonical_string(true);
+        fs::create_dir_all(packages_dir.get_package_dir(&broken_id)).unwrap();
+        let mut graph = GlobalCallGraph::load(&packages_dir.get_prefix()).unwrap();
+
+        let update = graph.update(&packages_dir).unwrap();
+        assert_eq!(update.updated, 2);
+        assert_eq!(update.failures.len(), 1);
+        assert_eq!(update.failures[0].0, broken_id);
+        // left out so the next update retries it
+        assert!(!graph.get_packages().contains_key(&broken_id));
+    }
+
+    #[test]
+    fn save_and_load() {
+        let packages_dir = write_test_store("global-call-graph-save");
+        let mut graph = GlobalCallGraph::load(&packages_dir.get_prefix()).unwrap();
+        graph.update(&packages_dir).unwrap();
+        graph.save(&packages_dir.get_prefix()).unwrap();
+
+        // the reverse edges are not stored and have to be rebuilt on load
+        let loaded = GlobalCallGraph::load(&packages_dir.get_prefix()).unwrap();
+        assert_eq!(loaded.get_packages(), graph.get_packages());
+        assert_eq!(loaded.get_edges(), graph.get_edges());
+        assert_eq!(
+            loaded.callers("0x0::test::mint").unwrap(),
+            names(&["0x5::pool::swap"])
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_function_names() {
+        for function in ["0x2::coin", "0x2::coin::mint::extra", "coin::mint::x"] {
+            assert!(matches!(
+                canonical_function_name(function),
+                Err(GlobalCallGraphError::InvalidFunctionName(_))
+            ));
+        }
+    }
+}
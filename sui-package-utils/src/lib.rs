@@ -1,9 +1,31 @@
+pub mod artifact_manifest;
+pub mod audit;
 pub mod bcs_json;
 pub mod call_graph;
+pub mod call_graph_export;
+pub mod cfg;
+pub mod codegen;
 pub mod common_types;
+pub mod constants;
 pub mod csv;
+pub mod dynamic_fields;
+pub mod events;
+pub mod events_index;
+pub mod global_call_graph;
 pub mod graphql;
 pub mod json_rpc;
 pub mod metadata;
+pub mod module_graph;
+pub mod normalized_modules;
+pub mod object_lifecycle;
+pub mod package_artifacts;
 pub mod package_id_io;
 pub mod package_saver;
+pub mod reachability;
+pub mod reverse_call_index;
+pub mod run_report;
+pub mod schemas;
+pub mod store_layout;
+
+#[cfg(test)]
+mod test_utils;
\ No newline at end of file
@@ -0,0 +1,64 @@
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+
+use clap::Parser;
+use sui_package_utils::audit::{audit_package, repair_package, AuditReport};
+use sui_package_utils::package_id_io::PackagesDir;
+use sui_package_utils::store_layout::get_store_schema_version;
+
+#[derive(Parser)]
+struct Args {
+    #[arg(long)]
+    packages_dir: PathBuf,
+    #[arg(long)]
+    report_file: PathBuf,
+    // misplaced copies of packages that are already in place are moved to
+    // <packages_dir>/quarantine rather than deleted
+    #[arg(long, default_value = "false")]
+    repair: bool,
+    // needed to repair missing or empty decompiled modules
+    #[arg(long)]
+    move_decompiler_path: Option<PathBuf>,
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let cli_args = Args::parse();
+    let packages_dir = PackagesDir::new(cli_args.packages_dir.clone());
+    let bcs_json_schema_version = get_store_schema_version(&packages_dir.get_prefix(), "bcs.json")?;
+    let mut audit_report = AuditReport::default();
+    for package_path in packages_dir.get_package_directories()? {
+        let mut package_report =
+            audit_package(&packages_dir, &package_path, bcs_json_schema_version);
+        if !package_report.issues.is_empty() {
+            println!(
+                "{}: {} issue(s)",
+                package_path.to_str().unwrap(),
+                package_report.issues.len()
+            );
+            if cli_args.repair {
+                match repair_package(
+                    &packages_dir,
+                    &package_path,
+                    &package_report,
+                    cli_args.move_decompiler_path.as_ref(),
+                ) {
+                    Ok(repaired) => package_report.repaired = repaired,
+                    Err(e) => package_report.repair_error = Some(e),
+                }
+            }
+        }
+        audit_report.add(package_report);
+    }
+    println!(
+        "Checked {} packages, {} with issues, {} repaired",
+        audit_report.packages_checked,
+        audit_report.packages_with_issues,
+        audit_report.packages_repaired
+    );
+    fs::write(
+        &cli_args.report_file,
+        serde_json::to_string_pretty(&audit_report)?,
+    )?;
+    Ok(())
+}
@@ -0,0 +1,66 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+
+use clap::Parser;
+use sui_package_utils::bcs_json::BcsJsonMismatch;
+use sui_package_utils::package_id_io::PackagesDir;
+use sui_package_utils::store_layout::get_store_schema_version;
+
+#[derive(Parser)]
+struct Args {
+    #[arg(long)]
+    packages_dir: PathBuf,
+    // validates only this package instead of the whole store
+    #[arg(long)]
+    package_id: Option<String>,
+    #[arg(long)]
+    report_file: Option<PathBuf>,
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let cli_args = Args::parse();
+    let packages_dir = PackagesDir::new(cli_args.packages_dir.clone());
+    let package_ids: Vec<String> = match &cli_args.package_id {
+        Some(package_id) => vec![package_id.clone()],
+        None => packages_dir
+            .get_package_directories()?
+            .iter()
+            .filter_map(|package_path| packages_dir.get_package_id_from_dir(package_path))
+            .collect(),
+    };
+
+    let bcs_json_schema_version = get_store_schema_version(&packages_dir.get_prefix(), "bcs.json")?;
+    let mut report: BTreeMap<String, Vec<BcsJsonMismatch>> = BTreeMap::new();
+    let mut unreadable_packages = 0;
+    for package_id in package_ids {
+        let mismatches = match packages_dir.load_bcs_json(&package_id) {
+            Ok(bcs_json) => bcs_json.validate(bcs_json_schema_version),
+            Err(e) => {
+                println!("Error reading bcs.json of {}: {}", package_id, e);
+                unreadable_packages += 1;
+                continue;
+            }
+        };
+        for mismatch in &mismatches {
+            println!("{}: {:?}", package_id, mismatch);
+        }
+        if !mismatches.is_empty() {
+            report.insert(package_id, mismatches);
+        }
+    }
+    println!(
+        "{} invalid and {} unreadable bcs.json files",
+        report.len(),
+        unreadable_packages
+    );
+
+    if let Some(report_file) = cli_args.report_file {
+        fs::write(report_file, serde_json::to_string_pretty(&report)?)?;
+    }
+    if !report.is_empty() || unreadable_packages > 0 {
+        return Err("bcs.json validation failed".into());
+    }
+    Ok(())
+}
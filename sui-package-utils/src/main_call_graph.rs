@@ -0,0 +1,181 @@
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+
+use clap::{Parser, Subcommand, ValueEnum};
+use sui_package_utils::call_graph_export::{CallGraphExport, ExportFormat, ExportOptions};
+use sui_package_utils::global_call_graph::{
+    GlobalCallGraph, StoreIndexUpdate, GLOBAL_CALL_GRAPH_FILE,
+};
+use sui_package_utils::package_id_io::PackagesDir;
+use sui_package_utils::reverse_call_index::{ReverseCallIndex, REVERSE_CALL_INDEX_FILE};
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Format {
+    Dot,
+    Graphml,
+    Mermaid,
+}
+
+#[derive(Parser)]
+struct Args {
+    #[arg(long)]
+    packages_dir: PathBuf,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    // merges packages added to the store since the last update into global_call_graph.json
+    // and reverse_call_index.json
+    Update,
+    // all transitive callers of a function, e.g. 0x2::coin::mint
+    Callers {
+        #[arg(long)]
+        function: String,
+    },
+    // everything a function may call, directly or indirectly
+    Reachable {
+        #[arg(long)]
+        function: String,
+    },
+    // packages, modules and functions calling a function directly, at any of its versions
+    WhoCalls {
+        #[arg(long)]
+        function: String,
+        // only callers linked to the exact package version in the function name
+        #[arg(long, default_value = "false")]
+        version_aware: bool,
+    },
+    // renders a package's call graph, or the subgraph around a function, for visualization tools
+    Export {
+        #[arg(
+            long,
+            conflicts_with = "function",
+            required_unless_present = "function"
+        )]
+        package_id: Option<String>,
+        // exports what the function may reach, or its callers with --callers
+        #[arg(long)]
+        function: Option<String>,
+        #[arg(long, default_value = "false", requires = "function")]
+        callers: bool,
+        #[arg(long, value_enum)]
+        format: Format,
+        // prints to stdout when not set
+        #[arg(long)]
+        out_file: Option<PathBuf>,
+        #[arg(long, default_value = "false")]
+        cluster_by_module: bool,
+        #[arg(long, default_value = "false")]
+        color_by_visibility: bool,
+        #[arg(long, default_value = "false")]
+        collapse_framework: bool,
+    },
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let cli_args = Args::parse();
+    let packages_dir = PackagesDir::new(cli_args.packages_dir.clone());
+    match cli_args.command {
+        Command::Update => {
+            let mut global_call_graph = GlobalCallGraph::load(&cli_args.packages_dir)?;
+            let update = global_call_graph.update(&packages_dir)?;
+            global_call_graph.save(&cli_args.packages_dir)?;
+            report_update(
+                &update,
+                global_call_graph.get_packages().len(),
+                GLOBAL_CALL_GRAPH_FILE,
+            );
+            let mut reverse_call_index = ReverseCallIndex::load(&cli_args.packages_dir)?;
+            let update = reverse_call_index.update(&packages_dir)?;
+            reverse_call_index.save(&cli_args.packages_dir)?;
+            report_update(
+                &update,
+                reverse_call_index.get_packages().len(),
+                REVERSE_CALL_INDEX_FILE,
+            );
+        }
+        Command::Callers { function } => {
+            let global_call_graph = GlobalCallGraph::load(&cli_args.packages_dir)?;
+            for caller in global_call_graph.callers(&function)? {
+                println!("{}", caller);
+            }
+        }
+        Command::Reachable { function } => {
+            let global_call_graph = GlobalCallGraph::load(&cli_args.packages_dir)?;
+            for callee in global_call_graph.reachable(&function)? {
+                println!("{}", callee);
+            }
+        }
+        Command::WhoCalls {
+            function,
+            version_aware,
+        } => {
+            let reverse_call_index = ReverseCallIndex::load(&cli_args.packages_dir)?;
+            for caller in reverse_call_index.who_calls(&function, version_aware)? {
+                println!(
+                    "{}::{}::{} (calls version {})",
+                    caller.package_id,
+                    caller.module_name,
+                    caller.function_name,
+                    caller.callee_package_id
+                );
+            }
+        }
+        Command::Export {
+            package_id,
+            function,
+            callers,
+            format,
+            out_file,
+            cluster_by_module,
+            color_by_visibility,
+            collapse_framework,
+        } => {
+            let mut export = match (package_id, function) {
+                (Some(package_id), _) => CallGraphExport::from_package(&packages_dir, &package_id)?,
+                (None, Some(function)) => {
+                    let global_call_graph = GlobalCallGraph::load(&cli_args.packages_dir)?;
+                    CallGraphExport::from_global_call_graph(&global_call_graph, &function, callers)?
+                }
+                (None, None) => return Err("either --package-id or --function is required".into()),
+            };
+            if color_by_visibility {
+                export.annotate(&packages_dir);
+            }
+            let format = match format {
+                Format::Dot => ExportFormat::Dot,
+                Format::Graphml => ExportFormat::GraphMl,
+                Format::Mermaid => ExportFormat::Mermaid,
+            };
+            let rendered = export.render(
+                format,
+                &ExportOptions {
+                    cluster_by_module,
+                    color_by_visibility,
+                    collapse_framework,
+                },
+            );
+            match out_file {
+                Some(out_file) => fs::write(out_file, rendered)?,
+                None => println!("{}", rendered),
+            }
+        }
+    }
+    Ok(())
+}
+
+fn report_update(update: &StoreIndexUpdate, package_count: usize, index_file: &str) {
+    for (package_id, e) in &update.failures {
+        println!("Skipped {}: {}", package_id, e);
+    }
+    println!(
+        "Updated {} packages, {} failed, {} packages in {}",
+        update.updated,
+        update.failures.len(),
+        package_count,
+        index_file
+    );
+}
@@ -0,0 +1,86 @@
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+
+use clap::{Parser, ValueEnum};
+use move_binary_format::file_format::CompiledModule;
+use sui_package_utils::cfg::FunctionCfg;
+use sui_package_utils::package_id_io::PackagesDir;
+
+#[derive(Clone, Copy, ValueEnum)]
+enum CfgFormat {
+    Json,
+    Dot,
+}
+
+#[derive(Parser)]
+struct Args {
+    #[arg(long)]
+    packages_dir: PathBuf,
+    #[arg(long)]
+    package_id: String,
+    // only functions of this module
+    #[arg(long)]
+    module: Option<String>,
+    // only functions with this name
+    #[arg(long)]
+    function: Option<String>,
+    #[arg(long, value_enum)]
+    format: CfgFormat,
+    // one file per function, written to <module>/<function>.<format>
+    #[arg(long)]
+    out_dir: PathBuf,
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let cli_args = Args::parse();
+    let packages_dir = PackagesDir::new(cli_args.packages_dir.clone());
+    let pkg_with_metadata = packages_dir.load_package(&cli_args.package_id)?;
+    fs::create_dir_all(&cli_args.out_dir)?;
+    let mut written = 0;
+    for (module_name, module_bytes) in pkg_with_metadata.package.serialized_module_map() {
+        if cli_args
+            .module
+            .as_ref()
+            .is_some_and(|module| module != module_name)
+        {
+            continue;
+        }
+        // the instructions are rendered from the bytecode, cfg.json only has offsets
+        let module = CompiledModule::deserialize_with_defaults(module_bytes).unwrap();
+        let module_dir = cli_args.out_dir.join(module_name);
+        for function_def in module.function_defs() {
+            let Some(code_unit) = &function_def.code else {
+                continue;
+            };
+            let function_handle = module.function_handle_at(function_def.function);
+            let function_name = module.identifier_at(function_handle.name).as_str();
+            if cli_args
+                .function
+                .as_ref()
+                .is_some_and(|function| function != function_name)
+            {
+                continue;
+            }
+            let function_cfg = FunctionCfg::from(code_unit);
+            let annotated_cfg = function_cfg.annotate(&code_unit.code);
+            let (extension, contents) = match cli_args.format {
+                CfgFormat::Json => ("json", serde_json::to_string_pretty(&annotated_cfg)?),
+                CfgFormat::Dot => (
+                    "dot",
+                    annotated_cfg.to_dot(&format!("{}::{}", module_name, function_name)),
+                ),
+            };
+            // identifiers cannot contain a path separator, so a directory per module is
+            // collision free
+            fs::create_dir_all(&module_dir)?;
+            fs::write(
+                module_dir.join(format!("{}.{}", function_name, extension)),
+                contents,
+            )?;
+            written += 1;
+        }
+    }
+    println!("Wrote {} files to {}", written, cli_args.out_dir.display());
+    Ok(())
+}
@@ -0,0 +1,71 @@
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+
+use clap::{Parser, ValueEnum};
+use sui_package_utils::codegen::move_interface::write_move_interface;
+use sui_package_utils::codegen::rust::{generate_rust, package_module_name};
+use sui_package_utils::codegen::typescript::generate_typescript;
+use sui_package_utils::package_id_io::PackagesDir;
+
+#[derive(Clone, Copy, ValueEnum)]
+enum CodegenTarget {
+    Typescript,
+    Rust,
+    MoveInterface,
+}
+
+#[derive(Parser)]
+struct Args {
+    #[arg(long)]
+    packages_dir: PathBuf,
+    #[arg(long)]
+    package_id: String,
+    #[arg(long, value_enum)]
+    target: CodegenTarget,
+    #[arg(long)]
+    out_dir: PathBuf,
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let cli_args = Args::parse();
+    let packages_dir = PackagesDir::new(cli_args.packages_dir.clone());
+    let pkg_with_metadata = packages_dir.load_package(&cli_args.package_id)?;
+    let package = &pkg_with_metadata.package;
+    fs::create_dir_all(&cli_args.out_dir)?;
+    let out_file = match cli_args.target {
+        CodegenTarget::Typescript => {
+            let out_file = cli_args
+                .out_dir
+                .join(format!("{}.ts", package.id().to_canonical_string(true)));
+            fs::write(&out_file, generate_typescript(package))?;
+            out_file
+        }
+        // dependency types are loaded from the same store
+        CodegenTarget::Rust => {
+            let out_file = cli_args.out_dir.join(format!(
+                "{}.rs",
+                package_module_name(&package.id().to_canonical_string(true))
+            ));
+            fs::write(
+                &out_file,
+                generate_rust(&packages_dir, &package.id().to_canonical_string(true))?,
+            )?;
+            out_file
+        }
+        // a Move package per directory, with the dependencies from the linkage table next to it
+        CodegenTarget::MoveInterface => {
+            let package_dirs = write_move_interface(
+                &packages_dir,
+                &package.id().to_canonical_string(true),
+                &cli_args.out_dir,
+            )?;
+            for package_dir in &package_dirs {
+                println!("Wrote {}", package_dir.display());
+            }
+            return Ok(());
+        }
+    };
+    println!("Wrote {}", out_file.display());
+    Ok(())
+}
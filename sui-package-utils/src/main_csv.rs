@@ -6,7 +6,10 @@ use std::path::PathBuf;
 
 use sui_package_utils::common_types::MovePackageWithMetadata;
 use sui_package_utils::csv::PackageBcsWithCreationInfo;
+use sui_package_utils::package_artifacts::ArtifactArgs;
+use sui_package_utils::package_id_io::PackagesDir;
 use sui_package_utils::package_saver::{save_package, SaveArgs};
+use sui_package_utils::store_layout::ensure_current_layout;
 
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
 enum Network {
@@ -22,6 +25,8 @@ struct Args {
     packages_dir: PathBuf,
     #[arg(long)]
     move_decompiler_path: PathBuf,
+    #[command(flatten)]
+    artifacts: ArtifactArgs,
     #[arg(long, default_value = "false")]
     force: bool,
 }
@@ -32,6 +37,7 @@ impl Into<SaveArgs> for &Args {
             bcs: true,
             bytecode: true,
             call_graph: true,
+            artifacts: self.artifacts.get_enabled(),
             metadata: true,
             move_code: true,
             force: self.force,
@@ -48,6 +54,10 @@ fn main() -> Result<(), Box<dyn Error>> {
 }
 
 fn process_csv_records(cli_args: &Args) -> Result<(), Box<dyn Error>> {
+    ensure_current_layout(
+        &PackagesDir::new(cli_args.packages_dir.clone()),
+        &cli_args.artifacts.get_enabled(),
+    )?;
     let save_args: SaveArgs = cli_args.into();
     let mut rdr = csv::Reader::from_path(&cli_args.package_bcs_csv)?;
     for result in rdr.deserialize::<PackageBcsWithCreationInfo>() {
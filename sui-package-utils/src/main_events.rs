@@ -0,0 +1,58 @@
+use std::error::Error;
+use std::path::PathBuf;
+
+use clap::{Parser, Subcommand};
+use sui_package_utils::events_index::{EventsIndex, EVENTS_INDEX_FILE};
+use sui_package_utils::package_id_io::PackagesDir;
+
+#[derive(Parser)]
+struct Args {
+    #[arg(long)]
+    packages_dir: PathBuf,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    // indexes packages added or upgraded in place since the last update into events_index.json
+    Update,
+    // package versions emitting an event type, e.g. 0x2::coin::CurrencyCreated
+    Emitters {
+        #[arg(long)]
+        event_type: String,
+    },
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let cli_args = Args::parse();
+    let packages_dir = PackagesDir::new(cli_args.packages_dir.clone());
+    match cli_args.command {
+        Command::Update => {
+            let mut events_index = EventsIndex::load(&cli_args.packages_dir)?;
+            let update = events_index.update(&packages_dir)?;
+            events_index.save(&cli_args.packages_dir)?;
+            for (package_id, e) in &update.failures {
+                println!("Skipped {}: {}", package_id, e);
+            }
+            println!(
+                "Updated {} packages, {} failed, {} packages in {}",
+                update.updated,
+                update.failures.len(),
+                events_index.get_packages().len(),
+                EVENTS_INDEX_FILE
+            );
+        }
+        Command::Emitters { event_type } => {
+            let events_index = EventsIndex::load(&cli_args.packages_dir)?;
+            for package_id in events_index
+                .emitting_packages(&event_type)?
+                .into_iter()
+                .flatten()
+            {
+                println!("{}", package_id);
+            }
+        }
+    }
+    Ok(())
+}
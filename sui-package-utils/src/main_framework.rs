@@ -3,7 +3,10 @@ use std::path::PathBuf;
 
 use clap::Parser;
 use sui_package_utils::graphql::PackageGraphQLFetcher;
+use sui_package_utils::package_artifacts::ArtifactArgs;
+use sui_package_utils::package_id_io::PackagesDir;
 use sui_package_utils::package_saver::{save_package, SaveArgs};
+use sui_package_utils::store_layout::ensure_current_layout;
 
 #[derive(Parser)]
 struct Args {
@@ -11,11 +14,17 @@ struct Args {
     move_decompiler_path: PathBuf,
     #[arg(long)]
     packages_dir: PathBuf,
+    #[command(flatten)]
+    artifacts: ArtifactArgs,
     #[arg(long, default_value = "true")]
     force: bool,
 }
 fn main() -> Result<(), Box<dyn Error>> {
     let cli_args = Args::parse();
+    ensure_current_layout(
+        &PackagesDir::new(cli_args.packages_dir.clone()),
+        &cli_args.artifacts.get_enabled(),
+    )?;
     let framework_packages = vec![
         "0x0000000000000000000000000000000000000000000000000000000000000001",
         "0x0000000000000000000000000000000000000000000000000000000000000002",
@@ -27,6 +36,7 @@ fn main() -> Result<(), Box<dyn Error>> {
         bcs: true,
         bytecode: true,
         call_graph: true,
+        artifacts: cli_args.artifacts.get_enabled(),
         metadata: true,
         move_code: true,
         force: cli_args.force,
@@ -4,8 +4,11 @@ use std::path::PathBuf;
 
 use clap::Parser;
 use sui_package_utils::graphql::PackageGraphQLFetcher;
+use sui_package_utils::package_artifacts::ArtifactArgs;
 use sui_package_utils::package_id_io::PackagesDir;
 use sui_package_utils::package_saver::{save_package, SaveArgs};
+use sui_package_utils::run_report::RunReport;
+use sui_package_utils::store_layout::ensure_current_layout;
 
 #[derive(Parser)]
 struct Args {
@@ -17,12 +20,17 @@ struct Args {
     initial_checkpoint: Option<u64>,
     #[arg(long)]
     max_checkpoint_seen_file: Option<PathBuf>,
+    #[arg(long)]
+    run_report_file: Option<PathBuf>,
+    #[command(flatten)]
+    artifacts: ArtifactArgs,
     #[arg(long, default_value = "false")]
     force: bool,
 }
 fn main() -> Result<(), Box<dyn Error>> {
     let cli_args = Args::parse();
     let packages_dir = PackagesDir::new(cli_args.packages_dir.clone());
+    ensure_current_layout(&packages_dir, &cli_args.artifacts.get_enabled())?;
     let initial_checkpoint = if cli_args.initial_checkpoint.is_some() {
         cli_args.initial_checkpoint.unwrap()
     } else {
@@ -49,14 +57,38 @@ fn main() -> Result<(), Box<dyn Error>> {
         bcs: true,
         bytecode: true,
         call_graph: true,
+        artifacts: cli_args.artifacts.get_enabled(),
         metadata: true,
         move_code: true,
         force: cli_args.force,
         packages_dir: packages_dir.get_prefix(),
         move_decompiler_path: cli_args.move_decompiler_path,
     };
+    let mut run_report = RunReport::new(initial_checkpoint, new_max_checkpoint);
     for pkg in res {
-        save_package(&save_args, &pkg)?;
+        match save_package(&save_args, &pkg) {
+            Ok(package_report) => run_report.add(package_report),
+            Err(failure) => {
+                println!("{}", failure);
+                run_report.add(*failure.report);
+            }
+        }
+    }
+    println!(
+        "{} packages saved ({} new, {} upgrades), {} failed, {} artifacts failed",
+        run_report.packages_saved,
+        run_report.new_packages,
+        run_report.upgrades,
+        run_report.packages_failed,
+        run_report.artifacts_failed
+    );
+    if let Some(run_report_file) = cli_args.run_report_file {
+        fs::write(run_report_file, serde_json::to_string_pretty(&run_report)?)?;
+    }
+    // keep the previous max checkpoint so failed packages are fetched again on the next run.
+    // Failed artifacts do not hold it back, they are left unstamped and listed in the run report
+    if run_report.packages_failed > 0 {
+        return Err(format!("{} packages failed to save", run_report.packages_failed).into());
     }
 
     if let Some(max_checkpoint_seen_file) = cli_args.max_checkpoint_seen_file {
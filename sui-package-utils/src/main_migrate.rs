@@ -0,0 +1,35 @@
+use std::error::Error;
+use std::path::PathBuf;
+
+use clap::Parser;
+use sui_package_utils::package_id_io::PackagesDir;
+use sui_package_utils::store_layout::migrate_store;
+
+#[derive(Parser)]
+struct Args {
+    #[arg(long)]
+    packages_dir: PathBuf,
+    #[arg(long, default_value = "false")]
+    dry_run: bool,
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let cli_args = Args::parse();
+    let packages_dir = PackagesDir::new(cli_args.packages_dir.clone());
+    let steps = migrate_store(&packages_dir, cli_args.dry_run)?;
+    if steps.is_empty() {
+        println!("Store is up to date");
+    }
+    for step in steps {
+        println!(
+            "{}{}",
+            if cli_args.dry_run {
+                "Would apply "
+            } else {
+                "Applied "
+            },
+            step
+        );
+    }
+    Ok(())
+}
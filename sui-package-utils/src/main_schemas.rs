@@ -0,0 +1,98 @@
+use std::error::Error;
+use std::path::PathBuf;
+
+use clap::{Parser, Subcommand};
+use sui_package_utils::events_index::EVENTS_INDEX_FILE;
+use sui_package_utils::global_call_graph::GLOBAL_CALL_GRAPH_FILE;
+use sui_package_utils::package_id_io::PackagesDir;
+use sui_package_utils::reverse_call_index::REVERSE_CALL_INDEX_FILE;
+use sui_package_utils::schemas::{write_schemas, ArtifactKind, ArtifactValidator};
+use sui_package_utils::store_layout::LAYOUT_FILE;
+
+#[derive(Parser)]
+struct Args {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    // writes a JSON Schema document for every store artifact
+    Generate {
+        #[arg(long)]
+        out_dir: PathBuf,
+    },
+    // checks artifacts against their schema, the kind is inferred from the file name
+    Validate {
+        // validates every artifact in the store
+        #[arg(long)]
+        packages_dir: Option<PathBuf>,
+        #[arg(long)]
+        file: Vec<PathBuf>,
+    },
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let cli_args = Args::parse();
+    match cli_args.command {
+        Command::Generate { out_dir } => {
+            for schema_file_name in write_schemas(&out_dir)? {
+                println!("Wrote {}", out_dir.join(schema_file_name).display());
+            }
+        }
+        Command::Validate { packages_dir, file } => {
+            let mut artifact_paths = file;
+            if let Some(packages_dir) = packages_dir {
+                for store_file in [
+                    LAYOUT_FILE,
+                    GLOBAL_CALL_GRAPH_FILE,
+                    REVERSE_CALL_INDEX_FILE,
+                    EVENTS_INDEX_FILE,
+                ] {
+                    let store_file = packages_dir.join(store_file);
+                    if store_file.exists() {
+                        artifact_paths.push(store_file);
+                    }
+                }
+                for package_path in PackagesDir::new(packages_dir).get_package_directories()? {
+                    for entry in package_path.read_dir()? {
+                        let path = entry?.path();
+                        let is_artifact = path
+                            .file_name()
+                            .and_then(|file_name| file_name.to_str())
+                            .and_then(ArtifactKind::from_file_name)
+                            .is_some();
+                        if is_artifact {
+                            artifact_paths.push(path);
+                        }
+                    }
+                }
+            }
+
+            let validator = ArtifactValidator::new()?;
+            let mut invalid_artifacts = 0;
+            for artifact_path in &artifact_paths {
+                let errors = match validator.validate_file(artifact_path) {
+                    Ok(errors) => errors,
+                    Err(e) => vec![e.to_string()],
+                };
+                if !errors.is_empty() {
+                    invalid_artifacts += 1;
+                    println!("{}:", artifact_path.display());
+                    for error in errors {
+                        println!("  {}", error);
+                    }
+                }
+            }
+            println!(
+                "Checked {} artifacts, {} invalid",
+                artifact_paths.len(),
+                invalid_artifacts
+            );
+            if invalid_artifacts > 0 {
+                return Err("artifact validation failed".into());
+            }
+        }
+    }
+    Ok(())
+}
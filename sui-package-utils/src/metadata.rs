@@ -1,10 +1,14 @@
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use sui_types::move_package::MovePackage;
 
 use crate::common_types::MovePackageWithMetadata;
 use crate::csv::PackageBcsWithCreationInfo;
 
-#[derive(Debug, Serialize, Deserialize)]
+// bump when the shape of metadata.json changes; see store_layout.rs
+pub const METADATA_SCHEMA_VERSION: u32 = 1;
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct PackageMetadata {
     pub id: String,
@@ -0,0 +1,163 @@
+use move_binary_format::file_format::CompiledModule;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use sui_types::base_types::ObjectID;
+use sui_types::move_package::MovePackage;
+
+// bump when the shape of module_graph.json changes; see store_layout.rs
+pub const MODULE_GRAPH_SCHEMA_VERSION: u32 = 1;
+
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+pub struct PackageModuleGraph {
+    package_id: String,
+    original_package_id: String,
+    modules: Vec<ModuleDependencies>,
+    // keyed by the original package id used in the bytecode
+    external_packages: BTreeMap<String, ExternalPackage>,
+}
+
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+pub struct ModuleDependencies {
+    module_name: String,
+    // modules of the same package used through module handles
+    intra_package_dependencies: BTreeSet<String>,
+    // fully qualified modules of other packages used through module handles
+    external_dependencies: BTreeSet<String>,
+    friends: BTreeSet<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+pub struct ExternalPackage {
+    // package version the linkage table resolves to, None if it has no entry
+    storage_id: Option<String>,
+    version: Option<u64>,
+    used_modules: BTreeSet<String>,
+    dependent_modules: BTreeSet<String>,
+}
+
+impl From<&MovePackage> for PackageModuleGraph {
+    fn from(pkg: &MovePackage) -> Self {
+        let mut package_module_graph = PackageModuleGraph {
+            package_id: pkg.id().to_canonical_string(true),
+            original_package_id: pkg.original_package_id().to_canonical_string(true),
+            modules: Vec::new(),
+            external_packages: BTreeMap::new(),
+        };
+        for (module_name, module_bytes) in pkg.serialized_module_map() {
+            let module = CompiledModule::deserialize_with_defaults(module_bytes).unwrap();
+            let self_address = *module.address();
+            let mut module_dependencies = ModuleDependencies {
+                module_name: module_name.clone(),
+                intra_package_dependencies: BTreeSet::new(),
+                external_dependencies: BTreeSet::new(),
+                friends: BTreeSet::new(),
+            };
+
+            for (index, module_handle) in module.module_handles().iter().enumerate() {
+                if index == module.self_handle_idx().0 as usize {
+                    continue;
+                }
+                let address = module.address_identifier_at(module_handle.address);
+                let dependency_name = module.identifier_at(module_handle.name).to_string();
+                if *address == self_address {
+                    module_dependencies
+                        .intra_package_dependencies
+                        .insert(dependency_name);
+                    continue;
+                }
+
+                let original_id = address.to_canonical_string(true);
+                module_dependencies
+                    .external_dependencies
+                    .insert(format!("{}::{}", original_id, dependency_name));
+                let upgrade_info = pkg.linkage_table().get(&ObjectID::from(*address));
+                let external_package = package_module_graph
+                    .external_packages
+                    .entry(original_id)
+                    .or_insert_with(|| ExternalPackage {
+                        storage_id: upgrade_info
+                            .map(|info| info.upgraded_id.to_canonical_string(true)),
+                        version: upgrade_info.map(|info| info.upgraded_version.value()),
+                        used_modules: BTreeSet::new(),
+                        dependent_modules: BTreeSet::new(),
+                    });
+                external_package.used_modules.insert(dependency_name);
+                external_package
+                    .dependent_modules
+                    .insert(module_name.clone());
+            }
+
+            for friend_handle in module.friend_decls() {
+                let address = module.address_identifier_at(friend_handle.address);
+                let friend_name = module.identifier_at(friend_handle.name).to_string();
+                module_dependencies
+                    .friends
+                    .insert(if *address == self_address {
+                        friend_name
+                    } else {
+                        format!("{}::{}", address.to_canonical_string(true), friend_name)
+                    });
+            }
+            package_module_graph.modules.push(module_dependencies);
+        }
+        package_module_graph
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{test_package, TestModuleBuilder};
+    use move_core_types::account_address::AccountAddress;
+    use sui_types::SUI_FRAMEWORK_ADDRESS;
+
+    #[test]
+    fn intra_package_and_external_dependencies() {
+        let mut pool = TestModuleBuilder::new();
+        pool.add_function(
+            "swap",
+            0,
+            &[
+                ("0x0::math", "mul", vec![]),
+                ("coin", "zero", vec![]),
+                ("balance", "zero", vec![]),
+            ],
+        );
+        let mut math = TestModuleBuilder::at(AccountAddress::ZERO, "math");
+        math.add_function("mul", 0, &[]);
+        math.add_friend("0x0::test");
+        let package_module_graph =
+            PackageModuleGraph::from(&test_package(&[pool.build(), math.build()], 1));
+
+        let framework_id = SUI_FRAMEWORK_ADDRESS.to_canonical_string(true);
+        let pool = &package_module_graph.modules[1];
+        assert_eq!(pool.module_name, "test");
+        assert_eq!(
+            pool.intra_package_dependencies,
+            BTreeSet::from(["math".to_string()])
+        );
+        assert_eq!(
+            pool.external_dependencies,
+            BTreeSet::from([
+                format!("{}::balance", framework_id),
+                format!("{}::coin", framework_id),
+            ])
+        );
+        let math = &package_module_graph.modules[0];
+        assert!(math.intra_package_dependencies.is_empty());
+        assert_eq!(math.friends, BTreeSet::from(["test".to_string()]));
+
+        // the test package has no linkage table
+        let framework = &package_module_graph.external_packages[&framework_id];
+        assert_eq!(framework.storage_id, None);
+        assert_eq!(
+            framework.used_modules,
+            BTreeSet::from(["balance".to_string(), "coin".to_string()])
+        );
+        assert_eq!(
+            framework.dependent_modules,
+            BTreeSet::from(["test".to_string()])
+        );
+    }
+}
@@ -0,0 +1,36 @@
+use move_binary_format::file_format::CompiledModule;
+use move_binary_format::normalized::{Module as NormalizedModule, RcPool};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use sui_json_rpc_types::SuiMoveNormalizedModule;
+use sui_types::move_package::MovePackage;
+
+// bump when the shape of normalized_modules.json changes; see store_layout.rs
+pub const NORMALIZED_MODULES_SCHEMA_VERSION: u32 = 1;
+
+// the result of sui_getNormalizedMoveModulesByPackage, generated offline from the stored
+// bytecode with the RPC's own types so consumers of the RPC can read the store instead
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+#[serde(transparent)]
+pub struct PackageNormalizedModules(pub BTreeMap<String, SuiMoveNormalizedModule>);
+
+impl From<&MovePackage> for PackageNormalizedModules {
+    fn from(pkg: &MovePackage) -> Self {
+        PackageNormalizedModules(
+            pkg.serialized_module_map()
+                .iter()
+                .map(|(module_name, module_bytes)| {
+                    let module = CompiledModule::deserialize_with_defaults(module_bytes).unwrap();
+                    (module_name.clone(), normalize_module(&module))
+                })
+                .collect(),
+        )
+    }
+}
+
+// the same conversion the RPC applies, without the function bodies
+pub fn normalize_module(module: &CompiledModule) -> SuiMoveNormalizedModule {
+    let mut pool = RcPool::new();
+    SuiMoveNormalizedModule::from(&NormalizedModule::new(&mut pool, module, false))
+}
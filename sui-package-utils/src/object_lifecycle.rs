@@ -0,0 +1,254 @@
+use move_binary_format::file_format::{CodeOffset, CompiledModule};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use sui_types::move_package::MovePackage;
+use sui_types::SUI_FRAMEWORK_ADDRESS;
+
+use crate::bcs_json::{format_signature_token, has_type_parameter};
+use crate::call_graph::for_each_call;
+
+// bump when the shape of object_lifecycle.json changes; see store_layout.rs
+pub const OBJECT_LIFECYCLE_SCHEMA_VERSION: u32 = 1;
+
+// serialized as the name of the framework function called
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize, JsonSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum LifecycleOperation {
+    Transfer,
+    PublicTransfer,
+    PartyTransfer,
+    PublicPartyTransfer,
+    ShareObject,
+    PublicShareObject,
+    FreezeObject,
+    PublicFreezeObject,
+    Delete,
+}
+
+impl LifecycleOperation {
+    fn from_framework_function(module_name: &str, function_name: &str) -> Option<Self> {
+        match (module_name, function_name) {
+            ("transfer", "transfer") => Some(LifecycleOperation::Transfer),
+            ("transfer", "public_transfer") => Some(LifecycleOperation::PublicTransfer),
+            ("transfer", "party_transfer") => Some(LifecycleOperation::PartyTransfer),
+            ("transfer", "public_party_transfer") => Some(LifecycleOperation::PublicPartyTransfer),
+            ("transfer", "share_object") => Some(LifecycleOperation::ShareObject),
+            ("transfer", "public_share_object") => Some(LifecycleOperation::PublicShareObject),
+            ("transfer", "freeze_object") => Some(LifecycleOperation::FreezeObject),
+            ("transfer", "public_freeze_object") => Some(LifecycleOperation::PublicFreezeObject),
+            ("object", "delete") => Some(LifecycleOperation::Delete),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+pub struct PackageObjectLifecycle {
+    package_id: String,
+    module_lifecycles: Vec<ModuleObjectLifecycle>,
+    // object type -> operations applied to it anywhere in the package, types that are type
+    // parameters of the caller are left out
+    object_types: BTreeMap<String, BTreeSet<LifecycleOperation>>,
+}
+
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+pub struct ModuleObjectLifecycle {
+    module_name: String,
+    // only functions with at least one site
+    functions: BTreeMap<String, Vec<LifecycleSite>>,
+}
+
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+pub struct LifecycleSite {
+    code_offset: CodeOffset,
+    operation: LifecycleOperation,
+    // the instantiated object type, formatted like bcs.json signatures. None for object::delete,
+    // which takes a UID
+    object_type: Option<String>,
+    // the object type is, or is built from, a type parameter of the calling function, so the
+    // actual type is chosen by its callers
+    generic: bool,
+}
+
+impl From<&MovePackage> for PackageObjectLifecycle {
+    fn from(pkg: &MovePackage) -> Self {
+        let mut package_lifecycle = PackageObjectLifecycle {
+            package_id: pkg.id().to_canonical_string(true),
+            module_lifecycles: Vec::new(),
+            object_types: BTreeMap::new(),
+        };
+        for (module_name, module_bytes) in pkg.serialized_module_map() {
+            let module = CompiledModule::deserialize_with_defaults(module_bytes).unwrap();
+            let module_lifecycle = move_module_to_module_lifecycle(module_name, &module);
+            for site in module_lifecycle.functions.values().flatten() {
+                if let (Some(object_type), false) = (&site.object_type, site.generic) {
+                    package_lifecycle
+                        .object_types
+                        .entry(object_type.clone())
+                        .or_default()
+                        .insert(site.operation);
+                }
+            }
+            package_lifecycle.module_lifecycles.push(module_lifecycle);
+        }
+        package_lifecycle
+    }
+}
+
+pub fn move_module_to_module_lifecycle(
+    module_name: &str,
+    module: &CompiledModule,
+) -> ModuleObjectLifecycle {
+    let mut module_lifecycle = ModuleObjectLifecycle {
+        module_name: module_name.to_string(),
+        functions: BTreeMap::new(),
+    };
+    for function_def in module.function_defs() {
+        let mut sites = Vec::new();
+        for_each_call(module, function_def, |call| {
+            let callee_module = module.module_handle_at(call.handle.module);
+            if *module.address_identifier_at(callee_module.address) != SUI_FRAMEWORK_ADDRESS {
+                return;
+            }
+            let Some(operation) = LifecycleOperation::from_framework_function(
+                module.identifier_at(callee_module.name).as_str(),
+                module.identifier_at(call.handle.name).as_str(),
+            ) else {
+                return;
+            };
+            // every transfer function takes the object type as its only type argument
+            let object_type = call.type_arguments.first();
+            sites.push(LifecycleSite {
+                code_offset: call.offset,
+                operation,
+                object_type: object_type
+                    .map(|object_type| format_signature_token(module, object_type)),
+                generic: object_type.is_some_and(has_type_parameter),
+            });
+        });
+        if !sites.is_empty() {
+            let function_handle = module.function_handle_at(function_def.function);
+            module_lifecycle.functions.insert(
+                module.identifier_at(function_handle.name).to_string(),
+                sites,
+            );
+        }
+    }
+    module_lifecycle
+}
+
+impl PackageObjectLifecycle {
+    pub fn get_package_id(&self) -> &str {
+        &self.package_id
+    }
+    pub fn get_module_lifecycles(&self) -> &Vec<ModuleObjectLifecycle> {
+        &self.module_lifecycles
+    }
+    pub fn get_object_types(&self) -> &BTreeMap<String, BTreeSet<LifecycleOperation>> {
+        &self.object_types
+    }
+}
+
+impl ModuleObjectLifecycle {
+    pub fn get_module_name(&self) -> &str {
+        &self.module_name
+    }
+    pub fn get_functions(&self) -> &BTreeMap<String, Vec<LifecycleSite>> {
+        &self.functions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use move_binary_format::file_format::{Ability, AbilitySet, SignatureToken};
+    use move_core_types::account_address::AccountAddress;
+
+    use super::*;
+    use crate::test_utils::TestModuleBuilder;
+
+    fn sites(
+        module_lifecycle: &ModuleObjectLifecycle,
+        function: &str,
+    ) -> Vec<(CodeOffset, LifecycleOperation, Option<String>, bool)> {
+        module_lifecycle.functions[function]
+            .iter()
+            .map(|site| {
+                (
+                    site.code_offset,
+                    site.operation,
+                    site.object_type.clone(),
+                    site.generic,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn matches_framework_calls() {
+        let mut builder = TestModuleBuilder::new();
+        let hero = builder.add_struct(
+            "Hero",
+            AbilitySet::EMPTY | Ability::Key | Ability::Store,
+            vec![],
+            &[],
+        );
+        let hero_type = format!(
+            "0x{}::test::Hero",
+            AccountAddress::ZERO.to_canonical_string(false)
+        );
+        builder.add_function(
+            "create",
+            0,
+            &[
+                ("transfer", "public_transfer", vec![hero.clone()]),
+                ("coin", "value", vec![hero.clone()]),
+                ("transfer", "share_object", vec![hero]),
+            ],
+        );
+        builder.add_function(
+            "send",
+            1,
+            &[(
+                "transfer",
+                "public_transfer",
+                vec![SignatureToken::TypeParameter(0)],
+            )],
+        );
+        builder.add_function("burn", 0, &[("object", "delete", vec![])]);
+        builder.add_function("noop", 0, &[("coin", "value", vec![SignatureToken::U64])]);
+        let module_lifecycle = move_module_to_module_lifecycle("test", &builder.build());
+
+        assert_eq!(
+            module_lifecycle.functions.keys().collect::<Vec<_>>(),
+            vec!["burn", "create", "send"]
+        );
+        assert_eq!(
+            sites(&module_lifecycle, "create"),
+            vec![
+                (
+                    0,
+                    LifecycleOperation::PublicTransfer,
+                    Some(hero_type.clone()),
+                    false
+                ),
+                (2, LifecycleOperation::ShareObject, Some(hero_type), false),
+            ]
+        );
+        assert_eq!(
+            sites(&module_lifecycle, "send"),
+            vec![(
+                0,
+                LifecycleOperation::PublicTransfer,
+                Some("T0".to_string()),
+                true
+            )]
+        );
+        assert_eq!(
+            sites(&module_lifecycle, "burn"),
+            vec![(0, LifecycleOperation::Delete, None, false)]
+        );
+    }
+}
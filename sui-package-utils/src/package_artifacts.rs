@@ -0,0 +1,151 @@
+use schemars::schema::RootSchema;
+use schemars::{schema_for, JsonSchema};
+use serde::Serialize;
+use sui_types::move_package::MovePackage;
+
+use crate::cfg::{PackageCfg, CFG_SCHEMA_VERSION};
+use crate::constants::{PackageConstants, CONSTANTS_SCHEMA_VERSION};
+use crate::dynamic_fields::{PackageDynamicFields, DYNAMIC_FIELDS_SCHEMA_VERSION};
+use crate::events::{PackageEvents, EVENTS_SCHEMA_VERSION};
+use crate::module_graph::{PackageModuleGraph, MODULE_GRAPH_SCHEMA_VERSION};
+use crate::normalized_modules::{PackageNormalizedModules, NORMALIZED_MODULES_SCHEMA_VERSION};
+use crate::object_lifecycle::{PackageObjectLifecycle, OBJECT_LIFECYCLE_SCHEMA_VERSION};
+use crate::reachability::{PackageReachability, REACHABILITY_SCHEMA_VERSION};
+
+// an optional per-package artifact derived from the package alone. None of them is written
+// unless enabled, and a store only tracks the schema version of the ones it contains
+#[derive(Debug)]
+pub struct PackageArtifact {
+    pub file_name: &'static str,
+    // bump whenever the code generating the artifact changes its output
+    pub generator_version: u32,
+    // bump when the shape of the artifact changes; see store_layout.rs
+    pub schema_version: u32,
+    pub generate: fn(&MovePackage) -> serde_json::Result<String>,
+    pub schema: fn() -> RootSchema,
+}
+
+// artifacts are identified by their file name
+impl PartialEq for PackageArtifact {
+    fn eq(&self, other: &Self) -> bool {
+        self.file_name == other.file_name
+    }
+}
+
+impl Eq for PackageArtifact {}
+
+pub static PACKAGE_ARTIFACTS: [PackageArtifact; 8] = [
+    PackageArtifact {
+        file_name: "constants.json",
+        generator_version: 2,
+        schema_version: CONSTANTS_SCHEMA_VERSION,
+        generate: to_json::<PackageConstants>,
+        schema: schema::<PackageConstants>,
+    },
+    PackageArtifact {
+        file_name: "module_graph.json",
+        generator_version: 1,
+        schema_version: MODULE_GRAPH_SCHEMA_VERSION,
+        generate: to_json::<PackageModuleGraph>,
+        schema: schema::<PackageModuleGraph>,
+    },
+    PackageArtifact {
+        file_name: "normalized_modules.json",
+        generator_version: 1,
+        schema_version: NORMALIZED_MODULES_SCHEMA_VERSION,
+        generate: to_json::<PackageNormalizedModules>,
+        schema: schema::<PackageNormalizedModules>,
+    },
+    PackageArtifact {
+        file_name: "reachability.json",
+        generator_version: 1,
+        schema_version: REACHABILITY_SCHEMA_VERSION,
+        generate: to_json::<PackageReachability>,
+        schema: schema::<PackageReachability>,
+    },
+    PackageArtifact {
+        file_name: "cfg.json",
+        generator_version: 2,
+        schema_version: CFG_SCHEMA_VERSION,
+        generate: to_json::<PackageCfg>,
+        schema: schema::<PackageCfg>,
+    },
+    PackageArtifact {
+        file_name: "object_lifecycle.json",
+        generator_version: 1,
+        schema_version: OBJECT_LIFECYCLE_SCHEMA_VERSION,
+        generate: to_json::<PackageObjectLifecycle>,
+        schema: schema::<PackageObjectLifecycle>,
+    },
+    PackageArtifact {
+        file_name: "events.json",
+        generator_version: 1,
+        schema_version: EVENTS_SCHEMA_VERSION,
+        generate: to_json::<PackageEvents>,
+        schema: schema::<PackageEvents>,
+    },
+    PackageArtifact {
+        file_name: "dynamic_fields.json",
+        generator_version: 2,
+        schema_version: DYNAMIC_FIELDS_SCHEMA_VERSION,
+        generate: to_json::<PackageDynamicFields>,
+        schema: schema::<PackageDynamicFields>,
+    },
+];
+
+pub fn get_package_artifact(file_name: &str) -> Option<&'static PackageArtifact> {
+    PACKAGE_ARTIFACTS
+        .iter()
+        .find(|artifact| artifact.file_name == file_name)
+}
+
+// opt-in flags for the artifacts above, shared by every command that saves packages
+#[derive(clap::Args, Debug, Default)]
+pub struct ArtifactArgs {
+    #[arg(long, default_value = "false")]
+    pub constants: bool,
+    #[arg(long, default_value = "false")]
+    pub module_graph: bool,
+    #[arg(long, default_value = "false")]
+    pub normalized_modules: bool,
+    #[arg(long, default_value = "false")]
+    pub reachability: bool,
+    #[arg(long, default_value = "false")]
+    pub cfg: bool,
+    #[arg(long, default_value = "false")]
+    pub object_lifecycle: bool,
+    #[arg(long, default_value = "false")]
+    pub events: bool,
+    #[arg(long, default_value = "false")]
+    pub dynamic_fields: bool,
+}
+
+impl ArtifactArgs {
+    pub fn get_enabled(&self) -> Vec<&'static PackageArtifact> {
+        [
+            ("constants.json", self.constants),
+            ("module_graph.json", self.module_graph),
+            ("normalized_modules.json", self.normalized_modules),
+            ("reachability.json", self.reachability),
+            ("cfg.json", self.cfg),
+            ("object_lifecycle.json", self.object_lifecycle),
+            ("events.json", self.events),
+            ("dynamic_fields.json", self.dynamic_fields),
+        ]
+        .into_iter()
+        .filter(|(_, enabled)| *enabled)
+        .filter_map(|(file_name, _)| get_package_artifact(file_name))
+        .collect()
+    }
+}
+
+fn to_json<T>(pkg: &MovePackage) -> serde_json::Result<String>
+where
+    T: Serialize + for<'a> From<&'a MovePackage>,
+{
+    serde_json::to_string_pretty(&T::from(pkg))
+}
+
+fn schema<T: JsonSchema>() -> RootSchema {
+    schema_for!(T)
+}
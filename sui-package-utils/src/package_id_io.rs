@@ -1,4 +1,7 @@
 use crate::bcs_json::BcsJsonSchema;
+use crate::call_graph::PackageCallGraph;
+use crate::common_types::MovePackageWithMetadata;
+use crate::events::PackageEvents;
 use crate::metadata::PackageMetadata;
 
 use base64::prelude::*;
@@ -7,19 +10,24 @@ use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
 use std::fs;
 use std::io::Error as IOError;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
 
 use move_binary_format::file_format::CompiledModule;
+use sui_types::move_package::MovePackage;
 pub struct PackagesDir {
     prefix: PathBuf,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Error, Debug, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
+#[error("{message}")]
 pub struct PackageIoError {
     message: String,
 }
 
+// get_package_dir and LAST_62_REGEX define the store layout, changing them requires a new
+// CURRENT_LAYOUT_VERSION and a migration in store_layout.rs
 const LAST_62_REGEX: &str = r"/[0-9a-f]{62}$";
 
 impl PackagesDir {
@@ -35,6 +43,18 @@ impl PackagesDir {
         format!("{}/{}/{}", self.prefix.to_str().unwrap(), first_4, last_62)
     }
 
+    // inverse of get_package_dir: rebuilds the package id from a package directory path
+    pub fn get_package_id_from_dir(self: &PackagesDir, package_dir: &Path) -> Option<String> {
+        let last_62 = package_dir.file_name()?.to_str()?;
+        let first_4 = package_dir.parent()?.file_name()?.to_str()?;
+        let id = format!("{}{}", first_4, last_62);
+        if id.len() == 66 && id.starts_with("0x") {
+            Some(id)
+        } else {
+            None
+        }
+    }
+
     pub fn get_package_directories(self: &PackagesDir) -> Result<Vec<PathBuf>, IOError> {
         let mut package_directories = Vec::new();
         let regex = Regex::new(LAST_62_REGEX).unwrap();
@@ -67,20 +87,63 @@ impl PackagesDir {
         Ok(package_directories)
     }
 
-    pub fn load_package_modules(
-        self: &PackagesDir,
-        id: &str,
-    ) -> Result<BTreeMap<String, CompiledModule>, PackageIoError> {
+    pub fn load_bcs_json(self: &PackagesDir, id: &str) -> Result<BcsJsonSchema, PackageIoError> {
         let package_dir = self.get_package_dir(id);
         let bcs_json = fs::read_to_string(format!("{}/bcs.json", package_dir)).map_err(|e| {
             PackageIoError {
                 message: e.to_string(),
             }
         })?;
-        let bcs_json: BcsJsonSchema =
-            serde_json::from_str(&bcs_json).map_err(|e| PackageIoError {
+        serde_json::from_str(&bcs_json).map_err(|e| PackageIoError {
+            message: e.to_string(),
+        })
+    }
+
+    pub fn load_metadata(self: &PackagesDir, id: &str) -> Result<PackageMetadata, PackageIoError> {
+        let package_dir = self.get_package_dir(id);
+        let metadata_json =
+            fs::read_to_string(format!("{}/metadata.json", package_dir)).map_err(|e| {
+                PackageIoError {
+                    message: e.to_string(),
+                }
+            })?;
+        serde_json::from_str(&metadata_json).map_err(|e| PackageIoError {
+            message: e.to_string(),
+        })
+    }
+
+    pub fn load_call_graph(
+        self: &PackagesDir,
+        id: &str,
+    ) -> Result<PackageCallGraph, PackageIoError> {
+        let package_dir = self.get_package_dir(id);
+        let call_graph_json = fs::read_to_string(format!("{}/call_graph.json", package_dir))
+            .map_err(|e| PackageIoError {
                 message: e.to_string(),
             })?;
+        serde_json::from_str(&call_graph_json).map_err(|e| PackageIoError {
+            message: e.to_string(),
+        })
+    }
+
+    pub fn load_events(self: &PackagesDir, id: &str) -> Result<PackageEvents, PackageIoError> {
+        let package_dir = self.get_package_dir(id);
+        let events_json =
+            fs::read_to_string(format!("{}/events.json", package_dir)).map_err(|e| {
+                PackageIoError {
+                    message: e.to_string(),
+                }
+            })?;
+        serde_json::from_str(&events_json).map_err(|e| PackageIoError {
+            message: e.to_string(),
+        })
+    }
+
+    pub fn load_package_modules(
+        self: &PackagesDir,
+        id: &str,
+    ) -> Result<BTreeMap<String, CompiledModule>, PackageIoError> {
+        let bcs_json = self.load_bcs_json(id)?;
         let mut modules = BTreeMap::new();
         for (module_name, module_b64) in bcs_json.get_module_map() {
             let module = CompiledModule::deserialize_with_defaults(
@@ -99,6 +162,105 @@ impl PackagesDir {
         Ok(modules)
     }
 
+    // rebuilds the full package from bcs.json and metadata.json
+    pub fn load_package(
+        self: &PackagesDir,
+        id: &str,
+    ) -> Result<MovePackageWithMetadata, PackageIoError> {
+        let bcs_json = self.load_bcs_json(id)?;
+        let metadata = self.load_metadata(id)?;
+        let package = bcs_json.to_move_package().map_err(|e| PackageIoError {
+            message: e.to_string(),
+        })?;
+        let package_id = package.id().to_canonical_string(true);
+        if metadata.id != package_id {
+            return Err(PackageIoError {
+                message: format!(
+                    "metadata.json id {} does not match bcs.json id {}",
+                    metadata.id, package_id
+                ),
+            });
+        }
+        Ok(MovePackageWithMetadata {
+            package,
+            checkpoint: metadata.checkpoint,
+            transaction_digest: metadata.transaction_digest,
+            sender: metadata.sender,
+        })
+    }
+
+    // checks that the package rebuilt from bcs.json serializes back to the same moduleMap,
+    // typeOriginTable and linkageTable, survives a BCS round trip and that every module is stored
+    // in its canonical serialization. No original package digest is stored, so this cannot tell
+    // whether bcs.json still matches the package on chain. The derived maps are left to
+    // BcsJsonSchema::validate, older schema versions lack some of them
+    pub fn verify_package_round_trip(self: &PackagesDir, id: &str) -> Result<(), PackageIoError> {
+        let bcs_json = self.load_bcs_json(id)?;
+        let package = bcs_json.to_move_package().map_err(|e| PackageIoError {
+            message: e.to_string(),
+        })?;
+        let rebuilt_bcs_json = BcsJsonSchema::from(&package);
+        for (field, matches) in [
+            (
+                "moduleMap",
+                rebuilt_bcs_json.get_module_map() == bcs_json.get_module_map(),
+            ),
+            (
+                "typeOriginTable",
+                rebuilt_bcs_json.get_type_origin_table() == bcs_json.get_type_origin_table(),
+            ),
+            (
+                "linkageTable",
+                rebuilt_bcs_json.get_linkage_table() == bcs_json.get_linkage_table(),
+            ),
+        ] {
+            if !matches {
+                return Err(PackageIoError {
+                    message: format!(
+                        "{} in bcs.json of {} does not re-serialize to the same content",
+                        field, id
+                    ),
+                });
+            }
+        }
+
+        let package_bcs = bcs::to_bytes(&package).map_err(|e| PackageIoError {
+            message: e.to_string(),
+        })?;
+        let deserialized_package: MovePackage =
+            bcs::from_bytes(&package_bcs).map_err(|e| PackageIoError {
+                message: e.to_string(),
+            })?;
+        if deserialized_package != package {
+            return Err(PackageIoError {
+                message: format!("package {} does not survive a BCS round trip", id),
+            });
+        }
+
+        for (module_name, module_bytes) in package.serialized_module_map() {
+            let module = CompiledModule::deserialize_with_defaults(module_bytes).map_err(|e| {
+                PackageIoError {
+                    message: format!("module {}: {}", module_name, e),
+                }
+            })?;
+            let mut canonical_bytes = Vec::new();
+            module
+                .serialize_with_version(module.version, &mut canonical_bytes)
+                .map_err(|e| PackageIoError {
+                    message: format!("module {}: {}", module_name, e),
+                })?;
+            if canonical_bytes != *module_bytes {
+                return Err(PackageIoError {
+                    message: format!(
+                        "module {} of package {} is not in canonical serialization",
+                        module_name, id
+                    ),
+                });
+            }
+        }
+        Ok(())
+    }
+
     pub fn get_latest_checkpoint(self: &PackagesDir) -> Result<u64, IOError> {
         let mut latest_checkpoint = 0;
         let regex = Regex::new(LAST_62_REGEX).unwrap();
@@ -136,3 +298,42 @@ impl PackagesDir {
         Ok(latest_checkpoint)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{test_dir, test_package_with_metadata, write_test_package};
+
+    #[test]
+    fn load_package_round_trip() {
+        let prefix = test_dir("package-id-io-round-trip");
+        let packages_dir = PackagesDir::new(prefix.clone());
+        let pkg_with_metadata = test_package_with_metadata();
+        write_test_package(&packages_dir, &pkg_with_metadata);
+
+        let id = pkg_with_metadata.package.id().to_canonical_string(true);
+        assert_eq!(packages_dir.load_package(&id).unwrap(), pkg_with_metadata);
+        packages_dir.verify_package_round_trip(&id).unwrap();
+        fs::remove_dir_all(prefix).unwrap();
+    }
+
+    #[test]
+    fn load_package_checks_metadata_id() {
+        let prefix = test_dir("package-id-io-metadata-id");
+        let packages_dir = PackagesDir::new(prefix.clone());
+        let pkg_with_metadata = test_package_with_metadata();
+        write_test_package(&packages_dir, &pkg_with_metadata);
+
+        let id = pkg_with_metadata.package.id().to_canonical_string(true);
+        let mut metadata = PackageMetadata::from(&pkg_with_metadata);
+        metadata.id =
+            "0x00000000000000000000000000000000000000000000000000000000000000cd".to_string();
+        fs::write(
+            format!("{}/metadata.json", packages_dir.get_package_dir(&id)),
+            serde_json::to_string_pretty(&metadata).unwrap(),
+        )
+        .unwrap();
+        assert!(packages_dir.load_package(&id).is_err());
+        fs::remove_dir_all(prefix).unwrap();
+    }
+}
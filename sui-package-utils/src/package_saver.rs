@@ -1,24 +1,52 @@
+use std::env;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
 
 use thiserror::Error;
 
+use crate::artifact_manifest::{hash_bytes, ArtifactManifest, ArtifactStamp};
 use crate::bcs_json::BcsJsonSchema;
 use crate::call_graph::PackageCallGraph;
 use crate::common_types::MovePackageWithMetadata;
 use crate::metadata::PackageMetadata;
+use crate::package_artifacts::PackageArtifact;
 use crate::package_id_io::PackagesDir;
+use crate::run_report::{ArtifactOutcome, PackageSaveReport};
+
+// bump these whenever the code generating the corresponding artifact changes its output, the
+// versions of the optional artifacts are in package_artifacts.rs
+const BCS_JSON_GENERATOR_VERSION: u32 = 3;
+const BYTECODE_GENERATOR_VERSION: u32 = 1;
+const DECOMPILED_GENERATOR_VERSION: u32 = 1;
+const CALL_GRAPH_GENERATOR_VERSION: u32 = 3;
+const METADATA_GENERATOR_VERSION: u32 = 1;
 
 #[derive(Error, Debug)]
 pub enum PackageSaverError {
     #[error("Error saving package: {0}, {1}")]
     SaveError(String, String),
+    // an external generator failed on one artifact, the rest of the package can still be saved
+    #[error("Error generating {0}: {1}")]
+    GeneratorFailed(String, String),
+}
+
+// the artifacts saved before the error are kept in the report, so a failed package still
+// records what was written
+#[derive(Error, Debug)]
+#[error("{error}")]
+pub struct PackageSaveFailure {
+    // boxed to keep the Err variant small
+    pub report: Box<PackageSaveReport>,
+    pub error: PackageSaverError,
 }
 
 pub struct SaveArgs {
     pub bcs: bool,
     pub bytecode: bool,
     pub call_graph: bool,
+    // the optional artifacts to write, see package_artifacts.rs
+    pub artifacts: Vec<&'static PackageArtifact>,
     pub metadata: bool,
     pub move_code: bool,
     pub force: bool,
@@ -29,36 +57,56 @@ pub struct SaveArgs {
 pub fn save_package(
     args: &SaveArgs,
     pkg_with_metadata: &MovePackageWithMetadata,
-) -> Result<(), PackageSaverError> {
-    save_bcs(args, pkg_with_metadata)?;
-    save_code_files(args, pkg_with_metadata)?;
-    save_call_graph(args, pkg_with_metadata)?;
-    save_metadata(args, pkg_with_metadata)?;
-    Ok(())
+) -> Result<PackageSaveReport, PackageSaveFailure> {
+    let mut report = PackageSaveReport::from(pkg_with_metadata);
+    match save_package_artifacts(args, pkg_with_metadata, &mut report) {
+        Ok(()) => Ok(report),
+        Err(error) => {
+            report.error = Some(error.to_string());
+            Err(PackageSaveFailure {
+                report: Box::new(report),
+                error,
+            })
+        }
+    }
 }
 
-fn save_bcs(
-    save_args: &SaveArgs,
+fn save_package_artifacts(
+    args: &SaveArgs,
     pkg_with_metadata: &MovePackageWithMetadata,
+    report: &mut PackageSaveReport,
 ) -> Result<(), PackageSaverError> {
-    if !save_args.bcs {
-        return Ok(());
+    let packages_dir = PackagesDir::new(args.packages_dir.clone());
+    let package_dir =
+        packages_dir.get_package_dir(&pkg_with_metadata.package.id().to_canonical_string(true));
+    let mut manifest = ArtifactManifest::load(&package_dir);
+    let package_bcs = bcs::to_bytes(&pkg_with_metadata.package).map_err(|e| {
+        PackageSaverError::SaveError(
+            format!("Error serializing package bcs: {}", e),
+            pkg_with_metadata.package.id().to_canonical_string(true),
+        )
+    })?;
+
+    let mut manifest_changed = false;
+    manifest_changed |= save_bcs(args, pkg_with_metadata, &mut manifest, report, &package_bcs)?;
+    manifest_changed |= save_code_files(args, pkg_with_metadata, &mut manifest, report)?;
+    manifest_changed |=
+        save_call_graph(args, pkg_with_metadata, &mut manifest, report, &package_bcs)?;
+    for artifact in &args.artifacts {
+        manifest_changed |= save_package_artifact(
+            args,
+            pkg_with_metadata,
+            &mut manifest,
+            report,
+            &package_bcs,
+            artifact,
+        )?;
     }
-    let package_dir = create_package_dir(save_args, pkg_with_metadata)?;
-    // create bcs.json
-    let bcs_json_file = format!("{}/bcs.json", package_dir);
-    if save_args.force || !std::path::Path::new(&bcs_json_file).exists() {
-        let bcs_json_schema = BcsJsonSchema::from(&pkg_with_metadata.package);
-        let bcs_json = serde_json::to_string_pretty(&bcs_json_schema).map_err(|e| {
-            PackageSaverError::SaveError(
-                format!("Error serializing bcs.json: {}", e),
-                pkg_with_metadata.package.id().to_canonical_string(true),
-            )
-        })?;
-        println!("Saving {}", bcs_json_file);
-        fs::write(bcs_json_file, bcs_json).map_err(|e| {
+    manifest_changed |= save_metadata(args, pkg_with_metadata, &mut manifest, report)?;
+    if manifest_changed {
+        manifest.save(&package_dir).map_err(|e| {
             PackageSaverError::SaveError(
-                format!("Error writing bcs.json: {}", e),
+                format!("Error writing manifest: {}", e),
                 pkg_with_metadata.package.id().to_canonical_string(true),
             )
         })?;
@@ -66,13 +114,49 @@ fn save_bcs(
     Ok(())
 }
 
+fn save_bcs(
+    save_args: &SaveArgs,
+    pkg_with_metadata: &MovePackageWithMetadata,
+    manifest: &mut ArtifactManifest,
+    report: &mut PackageSaveReport,
+    package_bcs: &[u8],
+) -> Result<bool, PackageSaverError> {
+    if !save_args.bcs {
+        return Ok(false);
+    }
+    let package_dir = create_package_dir(save_args, pkg_with_metadata)?;
+    // create bcs.json
+    save_artifact(
+        save_args,
+        pkg_with_metadata,
+        manifest,
+        report,
+        &package_dir,
+        "bcs.json",
+        ArtifactStamp::new(BCS_JSON_GENERATOR_VERSION, package_bcs),
+        || {
+            let bcs_json_schema = BcsJsonSchema::from(&pkg_with_metadata.package);
+            serde_json::to_string_pretty(&bcs_json_schema)
+                .map(String::into_bytes)
+                .map_err(|e| {
+                    PackageSaverError::SaveError(
+                        format!("Error serializing bcs.json: {}", e),
+                        pkg_with_metadata.package.id().to_canonical_string(true),
+                    )
+                })
+        },
+    )
+}
+
 // saves bytecode and decompiled move code files
 fn save_code_files(
     save_args: &SaveArgs,
     pkg_with_metadata: &MovePackageWithMetadata,
-) -> Result<(), PackageSaverError> {
+    manifest: &mut ArtifactManifest,
+    report: &mut PackageSaveReport,
+) -> Result<bool, PackageSaverError> {
     if !save_args.bytecode {
-        return Ok(());
+        return Ok(false);
     }
     let package_dir = create_package_dir(save_args, pkg_with_metadata)?;
 
@@ -95,105 +179,250 @@ fn save_code_files(
         })?;
     }
     if !save_args.bytecode && !save_args.move_code {
-        return Ok(());
+        return Ok(false);
     }
+    let decompiler_hash = if save_args.move_code {
+        get_decompiler_hash(save_args, pkg_with_metadata)?
+    } else {
+        String::new()
+    };
+    let mut manifest_changed = false;
     let pkg = &pkg_with_metadata.package;
     for (module_name, module_bytes) in pkg.serialized_module_map() {
         let bytecode_path = format!("{}/bytecode_modules/{}.mv", package_dir, module_name);
-        if save_args.bytecode && (save_args.force || !std::path::Path::new(&bytecode_path).exists())
-        {
-            println!("Saving {}", bytecode_path);
-            fs::write(&bytecode_path, module_bytes).map_err(|e| {
-                PackageSaverError::SaveError(
-                    format!("Error writing bytecode file: {}", e),
-                    pkg_with_metadata.package.id().to_canonical_string(true),
-                )
-            })?;
+        if save_args.bytecode {
+            manifest_changed |= save_artifact(
+                save_args,
+                pkg_with_metadata,
+                manifest,
+                report,
+                &package_dir,
+                &format!("bytecode_modules/{}.mv", module_name),
+                ArtifactStamp::new(BYTECODE_GENERATOR_VERSION, module_bytes),
+                || Ok(module_bytes.clone()),
+            )?;
         }
 
         if save_args.move_code {
-            let decompiled_path =
-                format!("{}/decompiled_modules/{}.move", package_dir, module_name);
-            if save_args.force || !std::path::Path::new(&decompiled_path).exists() {
-                let output = std::process::Command::new(&save_args.move_decompiler_path)
-                    .arg("--bytecode")
-                    .arg(bytecode_path)
-                    .output()
-                    .map_err(|e| {
-                        PackageSaverError::SaveError(
-                            format!("Error running move-decompiler: {}", e),
-                            pkg_with_metadata.package.id().to_canonical_string(true),
-                        )
-                    })?;
-                println!("Saving {}", decompiled_path);
-                fs::write(decompiled_path, output.stdout).map_err(|e| {
-                    PackageSaverError::SaveError(
-                        format!("Error writing decompiled file: {}", e),
-                        pkg_with_metadata.package.id().to_canonical_string(true),
-                    )
-                })?;
+            // the decompiler binary is part of the input, so upgrading it regenerates the output
+            let mut decompiler_input = module_bytes.clone();
+            decompiler_input.extend_from_slice(decompiler_hash.as_bytes());
+            let decompiled_file = format!("decompiled_modules/{}.move", module_name);
+            let result = save_artifact(
+                save_args,
+                pkg_with_metadata,
+                manifest,
+                report,
+                &package_dir,
+                &decompiled_file,
+                ArtifactStamp::new(DECOMPILED_GENERATOR_VERSION, &decompiler_input),
+                || {
+                    let output = std::process::Command::new(&save_args.move_decompiler_path)
+                        .arg("--bytecode")
+                        .arg(&bytecode_path)
+                        .output()
+                        .map_err(|e| {
+                            PackageSaverError::SaveError(
+                                format!("Error running move-decompiler: {}", e),
+                                pkg_with_metadata.package.id().to_canonical_string(true),
+                            )
+                        })?;
+                    if !output.status.success() {
+                        return Err(PackageSaverError::GeneratorFailed(
+                            decompiled_file.clone(),
+                            format!(
+                                "move-decompiler exited with {}: {}",
+                                output.status,
+                                String::from_utf8_lossy(&output.stderr).trim()
+                            ),
+                        ));
+                    }
+                    Ok(output.stdout)
+                },
+            );
+            match result {
+                Ok(changed) => manifest_changed |= changed,
+                // the module is left unstamped so the next save retries it, the rest of the
+                // package is still saved
+                Err(PackageSaverError::GeneratorFailed(artifact, error)) => {
+                    println!("Error generating {}: {}", artifact, error);
+                    report.artifact_failed(&artifact, error);
+                }
+                Err(e) => return Err(e),
             }
         }
     }
-    Ok(())
+    Ok(manifest_changed)
 }
 fn save_call_graph(
     save_args: &SaveArgs,
     pkg_with_metadata: &MovePackageWithMetadata,
-) -> Result<(), PackageSaverError> {
+    manifest: &mut ArtifactManifest,
+    report: &mut PackageSaveReport,
+    package_bcs: &[u8],
+) -> Result<bool, PackageSaverError> {
     if !save_args.call_graph {
-        return Ok(());
+        return Ok(false);
     }
     let package_dir = create_package_dir(save_args, pkg_with_metadata)?;
 
     // create call_graph.json
-    let call_graph_json_file = format!("{}/call_graph.json", package_dir);
-    if save_args.force || !std::path::Path::new(&call_graph_json_file).exists() {
-        let call_graph_json = PackageCallGraph::from(&pkg_with_metadata.package);
-        println!("Saving {}", call_graph_json_file);
-        fs::write(
-            call_graph_json_file,
-            serde_json::to_string_pretty(&call_graph_json)
-                .expect("could not serialize call_graph.json"),
-        )
-        .map_err(|e| {
-            PackageSaverError::SaveError(
-                format!("Error writing call_graph.json: {}", e),
-                pkg_with_metadata.package.id().to_canonical_string(true),
-            )
-        })?;
-    }
-    Ok(())
+    save_artifact(
+        save_args,
+        pkg_with_metadata,
+        manifest,
+        report,
+        &package_dir,
+        "call_graph.json",
+        ArtifactStamp::new(CALL_GRAPH_GENERATOR_VERSION, package_bcs),
+        || {
+            let call_graph_json = PackageCallGraph::from(&pkg_with_metadata.package);
+            Ok(serde_json::to_string_pretty(&call_graph_json)
+                .expect("could not serialize call_graph.json")
+                .into_bytes())
+        },
+    )
+}
+
+fn save_package_artifact(
+    save_args: &SaveArgs,
+    pkg_with_metadata: &MovePackageWithMetadata,
+    manifest: &mut ArtifactManifest,
+    report: &mut PackageSaveReport,
+    package_bcs: &[u8],
+    artifact: &PackageArtifact,
+) -> Result<bool, PackageSaverError> {
+    let package_dir = create_package_dir(save_args, pkg_with_metadata)?;
+    save_artifact(
+        save_args,
+        pkg_with_metadata,
+        manifest,
+        report,
+        &package_dir,
+        artifact.file_name,
+        ArtifactStamp::new(artifact.generator_version, package_bcs),
+        || {
+            (artifact.generate)(&pkg_with_metadata.package)
+                .map(String::into_bytes)
+                .map_err(|e| {
+                    PackageSaverError::SaveError(
+                        format!("Error serializing {}: {}", artifact.file_name, e),
+                        pkg_with_metadata.package.id().to_canonical_string(true),
+                    )
+                })
+        },
+    )
 }
 
 fn save_metadata(
     save_args: &SaveArgs,
     pkg_with_metadata: &MovePackageWithMetadata,
-) -> Result<(), PackageSaverError> {
+    manifest: &mut ArtifactManifest,
+    report: &mut PackageSaveReport,
+) -> Result<bool, PackageSaverError> {
     if !save_args.metadata {
-        return Ok(());
+        return Ok(false);
     }
     let package_dir = create_package_dir(save_args, pkg_with_metadata)?;
 
     // create metadata.json
-    let metadata_json_file = format!("{}/metadata.json", package_dir);
-    if save_args.force || !std::path::Path::new(&metadata_json_file).exists() {
-        let metadata = PackageMetadata::from(pkg_with_metadata);
-        let metadata_json = serde_json::to_string_pretty(&metadata).map_err(|e| {
-            PackageSaverError::SaveError(
-                format!("Error serializing metadata.json: {}", e),
-                pkg_with_metadata.package.id().to_canonical_string(true),
-            )
-        })?;
-        println!("Saving {}", metadata_json_file);
-        fs::write(metadata_json_file, metadata_json).map_err(|e| {
+    let metadata = PackageMetadata::from(pkg_with_metadata);
+    let metadata_json = serde_json::to_string_pretty(&metadata).map_err(|e| {
+        PackageSaverError::SaveError(
+            format!("Error serializing metadata.json: {}", e),
+            pkg_with_metadata.package.id().to_canonical_string(true),
+        )
+    })?;
+    save_artifact(
+        save_args,
+        pkg_with_metadata,
+        manifest,
+        report,
+        &package_dir,
+        "metadata.json",
+        ArtifactStamp::new(METADATA_GENERATOR_VERSION, metadata_json.as_bytes()),
+        || Ok(metadata_json.clone().into_bytes()),
+    )
+}
+
+// regenerates an artifact only when it is missing, its stamp in the manifest is stale or
+// --force is set, and only touches the file on disk when the generated content differs.
+// records the outcome in the report and returns true if the manifest was updated
+fn save_artifact(
+    save_args: &SaveArgs,
+    pkg_with_metadata: &MovePackageWithMetadata,
+    manifest: &mut ArtifactManifest,
+    report: &mut PackageSaveReport,
+    package_dir: &str,
+    artifact: &str,
+    stamp: ArtifactStamp,
+    generate: impl FnOnce() -> Result<Vec<u8>, PackageSaverError>,
+) -> Result<bool, PackageSaverError> {
+    let artifact_file = format!("{}/{}", package_dir, artifact);
+    let exists = std::path::Path::new(&artifact_file).exists();
+    if !save_args.force && exists && manifest.is_up_to_date(artifact, &stamp) {
+        report
+            .artifacts
+            .insert(artifact.to_string(), ArtifactOutcome::Unchanged);
+        return Ok(false);
+    }
+    let contents = generate()?;
+    let outcome = if !exists {
+        ArtifactOutcome::Created
+    } else if fs::read(&artifact_file).ok().as_deref() != Some(contents.as_slice()) {
+        ArtifactOutcome::Rewritten
+    } else {
+        ArtifactOutcome::Unchanged
+    };
+    if outcome != ArtifactOutcome::Unchanged {
+        println!("Saving {}", artifact_file);
+        fs::write(&artifact_file, contents).map_err(|e| {
             PackageSaverError::SaveError(
-                format!("Error writing metadata.json: {}", e),
+                format!("Error writing {}: {}", artifact, e),
                 pkg_with_metadata.package.id().to_canonical_string(true),
             )
         })?;
     }
-    Ok(())
+    report.artifacts.insert(artifact.to_string(), outcome);
+    Ok(manifest.stamp(artifact, stamp))
+}
+
+fn get_decompiler_hash(
+    save_args: &SaveArgs,
+    pkg_with_metadata: &MovePackageWithMetadata,
+) -> Result<String, PackageSaverError> {
+    static DECOMPILER_HASH: OnceLock<String> = OnceLock::new();
+    if let Some(decompiler_hash) = DECOMPILER_HASH.get() {
+        return Ok(decompiler_hash.clone());
+    }
+    let decompiler_path = resolve_executable(&save_args.move_decompiler_path);
+    let decompiler_bytes = fs::read(&decompiler_path).map_err(|e| {
+        PackageSaverError::SaveError(
+            format!(
+                "Error reading move-decompiler at {}: {}",
+                decompiler_path.display(),
+                e
+            ),
+            pkg_with_metadata.package.id().to_canonical_string(true),
+        )
+    })?;
+    Ok(DECOMPILER_HASH
+        .get_or_init(|| hash_bytes(&decompiler_bytes))
+        .clone())
+}
+
+// a bare command name is looked up on PATH the way Command::new does, anything else is used as is
+fn resolve_executable(path: &Path) -> PathBuf {
+    if path.components().count() != 1 || path.exists() {
+        return path.to_path_buf();
+    }
+    env::var_os("PATH")
+        .and_then(|paths| {
+            env::split_paths(&paths)
+                .map(|dir| dir.join(path))
+                .find(|candidate| candidate.is_file())
+        })
+        .unwrap_or_else(|| path.to_path_buf())
 }
 
 fn create_package_dir(
@@ -213,3 +442,79 @@ fn create_package_dir(
     }
     Ok(package_dir)
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::*;
+    use crate::test_utils::{test_dir, test_package_with_metadata};
+
+    #[test]
+    fn failed_decompile_is_an_artifact_outcome() {
+        let prefix = test_dir("package-saver-failed-decompile");
+        let save_args = SaveArgs {
+            bcs: true,
+            bytecode: true,
+            call_graph: false,
+            artifacts: Vec::new(),
+            metadata: true,
+            move_code: true,
+            force: false,
+            packages_dir: prefix.clone(),
+            // exits with 1 whatever it is given
+            move_decompiler_path: PathBuf::from("false"),
+        };
+        let pkg_with_metadata = test_package_with_metadata();
+        let report = save_package(&save_args, &pkg_with_metadata).unwrap();
+
+        assert_eq!(
+            report.artifacts["decompiled_modules/test.move"],
+            ArtifactOutcome::Failed
+        );
+        assert!(report
+            .artifact_errors
+            .contains_key("decompiled_modules/test.move"));
+        assert_eq!(report.artifacts["bcs.json"], ArtifactOutcome::Created);
+        assert_eq!(report.artifacts["metadata.json"], ArtifactOutcome::Created);
+        assert!(report.error.is_none());
+        // nothing was stamped for the failed module, so the next save retries it
+        let package_dir = PackagesDir::new(prefix.clone())
+            .get_package_dir(&pkg_with_metadata.package.id().to_canonical_string(true));
+        let manifest = ArtifactManifest::load(&package_dir);
+        assert!(manifest.get_artifacts().contains_key("bcs.json"));
+        assert!(!manifest
+            .get_artifacts()
+            .contains_key("decompiled_modules/test.move"));
+        fs::remove_dir_all(prefix).unwrap();
+    }
+
+    #[test]
+    fn failed_save_keeps_partial_outcomes() {
+        let prefix = test_dir("package-saver-partial-outcomes");
+        let save_args = SaveArgs {
+            bcs: true,
+            bytecode: false,
+            call_graph: false,
+            artifacts: Vec::new(),
+            metadata: true,
+            move_code: false,
+            force: false,
+            packages_dir: prefix.clone(),
+            move_decompiler_path: PathBuf::new(),
+        };
+        let pkg_with_metadata = test_package_with_metadata();
+        // a directory in place of metadata.json cannot be written, bcs.json is saved before it
+        let package_dir = PackagesDir::new(prefix.clone())
+            .get_package_dir(&pkg_with_metadata.package.id().to_canonical_string(true));
+        fs::create_dir_all(format!("{}/metadata.json", package_dir)).unwrap();
+
+        let failure = save_package(&save_args, &pkg_with_metadata).unwrap_err();
+        assert_eq!(
+            failure.report.artifacts,
+            BTreeMap::from([("bcs.json".to_string(), ArtifactOutcome::Created)])
+        );
+        assert_eq!(failure.report.error, Some(failure.error.to_string()));
+        fs::remove_dir_all(prefix).unwrap();
+    }
+}
@@ -0,0 +1,374 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use sui_types::move_package::MovePackage;
+
+use crate::bcs_json::BcsJsonSchema;
+use crate::call_graph::PackageCallGraph;
+
+// bump when the shape of reachability.json changes; see store_layout.rs
+pub const REACHABILITY_SCHEMA_VERSION: u32 = 1;
+
+// called by the runtime when the module is published, so it is a root whatever its visibility
+const INIT_FUNCTION_NAME: &str = "init";
+
+// functions are named <module>::<function>, only calls within the package are followed
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+pub struct PackageReachability {
+    package_id: String,
+    // public, entry and init functions, the surface callable from outside the package
+    roots: BTreeSet<String>,
+    functions: BTreeMap<String, FunctionReachability>,
+    // private and friend functions no root can reach
+    dead_functions: BTreeSet<String>,
+    // root -> functions reachable through that root only
+    exclusive_functions: BTreeMap<String, BTreeSet<String>>,
+    // strongly connected components with a cycle, i.e. directly or mutually recursive functions
+    recursive_components: Vec<BTreeSet<String>>,
+}
+
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+pub struct FunctionReachability {
+    visibility: String,
+    is_entry: bool,
+    // roots this function is reachable from, roots include themselves
+    reachable_from: BTreeSet<String>,
+}
+
+impl From<&MovePackage> for PackageReachability {
+    fn from(pkg: &MovePackage) -> Self {
+        PackageReachability::new(&PackageCallGraph::from(pkg), &BcsJsonSchema::from(pkg))
+    }
+}
+
+impl PackageReachability {
+    pub fn new(call_graph: &PackageCallGraph, bcs_json: &BcsJsonSchema) -> Self {
+        let package_id = call_graph.get_package_id();
+        let mut functions = BTreeMap::new();
+        for (module_name, function_map) in bcs_json.get_function_map() {
+            for (function_name, function_entry) in function_map {
+                functions.insert(
+                    format!("{}::{}", module_name, function_name),
+                    FunctionReachability {
+                        visibility: function_entry.get_visibility().to_string(),
+                        is_entry: function_entry.get_is_entry(),
+                        reachable_from: BTreeSet::new(),
+                    },
+                );
+            }
+        }
+
+        // the linkage call graph resolves calls within the package to this package id
+        let internal_prefix = format!("{}::", package_id);
+        let mut edges: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+        for module_call_graph in call_graph.get_module_call_graphs() {
+            for (caller, callees) in module_call_graph.get_linkage_call_graph() {
+                edges.insert(
+                    format!("{}::{}", module_call_graph.get_module_name(), caller),
+                    callees
+                        .iter()
+                        .filter_map(|callee| callee.strip_prefix(&internal_prefix))
+                        .map(|callee| callee.to_string())
+                        .collect(),
+                );
+            }
+        }
+
+        let roots: BTreeSet<String> = functions
+            .iter()
+            .filter(|(function, reachability)| {
+                reachability.visibility == "PUBLIC"
+                    || reachability.is_entry
+                    || function.ends_with(&format!("::{}", INIT_FUNCTION_NAME))
+            })
+            .map(|(function, _)| function.clone())
+            .collect();
+        for root in &roots {
+            let mut stack = vec![root.clone()];
+            let mut visited = BTreeSet::from([root.clone()]);
+            while let Some(function) = stack.pop() {
+                for callee in edges.get(&function).into_iter().flatten() {
+                    if visited.insert(callee.clone()) {
+                        stack.push(callee.clone());
+                    }
+                }
+            }
+            for function in visited {
+                if let Some(reachability) = functions.get_mut(&function) {
+                    reachability.reachable_from.insert(root.clone());
+                }
+            }
+        }
+
+        let dead_functions = functions
+            .iter()
+            .filter(|(_, reachability)| reachability.reachable_from.is_empty())
+            .map(|(function, _)| function.clone())
+            .collect();
+        let mut exclusive_functions: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+        for (function, reachability) in &functions {
+            if roots.contains(function) || reachability.reachable_from.len() != 1 {
+                continue;
+            }
+            let root = reachability.reachable_from.first().unwrap();
+            exclusive_functions
+                .entry(root.clone())
+                .or_default()
+                .insert(function.clone());
+        }
+
+        PackageReachability {
+            package_id: package_id.to_string(),
+            roots,
+            recursive_components: recursive_components(&edges),
+            functions,
+            dead_functions,
+            exclusive_functions,
+        }
+    }
+
+    pub fn get_package_id(&self) -> &str {
+        &self.package_id
+    }
+    pub fn get_roots(&self) -> &BTreeSet<String> {
+        &self.roots
+    }
+    pub fn get_functions(&self) -> &BTreeMap<String, FunctionReachability> {
+        &self.functions
+    }
+    pub fn get_dead_functions(&self) -> &BTreeSet<String> {
+        &self.dead_functions
+    }
+    pub fn get_exclusive_functions(&self) -> &BTreeMap<String, BTreeSet<String>> {
+        &self.exclusive_functions
+    }
+    pub fn get_recursive_components(&self) -> &Vec<BTreeSet<String>> {
+        &self.recursive_components
+    }
+}
+
+impl FunctionReachability {
+    pub fn get_visibility(&self) -> &str {
+        &self.visibility
+    }
+    pub fn get_is_entry(&self) -> bool {
+        self.is_entry
+    }
+    pub fn get_reachable_from(&self) -> &BTreeSet<String> {
+        &self.reachable_from
+    }
+}
+
+// Tarjan's algorithm, keeping the components that contain a cycle
+fn recursive_components(edges: &BTreeMap<String, BTreeSet<String>>) -> Vec<BTreeSet<String>> {
+    #[derive(Default)]
+    struct Tarjan<'a> {
+        next_index: usize,
+        indices: BTreeMap<&'a str, usize>,
+        low_links: BTreeMap<&'a str, usize>,
+        stack: Vec<&'a str>,
+        on_stack: BTreeSet<&'a str>,
+        components: Vec<BTreeSet<String>>,
+    }
+
+    impl<'a> Tarjan<'a> {
+        fn visit(&mut self, edges: &'a BTreeMap<String, BTreeSet<String>>, function: &'a str) {
+            self.indices.insert(function, self.next_index);
+            self.low_links.insert(function, self.next_index);
+            self.next_index += 1;
+            self.stack.push(function);
+            self.on_stack.insert(function);
+
+            for callee in edges.get(function).into_iter().flatten() {
+                let callee = callee.as_str();
+                if !self.indices.contains_key(callee) {
+                    self.visit(edges, callee);
+                    let low_link = self.low_links[function].min(self.low_links[callee]);
+                    self.low_links.insert(function, low_link);
+                } else if self.on_stack.contains(callee) {
+                    let low_link = self.low_links[function].min(self.indices[callee]);
+                    self.low_links.insert(function, low_link);
+                }
+            }
+
+            if self.low_links[function] == self.indices[function] {
+                let mut component = BTreeSet::new();
+                while let Some(member) = self.stack.pop() {
+                    self.on_stack.remove(member);
+                    component.insert(member.to_string());
+                    if member == function {
+                        break;
+                    }
+                }
+                let is_recursive = component.len() > 1
+                    || edges
+                        .get(function)
+                        .is_some_and(|callees| callees.contains(function));
+                if is_recursive {
+                    self.components.push(component);
+                }
+            }
+        }
+    }
+
+    let mut tarjan = Tarjan::default();
+    for function in edges.keys() {
+        if !tarjan.indices.contains_key(function.as_str()) {
+            tarjan.visit(edges, function);
+        }
+    }
+    tarjan.components
+}
+
+#[cfg(test)]
+mod tests {
+    use move_binary_format::file_format::Visibility;
+
+    use super::*;
+    use crate::test_utils::{test_package, TestModuleBuilder};
+
+    fn edges(calls: &[(&str, &[&str])]) -> BTreeMap<String, BTreeSet<String>> {
+        calls
+            .iter()
+            .map(|(caller, callees)| {
+                (
+                    caller.to_string(),
+                    callees.iter().map(|callee| callee.to_string()).collect(),
+                )
+            })
+            .collect()
+    }
+
+    fn component(functions: &[&str]) -> BTreeSet<String> {
+        functions
+            .iter()
+            .map(|function| function.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn acyclic_graph_has_no_recursive_components() {
+        let edges = edges(&[("m::a", &["m::b", "m::c"]), ("m::b", &["m::c"])]);
+        assert!(recursive_components(&edges).is_empty());
+    }
+
+    #[test]
+    fn self_recursion() {
+        let edges = edges(&[("m::a", &["m::a", "m::b"])]);
+        assert_eq!(recursive_components(&edges), vec![component(&["m::a"])]);
+    }
+
+    #[test]
+    fn mutual_recursion() {
+        // a -> b -> c -> a is one component, d -> e -> d another one, reached from c
+        let edges = edges(&[
+            ("m::a", &["m::b"]),
+            ("m::b", &["m::c"]),
+            ("m::c", &["m::a", "m::d"]),
+            ("m::d", &["m::e"]),
+            ("m::e", &["m::d", "m::f"]),
+        ]);
+        let mut components = recursive_components(&edges);
+        components.sort();
+        assert_eq!(
+            components,
+            vec![
+                component(&["m::a", "m::b", "m::c"]),
+                component(&["m::d", "m::e"])
+            ]
+        );
+    }
+
+    #[test]
+    fn callees_without_edges() {
+        // m::b never appears as a caller
+        let edges = edges(&[("m::a", &["m::b"]), ("n::c", &["n::c"])]);
+        assert_eq!(recursive_components(&edges), vec![component(&["n::c"])]);
+    }
+
+    // open and helper are public, run is entry, init runs on publish and orphan is never called
+    fn test_reachability() -> PackageReachability {
+        let mut builder = TestModuleBuilder::new();
+        builder.add_function("open", 0, &[("test", "helper", vec![])]);
+        builder.add_function_def(
+            "run",
+            Visibility::Private,
+            true,
+            0,
+            &[("test", "shared", vec![])],
+        );
+        builder.add_function_def(
+            "init",
+            Visibility::Private,
+            false,
+            0,
+            &[("test", "shared", vec![]), ("test", "setup", vec![])],
+        );
+        builder.add_function_def(
+            "helper",
+            Visibility::Private,
+            false,
+            0,
+            &[("test", "shared", vec![])],
+        );
+        builder.add_function_def("shared", Visibility::Private, false, 0, &[]);
+        builder.add_function_def("setup", Visibility::Friend, false, 0, &[]);
+        builder.add_function_def(
+            "orphan",
+            Visibility::Friend,
+            false,
+            0,
+            &[("test", "helper", vec![])],
+        );
+        builder.add_function_def(
+            "unused",
+            Visibility::Private,
+            false,
+            0,
+            &[("test", "unused", vec![])],
+        );
+        PackageReachability::from(&test_package(&[builder.build()], 1))
+    }
+
+    #[test]
+    fn public_entry_and_init_roots() {
+        let reachability = test_reachability();
+        assert_eq!(
+            *reachability.get_roots(),
+            component(&["test::init", "test::open", "test::run"])
+        );
+        let functions = reachability.get_functions();
+        assert_eq!(functions["test::run"].get_visibility(), "PRIVATE");
+        assert!(functions["test::run"].get_is_entry());
+        assert_eq!(
+            *functions["test::shared"].get_reachable_from(),
+            component(&["test::init", "test::open", "test::run"])
+        );
+        // roots are reachable from themselves
+        assert_eq!(
+            *functions["test::open"].get_reachable_from(),
+            component(&["test::open"])
+        );
+    }
+
+    #[test]
+    fn dead_and_exclusive_functions() {
+        let reachability = test_reachability();
+        // calls from dead functions do not make their callees live
+        assert_eq!(
+            *reachability.get_dead_functions(),
+            component(&["test::orphan", "test::unused"])
+        );
+        assert_eq!(
+            *reachability.get_exclusive_functions(),
+            BTreeMap::from([
+                ("test::init".to_string(), component(&["test::setup"])),
+                ("test::open".to_string(), component(&["test::helper"])),
+            ])
+        );
+        assert_eq!(
+            *reachability.get_recursive_components(),
+            vec![component(&["test::unused"])]
+        );
+    }
+}
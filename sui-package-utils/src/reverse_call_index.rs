@@ -0,0 +1,280 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::Path;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::call_graph::PackageCallGraph;
+use crate::global_call_graph::{
+    canonical_function_name, get_changed_packages, load_linkage_call_graph, GlobalCallGraphError,
+    StoreIndexUpdate,
+};
+use crate::package_id_io::PackagesDir;
+
+// store-level file next to layout.json
+pub const REVERSE_CALL_INDEX_FILE: &str = "reverse_call_index.json";
+// bump when the shape of reverse_call_index.json changes, older files are rebuilt from scratch
+pub const REVERSE_CALL_INDEX_SCHEMA_VERSION: u32 = 2;
+
+// callee -> call sites across the store. Callees are keyed by their original package id so a
+// query finds the callers of every version, each call site records the version it links to
+#[derive(Debug, Default, Deserialize, Serialize, JsonSchema)]
+pub struct ReverseCallIndex {
+    schema_version: u32,
+    // storage id -> indexed version, system packages keep their id across versions
+    packages: BTreeMap<String, u64>,
+    // storage id -> original id of every indexed package, so queries can name any version
+    original_ids: BTreeMap<String, String>,
+    callers: BTreeMap<String, BTreeSet<CallerEntry>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize, JsonSchema)]
+pub struct CallerEntry {
+    pub package_id: String,
+    pub module_name: String,
+    pub function_name: String,
+    // the callee package version the caller's linkage table resolves to
+    pub callee_package_id: String,
+}
+
+#[derive(Error, Debug)]
+pub enum ReverseCallIndexError {
+    #[error("Error reading or writing reverse_call_index.json: {0}")]
+    IoError(#[from] std::io::Error),
+    #[error("Error parsing reverse_call_index.json: {0}")]
+    ParseError(#[from] serde_json::Error),
+    #[error(transparent)]
+    InvalidFunctionName(#[from] GlobalCallGraphError),
+}
+
+impl ReverseCallIndex {
+    // returns an empty index if the store has none yet or it was written by an older version
+    pub fn load(prefix: &Path) -> Result<Self, ReverseCallIndexError> {
+        let index_file = prefix.join(REVERSE_CALL_INDEX_FILE);
+        if !index_file.exists() {
+            return Ok(Self::new());
+        }
+        let index: ReverseCallIndex = serde_json::from_str(&fs::read_to_string(index_file)?)?;
+        if index.schema_version != REVERSE_CALL_INDEX_SCHEMA_VERSION {
+            return Ok(Self::new());
+        }
+        Ok(index)
+    }
+
+    pub fn save(&self, prefix: &Path) -> Result<(), ReverseCallIndexError> {
+        fs::write(
+            prefix.join(REVERSE_CALL_INDEX_FILE),
+            serde_json::to_string_pretty(self)?,
+        )?;
+        Ok(())
+    }
+
+    fn new() -> Self {
+        ReverseCallIndex {
+            schema_version: REVERSE_CALL_INDEX_SCHEMA_VERSION,
+            ..Default::default()
+        }
+    }
+
+    pub fn get_packages(&self) -> &BTreeMap<String, u64> {
+        &self.packages
+    }
+
+    // indexes the packages of the store that are not in the index yet or were indexed at another
+    // version, replacing the call sites of the previous version
+    pub fn update(
+        &mut self,
+        packages_dir: &PackagesDir,
+    ) -> Result<StoreIndexUpdate, ReverseCallIndexError> {
+        let mut update = StoreIndexUpdate::default();
+        for (package_id, metadata) in
+            get_changed_packages(packages_dir, &self.packages, &mut update)?
+        {
+            match load_linkage_call_graph(packages_dir, &package_id) {
+                Ok(call_graph) => {
+                    self.remove_package(&package_id);
+                    self.add_package(&call_graph, &metadata.original_package_id, metadata.version);
+                    update.updated += 1;
+                }
+                Err(e) => update.failures.push((package_id, e.to_string())),
+            }
+        }
+        Ok(update)
+    }
+
+    // call sites keep the bytecode callee, keyed by original id, next to the linkage callee
+    fn add_package(&mut self, call_graph: &PackageCallGraph, original_id: &str, version: u64) {
+        let package_id = call_graph.get_package_id().to_string();
+        for module_call_graph in call_graph.get_module_call_graphs() {
+            for (caller, function_call_sites) in module_call_graph.get_call_sites() {
+                for call_site in function_call_sites.get_call_sites() {
+                    let Some((callee_package_id, _)) =
+                        call_site.get_linkage_callee().split_once("::")
+                    else {
+                        continue;
+                    };
+                    self.callers
+                        .entry(call_site.get_callee().to_string())
+                        .or_default()
+                        .insert(CallerEntry {
+                            package_id: package_id.clone(),
+                            module_name: module_call_graph.get_module_name().to_string(),
+                            function_name: caller.clone(),
+                            callee_package_id: callee_package_id.to_string(),
+                        });
+                }
+            }
+        }
+        self.original_ids
+            .insert(package_id.clone(), original_id.to_string());
+        self.packages.insert(package_id, version);
+    }
+
+    fn remove_package(&mut self, package_id: &str) {
+        if self.packages.remove(package_id).is_none() {
+            return;
+        }
+        for callers in self.callers.values_mut() {
+            callers.retain(|caller| caller.package_id != package_id);
+        }
+        self.callers.retain(|_, callers| !callers.is_empty());
+    }
+
+    // call sites of a function named at any of its package versions. When version_aware is
+    // set, only the call sites linked to exactly that version are returned
+    pub fn who_calls(
+        &self,
+        function: &str,
+        version_aware: bool,
+    ) -> Result<Vec<&CallerEntry>, ReverseCallIndexError> {
+        let function = canonical_function_name(function)?;
+        let Some((package_id, module_and_name)) = function.split_once("::") else {
+            return Ok(Vec::new());
+        };
+        let original_id = self
+            .original_ids
+            .get(package_id)
+            .map(|original_id| original_id.as_str())
+            .unwrap_or(package_id);
+        Ok(self
+            .callers
+            .get(&format!("{}::{}", original_id, module_and_name))
+            .into_iter()
+            .flatten()
+            .filter(|caller| !version_aware || caller.callee_package_id == package_id)
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use move_binary_format::file_format::{CompiledModule, SignatureToken};
+    use move_core_types::account_address::AccountAddress;
+    use sui_types::move_package::MovePackage;
+
+    use super::*;
+    use crate::common_types::MovePackageWithMetadata;
+    use crate::test_utils::{
+        test_dir, test_package, test_package_with_metadata, test_upgraded_package,
+        write_test_package, TestModuleBuilder,
+    };
+
+    fn write_package(packages_dir: &PackagesDir, package: MovePackage) {
+        write_test_package(
+            packages_dir,
+            &MovePackageWithMetadata {
+                package,
+                ..test_package_with_metadata()
+            },
+        );
+    }
+
+    fn test_module(
+        address: &str,
+        name: &str,
+        calls: &[(&str, &str, Vec<SignatureToken>)],
+    ) -> CompiledModule {
+        let mut builder =
+            TestModuleBuilder::at(AccountAddress::from_hex_literal(address).unwrap(), name);
+        builder.add_function("run", 0, calls);
+        builder.build()
+    }
+
+    // 0x7::math at versions 0x7 and 0x8, 0x5::pool linked to 0x8 and 0x6::vault linked to 0x7
+    fn write_test_store(name: &str) -> PackagesDir {
+        let packages_dir = PackagesDir::new(test_dir(name));
+        let math = test_module("0x7", "math", &[]);
+        write_package(&packages_dir, test_package(&[math.clone()], 1));
+        write_package(&packages_dir, test_upgraded_package("0x8", &[math], 2, &[]));
+        let pool = test_module("0x5", "pool", &[("0x7::math", "run", vec![])]);
+        write_package(
+            &packages_dir,
+            test_upgraded_package("0x5", &[pool], 1, &[("0x7", "0x8", 2)]),
+        );
+        let vault = test_module("0x6", "vault", &[("0x7::math", "run", vec![])]);
+        write_package(
+            &packages_dir,
+            test_upgraded_package("0x6", &[vault], 1, &[("0x7", "0x7", 1)]),
+        );
+        packages_dir
+    }
+
+    fn callers(index: &ReverseCallIndex, function: &str, version_aware: bool) -> Vec<String> {
+        index
+            .who_calls(function, version_aware)
+            .unwrap()
+            .into_iter()
+            .map(|caller| format!("{}::{}", caller.module_name, caller.function_name))
+            .collect()
+    }
+
+    #[test]
+    fn who_calls_any_version() {
+        let packages_dir = write_test_store("reverse-call-index-any-version");
+        let mut index = ReverseCallIndex::load(&packages_dir.get_prefix()).unwrap();
+        assert_eq!(index.update(&packages_dir).unwrap().updated, 4);
+        assert_eq!(index.update(&packages_dir).unwrap().updated, 0);
+
+        // either version names the same function
+        for function in ["0x7::math::run", "0x8::math::run"] {
+            assert_eq!(
+                callers(&index, function, false),
+                vec!["pool::run", "vault::run"]
+            );
+        }
+        assert!(callers(&index, "0x5::pool::run", false).is_empty());
+    }
+
+    #[test]
+    fn who_calls_version_aware() {
+        let packages_dir = write_test_store("reverse-call-index-version-aware");
+        let mut index = ReverseCallIndex::load(&packages_dir.get_prefix()).unwrap();
+        index.update(&packages_dir).unwrap();
+        index.save(&packages_dir.get_prefix()).unwrap();
+        let index = ReverseCallIndex::load(&packages_dir.get_prefix()).unwrap();
+
+        assert_eq!(callers(&index, "0x8::math::run", true), vec!["pool::run"]);
+        assert_eq!(callers(&index, "0x7::math::run", true), vec!["vault::run"]);
+    }
+
+    #[test]
+    fn reindex_package_upgraded_in_place() {
+        let packages_dir = write_test_store("reverse-call-index-in-place");
+        let mut index = ReverseCallIndex::load(&packages_dir.get_prefix()).unwrap();
+        index.update(&packages_dir).unwrap();
+
+        // 0x5::pool at version 2 under the same storage id no longer calls math
+        let pool = test_module("0x5", "pool", &[]);
+        write_package(
+            &packages_dir,
+            test_upgraded_package("0x5", &[pool], 2, &[("0x7", "0x8", 2)]),
+        );
+        let update = index.update(&packages_dir).unwrap();
+        assert_eq!(update.updated, 1);
+        assert!(update.failures.is_empty());
+        assert_eq!(callers(&index, "0x7::math::run", false), vec!["vault::run"]);
+        assert!(callers(&index, "0x8::math::run", true).is_empty());
+    }
+}
@@ -0,0 +1,212 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::common_types::MovePackageWithMetadata;
+
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ArtifactOutcome {
+    Created,
+    Rewritten,
+    // up to date, or regenerated with identical content
+    Unchanged,
+    // the generator failed, the artifact is left unstamped so the next save retries it
+    Failed,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PackageSaveReport {
+    pub package_id: String,
+    pub original_package_id: String,
+    pub version: u64,
+    pub sender: Option<String>,
+    pub checkpoint: u64,
+    pub is_upgrade: bool,
+    pub artifacts: BTreeMap<String, ArtifactOutcome>,
+    // why the artifacts with a Failed outcome could not be generated
+    pub artifact_errors: BTreeMap<String, String>,
+    pub error: Option<String>,
+}
+
+impl From<&MovePackageWithMetadata> for PackageSaveReport {
+    fn from(pkg_with_metadata: &MovePackageWithMetadata) -> Self {
+        let package = &pkg_with_metadata.package;
+        PackageSaveReport {
+            package_id: package.id().to_canonical_string(true),
+            original_package_id: package.original_package_id().to_canonical_string(true),
+            version: package.version().value(),
+            sender: pkg_with_metadata.sender.clone(),
+            checkpoint: pkg_with_metadata.checkpoint,
+            // system packages are upgraded in place, so their id never changes
+            is_upgrade: package.id() != package.original_package_id()
+                || package.version().value() > 1,
+            artifacts: BTreeMap::new(),
+            artifact_errors: BTreeMap::new(),
+            error: None,
+        }
+    }
+}
+
+impl PackageSaveReport {
+    pub fn artifact_failed(&mut self, artifact: &str, error: String) {
+        self.artifacts
+            .insert(artifact.to_string(), ArtifactOutcome::Failed);
+        self.artifact_errors.insert(artifact.to_string(), error);
+    }
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RunReport {
+    pub after_checkpoint: u64,
+    pub max_checkpoint: u64,
+    pub packages_saved: u64,
+    pub packages_failed: u64,
+    pub new_packages: u64,
+    pub upgrades: u64,
+    pub artifacts_created: u64,
+    pub artifacts_rewritten: u64,
+    pub artifacts_failed: u64,
+    pub packages_by_sender: BTreeMap<String, u64>,
+    pub packages: Vec<PackageSaveReport>,
+}
+
+impl RunReport {
+    pub fn new(after_checkpoint: u64, max_checkpoint: u64) -> Self {
+        RunReport {
+            after_checkpoint,
+            max_checkpoint,
+            ..Default::default()
+        }
+    }
+
+    pub fn add(&mut self, package_report: PackageSaveReport) {
+        if package_report.error.is_some() {
+            self.packages_failed += 1;
+        } else {
+            self.packages_saved += 1;
+        }
+        if package_report.is_upgrade {
+            self.upgrades += 1;
+        } else {
+            self.new_packages += 1;
+        }
+        for outcome in package_report.artifacts.values() {
+            match outcome {
+                ArtifactOutcome::Created => self.artifacts_created += 1,
+                ArtifactOutcome::Rewritten => self.artifacts_rewritten += 1,
+                ArtifactOutcome::Failed => self.artifacts_failed += 1,
+                ArtifactOutcome::Unchanged => {}
+            }
+        }
+        let sender = package_report
+            .sender
+            .clone()
+            .unwrap_or_else(|| "unknown".to_string());
+        *self.packages_by_sender.entry(sender).or_insert(0) += 1;
+        self.packages.push(package_report);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::test_package_with_metadata;
+    use sui_types::base_types::{ObjectID, SequenceNumber};
+    use sui_types::move_package::MovePackage;
+
+    // the test package at another id and version, keeping the original id of its modules
+    fn with_id_and_version(
+        pkg_with_metadata: &MovePackageWithMetadata,
+        id: &str,
+        version: u64,
+    ) -> MovePackageWithMetadata {
+        let package = MovePackage::new(
+            ObjectID::from_hex_literal(id).unwrap(),
+            SequenceNumber::from_u64(version),
+            pkg_with_metadata.package.serialized_module_map().clone(),
+            u64::MAX,
+            vec![],
+            BTreeMap::new(),
+        )
+        .unwrap();
+        MovePackageWithMetadata {
+            package,
+            checkpoint: pkg_with_metadata.checkpoint,
+            transaction_digest: pkg_with_metadata.transaction_digest.clone(),
+            sender: pkg_with_metadata.sender.clone(),
+        }
+    }
+
+    #[test]
+    fn add_splits_new_packages_and_upgrades() {
+        let published = test_package_with_metadata();
+        let id = published.package.id().to_canonical_string(true);
+        // upgraded in place like system packages
+        let upgraded_in_place = with_id_and_version(&published, &id, 2);
+        let upgraded = with_id_and_version(&published, "0xbb", 2);
+        // the first version of a package published at another id than its modules
+        let moved = with_id_and_version(&published, "0xbb", 1);
+
+        let mut report = RunReport::new(0, 42);
+        let mut published_report = PackageSaveReport::from(&published);
+        published_report
+            .artifacts
+            .insert("bcs.json".to_string(), ArtifactOutcome::Created);
+        published_report
+            .artifacts
+            .insert("metadata.json".to_string(), ArtifactOutcome::Unchanged);
+        report.add(published_report);
+        let mut upgraded_report = PackageSaveReport::from(&upgraded_in_place);
+        upgraded_report
+            .artifacts
+            .insert("bcs.json".to_string(), ArtifactOutcome::Rewritten);
+        report.add(upgraded_report);
+        let mut upgraded_report = PackageSaveReport::from(&upgraded);
+        upgraded_report.artifact_failed(
+            "decompiled_modules/test.move",
+            "move-decompiler exited with 1".to_string(),
+        );
+        report.add(upgraded_report);
+        // a failed package keeps the outcomes of the artifacts saved before the error
+        let mut moved_report = PackageSaveReport::from(&moved);
+        moved_report
+            .artifacts
+            .insert("bcs.json".to_string(), ArtifactOutcome::Created);
+        moved_report.error = Some("error".to_string());
+        report.add(moved_report);
+
+        assert_eq!(report.packages_saved, 3);
+        assert_eq!(report.packages_failed, 1);
+        assert_eq!(report.new_packages, 1);
+        assert_eq!(report.upgrades, 3);
+        assert_eq!(report.artifacts_created, 2);
+        assert_eq!(report.artifacts_rewritten, 1);
+        // a failed artifact does not fail the package
+        assert_eq!(report.artifacts_failed, 1);
+        assert_eq!(report.packages.len(), 4);
+    }
+
+    #[test]
+    fn add_counts_packages_by_sender() {
+        let mut report = RunReport::new(0, 42);
+        let pkg_with_metadata = test_package_with_metadata();
+        report.add(PackageSaveReport::from(&pkg_with_metadata));
+        let mut failed = PackageSaveReport::from(&pkg_with_metadata);
+        failed.error = Some("error".to_string());
+        report.add(failed);
+        let mut without_sender = PackageSaveReport::from(&pkg_with_metadata);
+        without_sender.sender = None;
+        report.add(without_sender);
+
+        assert_eq!(
+            report.packages_by_sender,
+            BTreeMap::from([
+                (pkg_with_metadata.sender.clone().unwrap(), 2),
+                ("unknown".to_string(), 1),
+            ])
+        );
+    }
+}
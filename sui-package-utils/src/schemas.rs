@@ -0,0 +1,229 @@
+use std::fs;
+use std::path::Path;
+
+use jsonschema::JSONSchema;
+use schemars::schema::RootSchema;
+use schemars::schema_for;
+use serde_json::Value;
+use thiserror::Error;
+
+use crate::artifact_manifest::{ArtifactManifest, ARTIFACT_MANIFEST_FILE};
+use crate::bcs_json::BcsJsonSchema;
+use crate::call_graph::PackageCallGraph;
+use crate::events_index::{EventsIndex, EVENTS_INDEX_FILE};
+use crate::global_call_graph::{GlobalCallGraph, GLOBAL_CALL_GRAPH_FILE};
+use crate::metadata::PackageMetadata;
+use crate::package_artifacts::{PackageArtifact, PACKAGE_ARTIFACTS};
+use crate::reverse_call_index::{ReverseCallIndex, REVERSE_CALL_INDEX_FILE};
+use crate::store_layout::{StoreLayout, LAYOUT_FILE};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArtifactKind {
+    Bcs,
+    CallGraph,
+    // the optional artifacts of package_artifacts.rs
+    Package(&'static PackageArtifact),
+    Metadata,
+    Manifest,
+    Layout,
+    GlobalCallGraph,
+    ReverseCallIndex,
+    EventsIndex,
+}
+
+pub fn artifact_kinds() -> Vec<ArtifactKind> {
+    let mut kinds = vec![ArtifactKind::Bcs, ArtifactKind::CallGraph];
+    kinds.extend(PACKAGE_ARTIFACTS.iter().map(ArtifactKind::Package));
+    kinds.extend([
+        ArtifactKind::Metadata,
+        ArtifactKind::Manifest,
+        ArtifactKind::Layout,
+        ArtifactKind::GlobalCallGraph,
+        ArtifactKind::ReverseCallIndex,
+        ArtifactKind::EventsIndex,
+    ]);
+    kinds
+}
+
+#[derive(Error, Debug)]
+pub enum SchemaError {
+    #[error("Error reading or writing {0}: {1}")]
+    IoError(String, std::io::Error),
+    #[error("Error parsing {0}: {1}")]
+    ParseError(String, serde_json::Error),
+    #[error("No schema for artifact {0}")]
+    UnknownArtifact(String),
+    #[error("Invalid schema for {0}: {1}")]
+    InvalidSchema(String, String),
+}
+
+impl ArtifactKind {
+    // the store file name the artifact is written to, layout.json and the store-wide indexes
+    // live at the store prefix
+    pub fn file_name(&self) -> &'static str {
+        match self {
+            ArtifactKind::Bcs => "bcs.json",
+            ArtifactKind::CallGraph => "call_graph.json",
+            ArtifactKind::Package(artifact) => artifact.file_name,
+            ArtifactKind::Metadata => "metadata.json",
+            ArtifactKind::Manifest => ARTIFACT_MANIFEST_FILE,
+            ArtifactKind::Layout => LAYOUT_FILE,
+            ArtifactKind::GlobalCallGraph => GLOBAL_CALL_GRAPH_FILE,
+            ArtifactKind::ReverseCallIndex => REVERSE_CALL_INDEX_FILE,
+            ArtifactKind::EventsIndex => EVENTS_INDEX_FILE,
+        }
+    }
+
+    pub fn from_file_name(file_name: &str) -> Option<Self> {
+        artifact_kinds()
+            .into_iter()
+            .find(|kind| kind.file_name() == file_name)
+    }
+
+    // e.g. bcs.schema.json
+    pub fn schema_file_name(&self) -> String {
+        self.file_name().replace(".json", ".schema.json")
+    }
+
+    pub fn schema(&self) -> RootSchema {
+        match self {
+            ArtifactKind::Bcs => schema_for!(BcsJsonSchema),
+            ArtifactKind::CallGraph => schema_for!(PackageCallGraph),
+            ArtifactKind::Package(artifact) => (artifact.schema)(),
+            ArtifactKind::Metadata => schema_for!(PackageMetadata),
+            ArtifactKind::Manifest => schema_for!(ArtifactManifest),
+            ArtifactKind::Layout => schema_for!(StoreLayout),
+            ArtifactKind::GlobalCallGraph => schema_for!(GlobalCallGraph),
+            ArtifactKind::ReverseCallIndex => schema_for!(ReverseCallIndex),
+            ArtifactKind::EventsIndex => schema_for!(EventsIndex),
+        }
+    }
+}
+
+// writes one <artifact>.schema.json per artifact kind, returns the written file names
+pub fn write_schemas(out_dir: &Path) -> Result<Vec<String>, SchemaError> {
+    let out_dir_name = out_dir.to_string_lossy().to_string();
+    fs::create_dir_all(out_dir).map_err(|e| SchemaError::IoError(out_dir_name, e))?;
+    let mut written = Vec::new();
+    for kind in artifact_kinds() {
+        let schema_file_name = kind.schema_file_name();
+        let schema_json = serde_json::to_string_pretty(&kind.schema())
+            .map_err(|e| SchemaError::ParseError(schema_file_name.clone(), e))?;
+        fs::write(out_dir.join(&schema_file_name), schema_json)
+            .map_err(|e| SchemaError::IoError(schema_file_name.clone(), e))?;
+        written.push(schema_file_name);
+    }
+    Ok(written)
+}
+
+// compiled schemas of every artifact kind, compiled once so a whole store can be validated
+pub struct ArtifactValidator {
+    schemas: Vec<(ArtifactKind, JSONSchema)>,
+}
+
+impl ArtifactValidator {
+    pub fn new() -> Result<Self, SchemaError> {
+        let mut schemas = Vec::new();
+        for kind in artifact_kinds() {
+            let schema = serde_json::to_value(kind.schema())
+                .map_err(|e| SchemaError::ParseError(kind.schema_file_name(), e))?;
+            let compiled_schema = JSONSchema::compile(&schema)
+                .map_err(|e| SchemaError::InvalidSchema(kind.schema_file_name(), e.to_string()))?;
+            schemas.push((kind, compiled_schema));
+        }
+        Ok(ArtifactValidator { schemas })
+    }
+
+    // checks an artifact file against the schema of its kind, inferred from the file name.
+    // returns the validation errors, an empty list means the artifact is valid
+    pub fn validate_file(&self, artifact_path: &Path) -> Result<Vec<String>, SchemaError> {
+        let path_name = artifact_path.to_string_lossy().to_string();
+        let kind = artifact_path
+            .file_name()
+            .and_then(|file_name| file_name.to_str())
+            .and_then(ArtifactKind::from_file_name)
+            .ok_or_else(|| SchemaError::UnknownArtifact(path_name.clone()))?;
+        let artifact_json = fs::read_to_string(artifact_path)
+            .map_err(|e| SchemaError::IoError(path_name.clone(), e))?;
+        let artifact: Value = serde_json::from_str(&artifact_json)
+            .map_err(|e| SchemaError::ParseError(path_name, e))?;
+        Ok(self.validate_value(kind, &artifact))
+    }
+
+    pub fn validate_value(&self, kind: ArtifactKind, artifact: &Value) -> Vec<String> {
+        let (_, compiled_schema) = self
+            .schemas
+            .iter()
+            .find(|(schema_kind, _)| *schema_kind == kind)
+            .expect("every artifact kind has a compiled schema");
+        match compiled_schema.validate(artifact) {
+            Ok(()) => Vec::new(),
+            Err(errors) => errors
+                .map(|e| format!("{}: {}", e.instance_path, e))
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{test_dir, test_package_with_metadata};
+
+    #[test]
+    fn artifact_kinds_by_file_name() {
+        for kind in artifact_kinds() {
+            assert_eq!(ArtifactKind::from_file_name(kind.file_name()), Some(kind));
+        }
+        assert_eq!(
+            ArtifactKind::from_file_name("constants.json"),
+            Some(ArtifactKind::Package(&PACKAGE_ARTIFACTS[0]))
+        );
+        assert_eq!(ArtifactKind::Bcs.schema_file_name(), "bcs.schema.json");
+        assert_eq!(ArtifactKind::from_file_name("other.json"), None);
+    }
+
+    #[test]
+    fn write_schemas_for_every_kind() {
+        let out_dir = test_dir("schemas-write");
+        let written = write_schemas(&out_dir).unwrap();
+        assert_eq!(written.len(), artifact_kinds().len());
+        for schema_file_name in written {
+            let schema_json = fs::read_to_string(out_dir.join(schema_file_name)).unwrap();
+            serde_json::from_str::<Value>(&schema_json).unwrap();
+        }
+        fs::remove_dir_all(out_dir).unwrap();
+    }
+
+    #[test]
+    fn generated_artifacts_match_their_schema() {
+        let validator = ArtifactValidator::new().unwrap();
+        let pkg_with_metadata = test_package_with_metadata();
+        let bcs_json =
+            serde_json::to_value(BcsJsonSchema::from(&pkg_with_metadata.package)).unwrap();
+        assert!(validator
+            .validate_value(ArtifactKind::Bcs, &bcs_json)
+            .is_empty());
+        let metadata = serde_json::to_value(PackageMetadata::from(&pkg_with_metadata)).unwrap();
+        assert!(validator
+            .validate_value(ArtifactKind::Metadata, &metadata)
+            .is_empty());
+        for artifact in &PACKAGE_ARTIFACTS {
+            let artifact_json = (artifact.generate)(&pkg_with_metadata.package).unwrap();
+            let errors = validator.validate_value(
+                ArtifactKind::Package(artifact),
+                &serde_json::from_str(&artifact_json).unwrap(),
+            );
+            assert!(errors.is_empty(), "{}: {:?}", artifact.file_name, errors);
+        }
+
+        let mut invalid_bcs_json = bcs_json.clone();
+        invalid_bcs_json["version"] = Value::String("1".to_string());
+        assert_eq!(
+            validator
+                .validate_value(ArtifactKind::Bcs, &invalid_bcs_json)
+                .len(),
+            1
+        );
+    }
+}
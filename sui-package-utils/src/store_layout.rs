@@ -0,0 +1,470 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::artifact_manifest::{ARTIFACT_MANIFEST_FILE, ARTIFACT_MANIFEST_SCHEMA_VERSION};
+use crate::bcs_json::BCS_JSON_SCHEMA_VERSION;
+use crate::call_graph::CALL_GRAPH_SCHEMA_VERSION;
+use crate::metadata::METADATA_SCHEMA_VERSION;
+use crate::package_artifacts::{get_package_artifact, PackageArtifact};
+use crate::package_id_io::PackagesDir;
+use crate::package_saver::{save_package, SaveArgs};
+
+pub const LAYOUT_FILE: &str = "layout.json";
+
+// version 1: <prefix>/<first 4 chars of id>/<last 62 chars of id>, see PackagesDir::get_package_dir.
+// stores written before layout.json existed are treated as version 0 and use the same directories
+pub const CURRENT_LAYOUT_VERSION: u32 = 1;
+const PACKAGE_DIR_LAYOUT: &str = "<first 4 chars of id>/<last 62 chars of id>";
+// artifacts written before layout.json existed, all at schema version 1
+const LEGACY_ARTIFACTS: [&str; 4] = [
+    "bcs.json",
+    "call_graph.json",
+    "metadata.json",
+    ARTIFACT_MANIFEST_FILE,
+];
+
+#[derive(Debug, Deserialize, PartialEq, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct StoreLayout {
+    pub layout_version: u32,
+    pub package_dir_layout: String,
+    pub schema_versions: BTreeMap<String, u32>,
+}
+
+#[derive(Error, Debug)]
+pub enum StoreLayoutError {
+    #[error("Error reading or writing layout.json: {0}")]
+    IoError(#[from] std::io::Error),
+    #[error("Error parsing layout.json: {0}")]
+    ParseError(#[from] serde_json::Error),
+    #[error(
+        "Store layout version {0} is newer than supported version {}",
+        CURRENT_LAYOUT_VERSION
+    )]
+    UnsupportedLayoutVersion(u32),
+    #[error("Store is outdated ({0}), run sui-packages-migrate first")]
+    OutdatedStore(String),
+    #[error("Error migrating {} package(s):\n{}", .0.len(), format_failures(.0))]
+    MigrationErrors(Vec<(String, String)>),
+}
+
+fn format_failures(failures: &[(String, String)]) -> String {
+    failures
+        .iter()
+        .map(|(package, e)| format!("{}: {}", package, e))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+impl StoreLayout {
+    pub fn current(artifacts: &[&PackageArtifact]) -> Self {
+        StoreLayout {
+            layout_version: CURRENT_LAYOUT_VERSION,
+            package_dir_layout: PACKAGE_DIR_LAYOUT.to_string(),
+            schema_versions: current_schema_versions(artifacts),
+        }
+    }
+
+    // returns None for stores created before layout.json existed
+    pub fn load(prefix: &Path) -> Result<Option<Self>, StoreLayoutError> {
+        let layout_file = prefix.join(LAYOUT_FILE);
+        if !layout_file.exists() {
+            return Ok(None);
+        }
+        let layout_json = fs::read_to_string(layout_file)?;
+        Ok(Some(serde_json::from_str(&layout_json)?))
+    }
+
+    pub fn save(&self, prefix: &Path) -> Result<(), StoreLayoutError> {
+        fs::create_dir_all(prefix)?;
+        fs::write(
+            prefix.join(LAYOUT_FILE),
+            serde_json::to_string_pretty(self)?,
+        )?;
+        Ok(())
+    }
+
+    // stores written before layout.json existed (layout 0) use the directories of layout 1 and
+    // have the legacy artifacts at schema version 1
+    fn legacy() -> Self {
+        StoreLayout {
+            layout_version: 1,
+            package_dir_layout: PACKAGE_DIR_LAYOUT.to_string(),
+            schema_versions: LEGACY_ARTIFACTS
+                .iter()
+                .map(|artifact| (artifact.to_string(), 1))
+                .collect(),
+        }
+    }
+
+    // the optional artifacts the store contains
+    pub fn get_artifacts(&self) -> Vec<&'static PackageArtifact> {
+        self.schema_versions
+            .keys()
+            .filter_map(|artifact| get_package_artifact(artifact))
+            .collect()
+    }
+
+    // artifacts whose schema in the store is older than what this crate writes, out of the
+    // required ones and the given optional artifacts
+    pub fn outdated_artifacts(&self, artifacts: &[&PackageArtifact]) -> Vec<String> {
+        current_schema_versions(artifacts)
+            .into_iter()
+            .filter(|(artifact, version)| {
+                self.schema_versions.get(artifact).copied().unwrap_or(0) < *version
+            })
+            .map(|(artifact, _)| artifact)
+            .collect()
+    }
+}
+
+// the schema version the store's artifacts of a kind were written with, 0 if it has none. Stores
+// created before layout.json existed have their legacy artifacts at version 1
+pub fn get_store_schema_version(prefix: &Path, artifact: &str) -> Result<u32, StoreLayoutError> {
+    Ok(match StoreLayout::load(prefix)? {
+        Some(layout) => layout.schema_versions.get(artifact).copied().unwrap_or(0),
+        None if LEGACY_ARTIFACTS.contains(&artifact) => 1,
+        None => 0,
+    })
+}
+
+// the schema versions of the artifacts every store has, plus the given optional artifacts
+pub fn current_schema_versions(artifacts: &[&PackageArtifact]) -> BTreeMap<String, u32> {
+    let mut schema_versions = BTreeMap::from([
+        ("bcs.json".to_string(), BCS_JSON_SCHEMA_VERSION),
+        ("call_graph.json".to_string(), CALL_GRAPH_SCHEMA_VERSION),
+        ("metadata.json".to_string(), METADATA_SCHEMA_VERSION),
+        (
+            ARTIFACT_MANIFEST_FILE.to_string(),
+            ARTIFACT_MANIFEST_SCHEMA_VERSION,
+        ),
+    ]);
+    for artifact in artifacts {
+        schema_versions.insert(artifact.file_name.to_string(), artifact.schema_version);
+    }
+    schema_versions
+}
+
+// makes sure writers only touch stores with the current layout. Empty stores get a fresh
+// layout.json, stores without one are treated as layout 0 and get it written as long as none of
+// their artifacts is outdated, anything else has to go through migrate_store first. Optional
+// artifacts only count when enabled: one the store does not have yet is added to layout.json,
+// one it has at an older schema version needs a migration
+pub fn ensure_current_layout(
+    packages_dir: &PackagesDir,
+    artifacts: &[&PackageArtifact],
+) -> Result<(), StoreLayoutError> {
+    let prefix = packages_dir.get_prefix();
+    let (mut layout, layout_changed) = match StoreLayout::load(&prefix)? {
+        Some(layout) => (layout, false),
+        None => {
+            if !prefix.exists() || packages_dir.get_package_directories()?.is_empty() {
+                return StoreLayout::current(artifacts).save(&prefix);
+            }
+            (StoreLayout::legacy(), true)
+        }
+    };
+    if layout.layout_version > CURRENT_LAYOUT_VERSION {
+        return Err(StoreLayoutError::UnsupportedLayoutVersion(
+            layout.layout_version,
+        ));
+    }
+    if layout.layout_version < CURRENT_LAYOUT_VERSION {
+        return Err(StoreLayoutError::OutdatedStore(format!(
+            "layout version {}",
+            layout.layout_version
+        )));
+    }
+    let new_artifacts: Vec<&PackageArtifact> = artifacts
+        .iter()
+        .filter(|artifact| !layout.schema_versions.contains_key(artifact.file_name))
+        .copied()
+        .collect();
+    let outdated_artifacts: Vec<String> = layout
+        .outdated_artifacts(artifacts)
+        .into_iter()
+        .filter(|artifact| !new_artifacts.iter().any(|new| *artifact == new.file_name))
+        .collect();
+    if !outdated_artifacts.is_empty() {
+        return Err(StoreLayoutError::OutdatedStore(
+            outdated_artifacts.join(", "),
+        ));
+    }
+    if new_artifacts.is_empty() && !layout_changed {
+        return Ok(());
+    }
+    for artifact in new_artifacts {
+        layout
+            .schema_versions
+            .insert(artifact.file_name.to_string(), artifact.schema_version);
+    }
+    layout.save(&prefix)
+}
+
+// upgrades the store in place: first the directory layout, then every artifact whose schema
+// version is behind. Optional artifacts are only regenerated in the packages that have them.
+// Returns the list of steps that were (or, with dry_run, would be) applied
+pub fn migrate_store(
+    packages_dir: &PackagesDir,
+    dry_run: bool,
+) -> Result<Vec<String>, StoreLayoutError> {
+    let prefix = packages_dir.get_prefix();
+    let mut steps: Vec<String> = Vec::new();
+    let mut layout = match StoreLayout::load(&prefix)? {
+        Some(layout) => layout,
+        None => {
+            steps.push(format!("layout 0 -> 1: write {}", LAYOUT_FILE));
+            StoreLayout::legacy()
+        }
+    };
+    if layout.layout_version > CURRENT_LAYOUT_VERSION {
+        return Err(StoreLayoutError::UnsupportedLayoutVersion(
+            layout.layout_version,
+        ));
+    }
+
+    let store_artifacts = layout.get_artifacts();
+    let current_versions = current_schema_versions(&store_artifacts);
+    let outdated_artifacts = layout.outdated_artifacts(&store_artifacts);
+    if !outdated_artifacts.is_empty() {
+        let mut save_args = SaveArgs {
+            bcs: false,
+            bytecode: false,
+            call_graph: false,
+            artifacts: Vec::new(),
+            metadata: false,
+            move_code: false,
+            force: false,
+            packages_dir: prefix.clone(),
+            move_decompiler_path: Default::default(),
+        };
+        let mut regenerate_artifacts: Vec<&PackageArtifact> = Vec::new();
+        for artifact in &outdated_artifacts {
+            steps.push(format!(
+                "{} {} -> {}: regenerate",
+                artifact,
+                layout.schema_versions.get(artifact).copied().unwrap_or(0),
+                current_versions[artifact]
+            ));
+            match artifact.as_str() {
+                "bcs.json" => save_args.bcs = true,
+                "call_graph.json" => save_args.call_graph = true,
+                "metadata.json" => save_args.metadata = true,
+                // manifests are rewritten as a side effect of saving any artifact
+                ARTIFACT_MANIFEST_FILE => {}
+                _ => regenerate_artifacts.extend(get_package_artifact(artifact)),
+            }
+        }
+        if !dry_run {
+            // keep going when a package fails so one bad package does not block the rest,
+            // layout.json is only bumped once every package has been migrated
+            let mut failures: Vec<(String, String)> = Vec::new();
+            for package_path in packages_dir.get_package_directories()? {
+                let Some(package_id) = packages_dir.get_package_id_from_dir(&package_path) else {
+                    failures.push((
+                        package_path.to_str().unwrap().to_string(),
+                        "not a package directory".to_string(),
+                    ));
+                    continue;
+                };
+                save_args.artifacts = regenerate_artifacts
+                    .iter()
+                    .filter(|artifact| package_path.join(artifact.file_name).exists())
+                    .copied()
+                    .collect();
+                let result = packages_dir
+                    .load_package(&package_id)
+                    .map_err(|e| e.to_string())
+                    .and_then(|pkg_with_metadata| {
+                        save_package(&save_args, &pkg_with_metadata).map_err(|e| e.to_string())
+                    });
+                if let Err(e) = result {
+                    failures.push((package_id, e));
+                }
+            }
+            if !failures.is_empty() {
+                return Err(StoreLayoutError::MigrationErrors(failures));
+            }
+        }
+    }
+
+    layout.layout_version = CURRENT_LAYOUT_VERSION;
+    layout.schema_versions = current_versions;
+    if !dry_run && !steps.is_empty() {
+        layout.save(&prefix)?;
+    }
+    Ok(steps)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::CONSTANTS_SCHEMA_VERSION;
+    use crate::test_utils::test_dir;
+
+    const PACKAGE_ID: &str = "0x00000000000000000000000000000000000000000000000000000000000000aa";
+
+    fn constants_artifact() -> &'static PackageArtifact {
+        get_package_artifact("constants.json").unwrap()
+    }
+
+    fn add_package_dir(packages_dir: &PackagesDir) {
+        fs::create_dir_all(packages_dir.get_package_dir(PACKAGE_ID)).unwrap();
+    }
+
+    #[test]
+    fn empty_store_gets_current_layout() {
+        let prefix = test_dir("layout-empty-store");
+        let packages_dir = PackagesDir::new(prefix.clone());
+        ensure_current_layout(&packages_dir, &[]).unwrap();
+        assert_eq!(
+            StoreLayout::load(&prefix).unwrap(),
+            Some(StoreLayout::current(&[]))
+        );
+
+        // enabling an artifact the store does not have yet only records it
+        add_package_dir(&packages_dir);
+        ensure_current_layout(&packages_dir, &[constants_artifact()]).unwrap();
+        let layout = StoreLayout::load(&prefix).unwrap().unwrap();
+        assert_eq!(layout, StoreLayout::current(&[constants_artifact()]));
+        assert_eq!(layout.get_artifacts(), vec![constants_artifact()]);
+        fs::remove_dir_all(prefix).unwrap();
+    }
+
+    #[test]
+    fn outdated_artifacts_need_migration() {
+        let prefix = test_dir("layout-outdated-artifact");
+        let packages_dir = PackagesDir::new(prefix.clone());
+        let mut layout = StoreLayout::current(&[]);
+        layout.schema_versions.remove("call_graph.json");
+        layout.save(&prefix).unwrap();
+
+        assert_eq!(
+            layout.outdated_artifacts(&[]),
+            vec!["call_graph.json".to_string()]
+        );
+        assert!(matches!(
+            ensure_current_layout(&packages_dir, &[]),
+            Err(StoreLayoutError::OutdatedStore(_))
+        ));
+        fs::remove_dir_all(prefix).unwrap();
+    }
+
+    #[test]
+    fn outdated_optional_artifacts_need_migration_when_enabled() {
+        let prefix = test_dir("layout-outdated-optional-artifact");
+        let packages_dir = PackagesDir::new(prefix.clone());
+        let mut layout = StoreLayout::current(&[constants_artifact()]);
+        layout
+            .schema_versions
+            .insert("constants.json".to_string(), CONSTANTS_SCHEMA_VERSION - 1);
+        layout.save(&prefix).unwrap();
+
+        assert_eq!(
+            layout.outdated_artifacts(&[constants_artifact()]),
+            vec!["constants.json".to_string()]
+        );
+        assert!(matches!(
+            ensure_current_layout(&packages_dir, &[constants_artifact()]),
+            Err(StoreLayoutError::OutdatedStore(_))
+        ));
+        ensure_current_layout(&packages_dir, &[]).unwrap();
+        fs::remove_dir_all(prefix).unwrap();
+    }
+
+    #[test]
+    fn rejects_unsupported_and_outdated_legacy_layouts() {
+        let prefix = test_dir("layout-unsupported");
+        let packages_dir = PackagesDir::new(prefix.clone());
+        let mut layout = StoreLayout::current(&[]);
+        layout.layout_version = CURRENT_LAYOUT_VERSION + 1;
+        layout.save(&prefix).unwrap();
+        assert!(matches!(
+            ensure_current_layout(&packages_dir, &[]),
+            Err(StoreLayoutError::UnsupportedLayoutVersion(_))
+        ));
+        assert!(matches!(
+            migrate_store(&packages_dir, false),
+            Err(StoreLayoutError::UnsupportedLayoutVersion(_))
+        ));
+
+        // packages but no layout.json: layout 0, whose legacy bcs.json and call_graph.json are
+        // behind the current schema versions
+        fs::remove_file(prefix.join(LAYOUT_FILE)).unwrap();
+        add_package_dir(&packages_dir);
+        match ensure_current_layout(&packages_dir, &[]) {
+            Err(StoreLayoutError::OutdatedStore(outdated)) => {
+                assert_eq!(outdated, "bcs.json, call_graph.json");
+            }
+            result => panic!("unexpected result {:?}", result),
+        }
+        assert_eq!(StoreLayout::load(&prefix).unwrap(), None);
+        fs::remove_dir_all(prefix).unwrap();
+    }
+
+    #[test]
+    fn migrates_legacy_store() {
+        let prefix = test_dir("layout-migrate-legacy");
+        let packages_dir = PackagesDir::new(prefix.clone());
+        assert_eq!(get_store_schema_version(&prefix, "bcs.json").unwrap(), 1);
+        // legacy stores have no optional artifacts
+        assert_eq!(
+            get_store_schema_version(&prefix, "constants.json").unwrap(),
+            0
+        );
+
+        let expected_steps = vec![
+            format!("layout 0 -> 1: write {}", LAYOUT_FILE),
+            format!("bcs.json 1 -> {}: regenerate", BCS_JSON_SCHEMA_VERSION),
+            format!(
+                "call_graph.json 1 -> {}: regenerate",
+                CALL_GRAPH_SCHEMA_VERSION
+            ),
+        ];
+        assert_eq!(migrate_store(&packages_dir, true).unwrap(), expected_steps);
+        assert_eq!(StoreLayout::load(&prefix).unwrap(), None);
+
+        assert_eq!(migrate_store(&packages_dir, false).unwrap(), expected_steps);
+        assert_eq!(
+            StoreLayout::load(&prefix).unwrap(),
+            Some(StoreLayout::current(&[]))
+        );
+        assert!(migrate_store(&packages_dir, false).unwrap().is_empty());
+        assert_eq!(
+            get_store_schema_version(&prefix, "bcs.json").unwrap(),
+            BCS_JSON_SCHEMA_VERSION
+        );
+        fs::remove_dir_all(prefix).unwrap();
+    }
+
+    #[test]
+    fn failed_migration_keeps_layout() {
+        let prefix = test_dir("layout-migrate-failure");
+        let packages_dir = PackagesDir::new(prefix.clone());
+        let mut layout = StoreLayout::current(&[]);
+        layout.schema_versions.remove("call_graph.json");
+        layout.save(&prefix).unwrap();
+        // neither package directory has a bcs.json to load the package from, both are reported
+        add_package_dir(&packages_dir);
+        let other_package_id = PACKAGE_ID.replace("aa", "bb");
+        fs::create_dir_all(packages_dir.get_package_dir(&other_package_id)).unwrap();
+        match migrate_store(&packages_dir, false) {
+            Err(StoreLayoutError::MigrationErrors(failures)) => {
+                let mut failed_ids: Vec<&str> = failures
+                    .iter()
+                    .map(|(package_id, _)| package_id.as_str())
+                    .collect();
+                failed_ids.sort();
+                assert_eq!(failed_ids, vec![PACKAGE_ID, other_package_id.as_str()]);
+            }
+            result => panic!("unexpected migration result {:?}", result),
+        }
+        assert_eq!(StoreLayout::load(&prefix).unwrap(), Some(layout));
+        fs::remove_dir_all(prefix).unwrap();
+    }
+}
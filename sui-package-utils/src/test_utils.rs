@@ -0,0 +1,386 @@
+use move_binary_format::file_format::{
+    AbilitySet, AddressIdentifierIndex, Bytecode, CodeUnit, CompiledModule, DatatypeHandle,
+    DatatypeHandleIndex, DatatypeTyParameter, EnumDefinition, FieldDefinition, FunctionDefinition,
+    FunctionHandle, FunctionHandleIndex, FunctionInstantiation, FunctionInstantiationIndex,
+    IdentifierIndex, ModuleHandle, ModuleHandleIndex, Signature, SignatureIndex, SignatureToken,
+    StructDefinition, StructFieldInformation, TypeSignature, VariantDefinition, Visibility,
+};
+use move_binary_format::file_format_common::VERSION_MAX;
+use move_core_types::account_address::AccountAddress;
+use move_core_types::identifier::Identifier;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use sui_types::base_types::{ObjectID, SequenceNumber};
+use sui_types::move_package::{MovePackage, UpgradeInfo};
+use sui_types::SUI_FRAMEWORK_ADDRESS;
+
+use crate::bcs_json::BcsJsonSchema;
+use crate::common_types::MovePackageWithMetadata;
+use crate::metadata::PackageMetadata;
+use crate::package_id_io::PackagesDir;
+
+// an empty directory under the system temp dir, name has to be unique across tests
+pub(crate) fn test_dir(name: &str) -> PathBuf {
+    let dir =
+        std::env::temp_dir().join(format!("sui-package-utils-{}-{}", std::process::id(), name));
+    if dir.exists() {
+        fs::remove_dir_all(&dir).unwrap();
+    }
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+// a package at the address of its modules, without type origins or linkage
+pub(crate) fn test_package(modules: &[CompiledModule], version: u64) -> MovePackage {
+    let module_map = modules
+        .iter()
+        .map(|module| {
+            let mut module_bytes = Vec::new();
+            module
+                .serialize_with_version(module.version, &mut module_bytes)
+                .unwrap();
+            (module.name().to_string(), module_bytes)
+        })
+        .collect();
+    MovePackage::new(
+        ObjectID::from(*modules[0].address()),
+        SequenceNumber::from_u64(version),
+        module_map,
+        u64::MAX,
+        vec![],
+        BTreeMap::new(),
+    )
+    .unwrap()
+}
+
+// a package at storage id `id`, with linkage table entries original id -> (upgraded id, version)
+pub(crate) fn test_upgraded_package(
+    id: &str,
+    modules: &[CompiledModule],
+    version: u64,
+    linkage: &[(&str, &str, u64)],
+) -> MovePackage {
+    let package = test_package(modules, version);
+    let linkage_table = linkage
+        .iter()
+        .map(|(original_id, upgraded_id, upgraded_version)| {
+            (
+                ObjectID::from_hex_literal(original_id).unwrap(),
+                UpgradeInfo {
+                    upgraded_id: ObjectID::from_hex_literal(upgraded_id).unwrap(),
+                    upgraded_version: SequenceNumber::from_u64(*upgraded_version),
+                },
+            )
+        })
+        .collect();
+    MovePackage::new(
+        ObjectID::from_hex_literal(id).unwrap(),
+        package.version(),
+        package.serialized_module_map().clone(),
+        u64::MAX,
+        vec![],
+        linkage_table,
+    )
+    .unwrap()
+}
+
+// one module without references to other packages, at version 1
+pub(crate) fn test_package_with_metadata() -> MovePackageWithMetadata {
+    let mut builder = TestModuleBuilder::new();
+    builder.add_function("mint", 0, &[("test", "new", vec![])]);
+    builder.add_function("new", 0, &[]);
+    MovePackageWithMetadata {
+        package: test_package(&[builder.build()], 1),
+        checkpoint: 42,
+        transaction_digest: "4uJmWHzGFm2ZZKjbi6vq5eZnKpNFnFXZfNCMrP5XWXP7".to_string(),
+        sender: Some(
+            "0x00000000000000000000000000000000000000000000000000000000000000ab".to_string(),
+        ),
+    }
+}
+
+// writes bcs.json and metadata.json only, the way a store would have them
+pub(crate) fn write_test_package(
+    packages_dir: &PackagesDir,
+    pkg_with_metadata: &MovePackageWithMetadata,
+) {
+    let id = pkg_with_metadata.package.id().to_canonical_string(true);
+    let package_dir = packages_dir.get_package_dir(&id);
+    fs::create_dir_all(&package_dir).unwrap();
+    fs::write(
+        format!("{}/bcs.json", package_dir),
+        serde_json::to_string_pretty(&BcsJsonSchema::from(&pkg_with_metadata.package)).unwrap(),
+    )
+    .unwrap();
+    fs::write(
+        format!("{}/metadata.json", package_dir),
+        serde_json::to_string_pretty(&PackageMetadata::from(pkg_with_metadata)).unwrap(),
+    )
+    .unwrap();
+}
+
+// hand-built modules for unit tests. Functions do nothing but call other functions, and nothing
+// is verified
+pub(crate) struct TestModuleBuilder {
+    module: CompiledModule,
+}
+
+impl TestModuleBuilder {
+    // module test at 0x0
+    pub(crate) fn new() -> Self {
+        Self::at(AccountAddress::ZERO, "test")
+    }
+
+    pub(crate) fn at(address: AccountAddress, name: &str) -> Self {
+        let mut builder = TestModuleBuilder {
+            module: CompiledModule {
+                version: VERSION_MAX,
+                address_identifiers: vec![address],
+                signatures: vec![Signature(vec![])],
+                ..Default::default()
+            },
+        };
+        let name = builder.identifier(name);
+        builder.module.module_handles.push(ModuleHandle {
+            address: AddressIdentifierIndex(0),
+            name,
+        });
+        builder.module.self_module_handle_idx = ModuleHandleIndex(0);
+        builder
+    }
+
+    // a struct defined in this module, returns its type for use in fields and type arguments
+    pub(crate) fn add_struct(
+        &mut self,
+        name: &str,
+        abilities: AbilitySet,
+        type_parameters: Vec<DatatypeTyParameter>,
+        fields: &[(&str, SignatureToken)],
+    ) -> SignatureToken {
+        let struct_handle = self.datatype_handle(name, abilities, type_parameters);
+        let fields = self.fields(fields);
+        self.module.struct_defs.push(StructDefinition {
+            struct_handle,
+            field_information: StructFieldInformation::Declared(fields),
+        });
+        SignatureToken::Datatype(struct_handle)
+    }
+
+    // an enum defined in this module with the given variants in order, see add_struct
+    pub(crate) fn add_enum(
+        &mut self,
+        name: &str,
+        abilities: AbilitySet,
+        type_parameters: Vec<DatatypeTyParameter>,
+        variants: &[(&str, Vec<(&str, SignatureToken)>)],
+    ) -> SignatureToken {
+        let enum_handle = self.datatype_handle(name, abilities, type_parameters);
+        let variants = variants
+            .iter()
+            .map(|(variant_name, fields)| VariantDefinition {
+                variant_name: self.identifier(variant_name),
+                fields: self.fields(fields),
+            })
+            .collect();
+        self.module.enum_defs.push(EnumDefinition {
+            enum_handle,
+            variants,
+        });
+        SignatureToken::Datatype(enum_handle)
+    }
+
+    // declares <address>::<module> a friend of this module
+    pub(crate) fn add_friend(&mut self, module_name: &str) {
+        let (address, name) = module_name.split_once("::").unwrap();
+        let friend = ModuleHandle {
+            address: self.address_identifier(AccountAddress::from_hex_literal(address).unwrap()),
+            name: self.identifier(name),
+        };
+        self.module.friend_decls.push(friend);
+    }
+
+    // a public function, see add_function_def
+    pub(crate) fn add_function(
+        &mut self,
+        name: &str,
+        type_parameters: usize,
+        calls: &[(&str, &str, Vec<SignatureToken>)],
+    ) {
+        self.add_function_def(name, Visibility::Public, false, type_parameters, calls);
+    }
+
+    // a function calling each of the given functions in order, with the given type arguments.
+    // Callee modules are this module's name, <address>::<module> or the name of a 0x2 module
+    pub(crate) fn add_function_def(
+        &mut self,
+        name: &str,
+        visibility: Visibility,
+        is_entry: bool,
+        type_parameters: usize,
+        calls: &[(&str, &str, Vec<SignatureToken>)],
+    ) {
+        let handle = self.function_handle(ModuleHandleIndex(0), name, type_parameters);
+        let mut code = Vec::new();
+        for (module_name, function_name, type_arguments) in calls {
+            let module = self.module_handle(module_name);
+            let callee = self.function_handle(module, function_name, type_arguments.len());
+            if type_arguments.is_empty() {
+                code.push(Bytecode::Call(callee));
+                continue;
+            }
+            let type_parameters = self.signature(type_arguments.clone());
+            self.module
+                .function_instantiations
+                .push(FunctionInstantiation {
+                    handle: callee,
+                    type_parameters,
+                });
+            code.push(Bytecode::CallGeneric(FunctionInstantiationIndex(
+                (self.module.function_instantiations.len() - 1) as u16,
+            )));
+        }
+        code.push(Bytecode::Ret);
+        self.module.function_defs.push(FunctionDefinition {
+            function: handle,
+            visibility,
+            is_entry,
+            acquires_global_resources: vec![],
+            code: Some(CodeUnit {
+                locals: SignatureIndex(0),
+                code,
+                jump_tables: vec![],
+            }),
+        });
+    }
+
+    pub(crate) fn build(self) -> CompiledModule {
+        self.module
+    }
+
+    fn identifier(&mut self, name: &str) -> IdentifierIndex {
+        let identifier = Identifier::new(name).unwrap();
+        let index = match self
+            .module
+            .identifiers
+            .iter()
+            .position(|i| *i == identifier)
+        {
+            Some(index) => index,
+            None => {
+                self.module.identifiers.push(identifier);
+                self.module.identifiers.len() - 1
+            }
+        };
+        IdentifierIndex(index as u16)
+    }
+
+    fn datatype_handle(
+        &mut self,
+        name: &str,
+        abilities: AbilitySet,
+        type_parameters: Vec<DatatypeTyParameter>,
+    ) -> DatatypeHandleIndex {
+        let name = self.identifier(name);
+        self.module.datatype_handles.push(DatatypeHandle {
+            module: ModuleHandleIndex(0),
+            name,
+            abilities,
+            type_parameters,
+        });
+        DatatypeHandleIndex((self.module.datatype_handles.len() - 1) as u16)
+    }
+
+    fn fields(&mut self, fields: &[(&str, SignatureToken)]) -> Vec<FieldDefinition> {
+        fields
+            .iter()
+            .map(|(name, signature)| FieldDefinition {
+                name: self.identifier(name),
+                signature: TypeSignature(signature.clone()),
+            })
+            .collect()
+    }
+
+    fn address_identifier(&mut self, address: AccountAddress) -> AddressIdentifierIndex {
+        let index = match self
+            .module
+            .address_identifiers
+            .iter()
+            .position(|a| *a == address)
+        {
+            Some(index) => index,
+            None => {
+                self.module.address_identifiers.push(address);
+                self.module.address_identifiers.len() - 1
+            }
+        };
+        AddressIdentifierIndex(index as u16)
+    }
+
+    fn signature(&mut self, tokens: Vec<SignatureToken>) -> SignatureIndex {
+        let signature = Signature(tokens);
+        let index = match self.module.signatures.iter().position(|s| *s == signature) {
+            Some(index) => index,
+            None => {
+                self.module.signatures.push(signature);
+                self.module.signatures.len() - 1
+            }
+        };
+        SignatureIndex(index as u16)
+    }
+
+    fn module_handle(&mut self, module_name: &str) -> ModuleHandleIndex {
+        if module_name == self.module.name().as_str() {
+            return ModuleHandleIndex(0);
+        }
+        let (address, name) = match module_name.split_once("::") {
+            Some((address, name)) => (AccountAddress::from_hex_literal(address).unwrap(), name),
+            None => (SUI_FRAMEWORK_ADDRESS, module_name),
+        };
+        let module_handle = ModuleHandle {
+            address: self.address_identifier(address),
+            name: self.identifier(name),
+        };
+        let index = match self
+            .module
+            .module_handles
+            .iter()
+            .position(|m| *m == module_handle)
+        {
+            Some(index) => index,
+            None => {
+                self.module.module_handles.push(module_handle);
+                self.module.module_handles.len() - 1
+            }
+        };
+        ModuleHandleIndex(index as u16)
+    }
+
+    fn function_handle(
+        &mut self,
+        module: ModuleHandleIndex,
+        name: &str,
+        type_parameters: usize,
+    ) -> FunctionHandleIndex {
+        let function_handle = FunctionHandle {
+            module,
+            name: self.identifier(name),
+            parameters: SignatureIndex(0),
+            return_: SignatureIndex(0),
+            type_parameters: vec![AbilitySet::EMPTY; type_parameters],
+        };
+        let index = match self
+            .module
+            .function_handles
+            .iter()
+            .position(|f| *f == function_handle)
+        {
+            Some(index) => index,
+            None => {
+                self.module.function_handles.push(function_handle);
+                self.module.function_handles.len() - 1
+            }
+        };
+        FunctionHandleIndex(index as u16)
+    }
+}